@@ -57,7 +57,8 @@ fn easy_fs_pack() -> std::io::Result<()> {
             .write(true)
             .create(true)
             .open(format!("{}{}", target_path, "fs.img"))?;
-        f.set_len(16 * 2048 * 512).unwrap();
+        // 16MiB file system followed by a 16MiB swap area used by the kernel
+        f.set_len((16 * 2048 + 32 * 1024) * 512).unwrap();
         f
     })));
     // 16MiB, at most 4095 files
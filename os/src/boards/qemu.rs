@@ -1,7 +1,8 @@
 //! Constants used in rCore for qemu
 
 pub const CLOCK_FREQ: usize = 12500000;
-pub const MEMORY_END: usize = 0x801000000;
+/// 内核只管理前 16MiB 物理内存, 用户程序用尽之后由交换区补充
+pub const MEMORY_END: usize = 0x8100_0000;
 
 pub const MMIO: &[(usize, usize)] = &[
     (0x0010_0000, 0x00_2000), // VIRT_TEST/RTC  in virt machine
@@ -3,6 +3,8 @@
 pub const USER_STACK_SIZE: usize = 4096 * 2;
 pub const KERNEL_STACK_SIZE: usize = 4096 * 2;
 pub const KERNEL_HEAP_SIZE: usize = 0x20_0000;
+/// 支持的 hart 数量, 与`entry.asm`中启动栈的数量一致
+pub const MAX_HARTS: usize = 4;
 
 /// 页大小 4096 bytes
 pub const PAGE_SIZE: usize = 0x1000;
@@ -11,7 +13,25 @@ pub const PAGE_SIZE_BITS: usize = 0xc;
 
 /// 跳板的物理地址
 pub const TRAMPOLINE: usize = usize::MAX - PAGE_SIZE + 1;
-pub const TRAP_CONTEXT: usize = TRAMPOLINE - PAGE_SIZE;
+/// 信号处理函数返回时进入的跳板, 在 SV39 虚拟地址空间高半部分的开头, 用户态可以执行
+pub const SIGRETURN_TRAMPOLINE: usize = !(MMAP_TOP - 1);
+/// 0号线程的 Trap 上下文, 线程`tid`的 Trap 上下文在它下方`tid`个页
+pub const TRAP_CONTEXT_BASE: usize = TRAMPOLINE - PAGE_SIZE;
+
+/// mmap 未指定地址时, 从这里开始寻找空闲的虚拟地址
+pub const MMAP_BASE: usize = 0x1_0000_0000;
+/// 用户可以 mmap 的最高地址(SV39 虚拟地址空间的低半部分)
+pub const MMAP_TOP: usize = 1 << 38;
+/// 线程的用户栈从这里向下排列, 每个用户栈下方有一个保护页
+pub const USER_STACK_BASE: usize = MMAP_TOP;
+
+/// 交换区在块设备上的起始块号, 紧跟在 16MiB 的文件系统之后
+pub const SWAP_START_BLOCK: usize = 16 * 2048;
+/// 交换区能容纳的页数(16MiB)
+pub const SWAP_PAGES: usize = 4096;
+
+/// 调用无法识别的系统调用时, 除了返回`-ENOSYS`之外是否还向调用者发送 SIGSYS
+pub const SIGSYS_ON_UNKNOWN_SYSCALL: bool = false;
 
 /*
 #[cfg(feature = "board_k210")]
@@ -1,10 +1,8 @@
 use super::BlockDevice;
 use crate::mm::{
-    frame_alloc, frame_dealloc, kernel_token, FrameTracker, PageTable, PhysAddr, PhysPageNum,
-    VirtAddr,
+    frame_alloc_contiguous, kernel_token, FrameBlock, PageTable, PhysAddr, PhysPageNum, VirtAddr,
 };
-use crate::sync::UPSafeCell;
-use crate::util::range::StepByOne;
+use crate::sync::SpinLock;
 use alloc::vec::Vec;
 use lazy_static::*;
 use virtio_drivers::{Hal, VirtIOBlk, VirtIOHeader};
@@ -12,10 +10,10 @@ use virtio_drivers::{Hal, VirtIOBlk, VirtIOHeader};
 #[allow(unused)]
 const VIRTIO0: usize = 0x10001000;
 
-pub struct VirtIOBlock(UPSafeCell<VirtIOBlk<'static, VirtioHal>>);
+pub struct VirtIOBlock(SpinLock<VirtIOBlk<'static, VirtioHal>>);
 
 lazy_static! {
-    static ref QUEUE_FRAMES: UPSafeCell<Vec<FrameTracker>> = unsafe { UPSafeCell::new(Vec::new()) };
+    static ref QUEUE_FRAMES: SpinLock<Vec<FrameBlock>> = SpinLock::new(Vec::new());
 }
 
 impl BlockDevice for VirtIOBlock {
@@ -37,7 +35,7 @@ impl VirtIOBlock {
     #[allow(unused)]
     pub fn new() -> Self {
         unsafe {
-            Self(UPSafeCell::new(
+            Self(SpinLock::new(
                 VirtIOBlk::<VirtioHal>::new(&mut *(VIRTIO0 as *mut VirtIOHeader)).unwrap(),
             ))
         }
@@ -48,26 +46,20 @@ pub struct VirtioHal;
 
 impl Hal for VirtioHal {
     fn dma_alloc(pages: usize) -> usize {
-        let mut ppn_base = PhysPageNum(0);
-        for i in 0..pages {
-            let frame = frame_alloc().unwrap();
-            if i == 0 {
-                ppn_base = frame.ppn;
-            }
-            assert_eq!(frame.ppn.0, ppn_base.0 + i);
-            QUEUE_FRAMES.exclusive_access().push(frame);
-        }
-        let pa: PhysAddr = ppn_base.into();
+        // DMA buffers must be physically contiguous
+        let block = frame_alloc_contiguous(pages, 1).unwrap();
+        let pa: PhysAddr = block.ppn.into();
+        QUEUE_FRAMES.exclusive_access().push(block);
         pa.0
     }
 
-    fn dma_dealloc(pa: usize, pages: usize) -> i32 {
+    fn dma_dealloc(pa: usize, _pages: usize) -> i32 {
         let pa = PhysAddr::from(pa);
-        let mut ppn_base: PhysPageNum = pa.into();
-        for _ in 0..pages {
-            frame_dealloc(ppn_base);
-            ppn_base.step();
-        }
+        let ppn_base: PhysPageNum = pa.into();
+        // dropping the block returns it to the frame allocator
+        QUEUE_FRAMES
+            .exclusive_access()
+            .retain(|block| block.ppn != ppn_base);
         0
     }
 
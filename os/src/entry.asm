@@ -1,12 +1,26 @@
     .section .text.entry
     .globl _start
 _start:
+    # a0 = hartid, 每个 hart 使用自己的启动栈, 编号保存在 tp 中
+    mv tp, a0
     la sp, boot_stack_top
+    slli t0, a0, 16
+    sub sp, sp, t0
     call rust_main
 
+    # 其它 hart 被 SBI HSM 扩展启动后从这里开始执行
+    .globl _start_secondary
+_start_secondary:
+    mv tp, a0
+    la sp, boot_stack_top
+    slli t0, a0, 16
+    sub sp, sp, t0
+    call rust_main_secondary
+
     .section .bss.stack
     .globl boot_stack
 boot_stack:
-    .space 4096 * 16
+    # 4096 * 16 bytes for each of the MAX_HARTS harts
+    .space 4096 * 16 * 4
     .globl boot_stack_top
-boot_stack_top:
\ No newline at end of file
+boot_stack_top:
@@ -2,12 +2,12 @@
 //! we need to wrap `Inode` into `Arc`,but `Mutex` in `Inode` prevents
 //! file systems from being accessed simultaneously
 //!
-//! `UPSafeCell<OSInodeInner>` -> `OSInode`: for static `ROOT_INODE`,we
-//! need to wrap `OSInodeInner` into `UPSafeCell`
+//! `SpinLock<OSInodeInner>` -> `OSInode`: for static `ROOT_INODE`,we
+//! need to wrap `OSInodeInner` into `SpinLock`
 use super::File;
 use crate::drivers::BLOCK_DEVICE;
 use crate::mm::UserBuffer;
-use crate::sync::UPSafeCell;
+use crate::sync::SpinLock;
 use alloc::sync::Arc;
 use alloc::vec::Vec;
 use bitflags::*;
@@ -18,9 +18,9 @@ use lazy_static::*;
 pub struct OSInode {
     readable: bool,
     writable: bool,
-    inner: UPSafeCell<OSInodeInner>,
+    inner: SpinLock<OSInodeInner>,
 }
-/// The OS inode inner in 'UPSafeCell'
+/// The OS inode inner in 'SpinLock'
 pub struct OSInodeInner {
     offset: usize,
     inode: Arc<Inode>,
@@ -32,9 +32,13 @@ impl OSInode {
         Self {
             readable,
             writable,
-            inner: unsafe { UPSafeCell::new(OSInodeInner { offset: 0, inode }) },
+            inner: SpinLock::new(OSInodeInner { offset: 0, inode }),
         }
     }
+    /// The easy-fs inode behind this file
+    pub fn inode(&self) -> Arc<Inode> {
+        self.inner.exclusive_access().inode.clone()
+    }
     /// Read all data inside a inode into vector
     pub fn read_all(&self) -> Vec<u8> {
         let mut inner = self.inner.exclusive_access();
@@ -151,4 +155,7 @@ impl File for OSInode {
         }
         total_write_size
     }
+    fn backing_inode(&self) -> Option<Arc<Inode>> {
+        Some(self.inode())
+    }
 }
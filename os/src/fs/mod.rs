@@ -4,6 +4,8 @@ mod pipe;
 mod stdio;
 
 use crate::mm::UserBuffer;
+use alloc::sync::Arc;
+use easy_fs::Inode;
 /// File trait
 pub trait File: Send + Sync {
     /// If readable
@@ -14,8 +16,12 @@ pub trait File: Send + Sync {
     fn read(&self, buf: UserBuffer) -> usize;
     /// Write `UserBuffer` to file
     fn write(&self, buf: UserBuffer) -> usize;
+    /// The easy-fs inode backing this file, files without one can not be mmapped
+    fn backing_inode(&self) -> Option<Arc<Inode>> {
+        None
+    }
 }
 
 pub use inode::{list_apps, open_file, OSInode, OpenFlags};
 pub use pipe::{make_pipe, Pipe};
-pub use stdio::{Stdin, Stdout};
+pub use stdio::{poll_stdin, Stdin, Stdout};
@@ -1,10 +1,10 @@
 //! 管道的实现
 use super::File;
 use crate::mm::UserBuffer;
-use crate::sync::UPSafeCell;
+use crate::sync::SpinLock;
 use alloc::sync::{Arc, Weak};
 
-use crate::task::suspend_current_and_run_next;
+use crate::task::{block_current_and_run_next, current_has_interrupting_signal, WaitQueue};
 
 /// 管道结构体
 pub struct Pipe {
@@ -13,12 +13,12 @@ pub struct Pipe {
     /// 可写?
     writable: bool,
     /// 双端环形队列.
-    buffer: Arc<UPSafeCell<PipeRingBuffer>>,
+    buffer: Arc<SpinLock<PipeRingBuffer>>,
 }
 
 impl Pipe {
     /// 创建读端pipe
-    pub fn read_end_with_buffer(buffer: Arc<UPSafeCell<PipeRingBuffer>>) -> Self {
+    pub fn read_end_with_buffer(buffer: Arc<SpinLock<PipeRingBuffer>>) -> Self {
         Self {
             readable: true,
             writable: false,
@@ -26,7 +26,7 @@ impl Pipe {
         }
     }
     /// 创建写端pipe
-    pub fn write_end_with_buffer(buffer: Arc<UPSafeCell<PipeRingBuffer>>) -> Self {
+    pub fn write_end_with_buffer(buffer: Arc<SpinLock<PipeRingBuffer>>) -> Self {
         Self {
             readable: false,
             writable: true,
@@ -35,6 +35,15 @@ impl Pipe {
     }
 }
 
+impl Drop for Pipe {
+    fn drop(&mut self) {
+        // readers blocked on an empty pipe see the end of file now
+        if self.writable {
+            self.buffer.exclusive_access().read_wait.wake_all();
+        }
+    }
+}
+
 /// 队列大小
 const RING_BUFFER_SIZE: usize = 32;
 
@@ -61,6 +70,10 @@ pub struct PipeRingBuffer {
     status: RingBufferStatus,
     /// 写端的弱引用
     write_end: Option<Weak<Pipe>>,
+    /// 等待数据的读者
+    read_wait: WaitQueue,
+    /// 等待空间的写者
+    write_wait: WaitQueue,
 }
 
 impl PipeRingBuffer {
@@ -72,6 +85,8 @@ impl PipeRingBuffer {
             tail: 0,
             status: RingBufferStatus::Empty,
             write_end: None,
+            read_wait: WaitQueue::new(),
+            write_wait: WaitQueue::new(),
         }
     }
     /// 设置写端
@@ -123,7 +138,7 @@ impl PipeRingBuffer {
 
 /// Return (read_end, write_end)
 pub fn make_pipe() -> (Arc<Pipe>, Arc<Pipe>) {
-    let buffer = Arc::new(unsafe { UPSafeCell::new(PipeRingBuffer::new()) });
+    let buffer = Arc::new(SpinLock::new(PipeRingBuffer::new()));
     let read_end = Arc::new(Pipe::read_end_with_buffer(buffer.clone()));
     let write_end = Arc::new(Pipe::write_end_with_buffer(buffer.clone()));
     buffer.exclusive_access().set_write_end(&write_end);
@@ -148,11 +163,13 @@ impl File for Pipe {
             let mut ring_buffer = self.buffer.exclusive_access();
             let loop_read = ring_buffer.available_read();
             if loop_read == 0 {
-                if ring_buffer.all_write_ends_closed() {
+                if ring_buffer.all_write_ends_closed() || current_has_interrupting_signal() {
                     return read_size;
                 }
+                // wait for a writer or the last write end to be closed
+                ring_buffer.read_wait.add_current();
                 drop(ring_buffer);
-                suspend_current_and_run_next();
+                block_current_and_run_next();
                 continue;
             }
             // read at most loop_read bytes
@@ -163,9 +180,11 @@ impl File for Pipe {
                     }
                     read_size += 1;
                 } else {
+                    ring_buffer.write_wait.wake_all();
                     return read_size;
                 }
             }
+            ring_buffer.write_wait.wake_all();
         }
     }
     /// 从 `buf` 中读取, 写入 `ring_buffer`中
@@ -177,8 +196,13 @@ impl File for Pipe {
             let mut ring_buffer = self.buffer.exclusive_access();
             let loop_write = ring_buffer.available_write();
             if loop_write == 0 {
+                if current_has_interrupting_signal() {
+                    return write_size;
+                }
+                // wait for a reader
+                ring_buffer.write_wait.add_current();
                 drop(ring_buffer);
-                suspend_current_and_run_next();
+                block_current_and_run_next();
                 continue;
             }
             // write at most loop_write bytes
@@ -187,9 +211,11 @@ impl File for Pipe {
                     ring_buffer.write_byte(unsafe { *byte_ref });
                     write_size += 1;
                 } else {
+                    ring_buffer.read_wait.wake_all();
                     return write_size;
                 }
             }
+            ring_buffer.read_wait.wake_all();
         }
     }
 }
@@ -2,12 +2,50 @@
 use super::File;
 use crate::mm::UserBuffer;
 use crate::sbi::console_getchar;
-use crate::task::{current_add_signal, suspend_current_and_run_next, SignalFlags};
+use crate::sync::SpinLock;
+use crate::task::{
+    block_current_and_run_next, current_add_signal, current_has_interrupting_signal, SignalFlags,
+    SignalInfo, WaitQueue, SI_KERNEL,
+};
+use alloc::collections::VecDeque;
+use lazy_static::*;
 ///Standard input
 pub struct Stdin;
 ///Standard output
 pub struct Stdout;
 
+/// 已经从串口读到但还没有被读走的字符, 以及等待输入的任务
+struct StdinBuffer {
+    chars: VecDeque<u8>,
+    wait_queue: WaitQueue,
+}
+
+lazy_static! {
+    static ref STDIN_BUFFER: SpinLock<StdinBuffer> = SpinLock::new(StdinBuffer {
+        chars: VecDeque::new(),
+        wait_queue: WaitQueue::new(),
+    });
+}
+
+/// 读入串口上所有已经到达的字符, 唤醒等待输入的任务.
+/// 串口没有接中断, 在时钟中断和处理器空闲时轮询
+pub fn poll_stdin() {
+    let mut stdin = STDIN_BUFFER.exclusive_access();
+    let mut received = false;
+    loop {
+        let c = console_getchar();
+        // 没有输入时 SBI 返回 0 或 -1
+        if c == 0 || c > u8::MAX as usize {
+            break;
+        }
+        stdin.chars.push_back(c as u8);
+        received = true;
+    }
+    if received {
+        stdin.wait_queue.wake_all();
+    }
+}
+
 impl File for Stdin {
     fn readable(&self) -> bool {
         true
@@ -17,23 +55,25 @@ impl File for Stdin {
     }
     fn read(&self, mut user_buf: UserBuffer) -> usize {
         assert_eq!(user_buf.len(), 1);
-        // busy loop
-        let mut c: usize;
-        loop {
-            c = console_getchar();
-            if c == 0 {
-                suspend_current_and_run_next();
-                continue;
-            } else if c == 3 {
-                // 3 is ctrl_c
-                //println!("[K] os/fs/stdio/read: Got Ctrl_C");
-                current_add_signal(SignalFlags::SIGINT);
-                break;
-            } else {
-                break;
+        let ch = loop {
+            poll_stdin();
+            let mut stdin = STDIN_BUFFER.exclusive_access();
+            if let Some(ch) = stdin.chars.pop_front() {
+                break ch;
+            }
+            if current_has_interrupting_signal() {
+                return 0;
             }
+            // wait for the console
+            stdin.wait_queue.add_current();
+            drop(stdin);
+            block_current_and_run_next();
+        };
+        if ch == 3 {
+            // 3 is ctrl_c
+            //println!("[K] os/fs/stdio/read: Got Ctrl_C");
+            current_add_signal(SignalInfo::new(SignalFlags::SIGINT, SI_KERNEL));
         }
-        let ch = c as u8;
         unsafe {
             user_buf.buffers[0].as_mut_ptr().write_volatile(ch);
         }
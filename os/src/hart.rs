@@ -0,0 +1,32 @@
+//! hart(RISC-V 的硬件线程)相关的功能
+//!
+//! 每个 hart 在`entry.asm`中把自己的编号保存在`tp`寄存器中, 内核运行期间不会改变它.
+//! 进入用户态时`tp`被保存在 Trap 上下文中, 回到内核时恢复
+
+use crate::config::MAX_HARTS;
+use crate::sbi::hart_start;
+use core::arch::asm;
+
+/// 当前 hart 的编号
+#[inline(always)]
+pub fn hart_id() -> usize {
+    let hart_id;
+    unsafe {
+        asm!("mv {}, tp", out(reg) hart_id);
+    }
+    hart_id
+}
+
+/// 通过 SBI HSM 扩展启动除了`boot_hart`之外的 hart, 它们从`_start_secondary`开始执行.
+/// 不存在的 hart 会启动失败, 忽略
+pub fn start_secondary_harts(boot_hart: usize) {
+    extern "C" {
+        fn _start_secondary();
+    }
+    for hart in (0..MAX_HARTS).filter(|hart| *hart != boot_hart) {
+        let ret = hart_start(hart, _start_secondary as usize, 0);
+        if ret != 0 {
+            debug!("[kernel] failed to start hart {}: {}", hart, ret);
+        }
+    }
+}
@@ -9,6 +9,7 @@
 //! - [`mm`]: Address map using SV39
 //! - [`sync`]: Wrap a static data structure inside it so that we are able to access it without any `unsafe`.
 //! - [`fs`]: Separate user from file system with some structures
+//! - [`hart`]: 多个 hart 的启动和编号
 //!
 //! The operating system also starts in this module. Kernel code starts
 //! executing from `entry.asm`, after which [`rust_main()`] is called to
@@ -16,7 +17,8 @@
 //! details.)
 //!
 //! We then call [`task::run_tasks()`] and for the first time go to
-//! userspace.
+//! userspace. After initialization the boot hart starts the other harts, which
+//! enter [`rust_main_secondary()`] and run [`task::run_tasks()`] as well.
 
 #![deny(missing_docs)]
 #![deny(warnings)]
@@ -45,6 +47,7 @@ mod logging;
 mod config;
 mod drivers;
 pub mod fs;
+pub mod hart;
 pub mod lang_items;
 pub mod mm;
 pub mod sbi;
@@ -58,9 +61,9 @@ pub mod util;
 use core::arch::global_asm;
 global_asm!(include_str!("entry.asm"));
 
-/// rust os enter function
+/// rust os enter function, 只有启动 hart 执行
 #[no_mangle]
-pub fn rust_main() -> ! {
+pub fn rust_main(hart_id: usize) -> ! {
     clear_bss();
     logging::init();
     debug!("Hello, world!");
@@ -73,10 +76,23 @@ pub fn rust_main() -> ! {
     timer::set_next_trigger();
     fs::list_apps();
     task::add_initproc();
+    hart::start_secondary_harts(hart_id);
     task::run_tasks();
     panic!("Unreachable in rust_main!");
 }
 
+/// 其它 hart 的入口, 内核已经由启动 hart 初始化完毕
+#[no_mangle]
+pub fn rust_main_secondary(hart_id: usize) -> ! {
+    mm::init_secondary();
+    trap::init();
+    trap::enable_timer_interrupt();
+    timer::set_next_trigger();
+    debug!("hart {} started", hart_id);
+    task::run_tasks();
+    panic!("Unreachable in rust_main_secondary!");
+}
+
 fn clear_bss() {
     extern "C" {
         fn sbss();
@@ -2,7 +2,8 @@
 
 use crate::config::MEMORY_END;
 use crate::mm::address::{PhysAddr, PhysPageNum};
-use crate::sync::UPSafeCell;
+use crate::sync::SpinLock;
+use alloc::collections::{BTreeMap, BTreeSet};
 use alloc::vec::Vec;
 use core::fmt;
 use core::fmt::{Debug, Formatter};
@@ -25,6 +26,12 @@ impl FrameTracker {
         }
         Self { ppn }
     }
+    /// 与`self`共享同一个物理页(写时复制), 页的引用计数加1, 内容不清零
+    pub fn share(&self) -> Self {
+        trace!("share frame tracker {:?}", self.ppn);
+        frame_add_ref(self.ppn);
+        Self { ppn: self.ppn }
+    }
 }
 
 /// Debugging
@@ -34,11 +41,55 @@ impl Debug for FrameTracker {
     }
 }
 
-/// 实现drop方法, tracker被回收时自动调用frame_dealloc,回收物理页
+/// 实现drop方法, 最后一个tracker被回收时自动调用frame_dealloc,回收物理页
 impl Drop for FrameTracker {
     fn drop(&mut self) {
-        trace!("dealloc frame tracker {:?}", self.ppn);
-        frame_dealloc(self.ppn);
+        if frame_dec_ref(self.ppn) == 0 {
+            trace!("dealloc frame tracker {:?}", self.ppn);
+            frame_dealloc(self.ppn);
+        }
+    }
+}
+
+/// 一块连续的物理页, 整块申请整块释放
+pub struct FrameBlock {
+    /// 起始物理页号, 按块的大小对齐
+    pub ppn: PhysPageNum,
+    /// 块的阶数, 块大小为`1 << order`页
+    pub order: usize,
+}
+
+impl FrameBlock {
+    /// 初始化块, 把内存清零
+    fn new(ppn: PhysPageNum, order: usize) -> Self {
+        trace!("alloc a new frame block {:?}, order {}", ppn, order);
+        for i in 0..1 << order {
+            PhysPageNum(ppn.0 + i).get_bytes_array().fill(0);
+        }
+        Self { ppn, order }
+    }
+    /// 块包含的页数
+    pub fn pages(&self) -> usize {
+        1 << self.order
+    }
+}
+
+impl Debug for FrameBlock {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_fmt(format_args!(
+            "FrameBlock:PPN={:#x},order={}",
+            self.ppn.0, self.order
+        ))
+    }
+}
+
+/// 块被回收时一次性还给伙伴系统
+impl Drop for FrameBlock {
+    fn drop(&mut self) {
+        trace!("dealloc frame block {:?}, order {}", self.ppn, self.order);
+        FRAME_ALLOCATOR
+            .exclusive_access()
+            .dealloc_contiguous(self.ppn, self.order);
     }
 }
 
@@ -47,74 +98,117 @@ trait FrameAllocator {
     fn new() -> Self;
     /// 申请一页物理地址
     fn alloc(&mut self) -> Option<PhysPageNum>;
+    /// 申请`1 << order`页连续的物理地址, 起始页号按块的大小对齐
+    fn alloc_contiguous(&mut self, order: usize) -> Option<PhysPageNum>;
     /// 释放一页物理地址
     fn dealloc(&mut self, ppn: PhysPageNum);
+    /// 释放[`FrameAllocator::alloc_contiguous`]申请的块
+    fn dealloc_contiguous(&mut self, ppn: PhysPageNum, order: usize);
 }
 
-/// 栈式物理页帧管理策略
-pub struct StackFrameAllocator {
-    // 空闲内存的起始物理页号
-    current: usize,
-    // 空闲内存的结束物理页号
+/// 伙伴系统的最大阶数, 最大的块为`1 << (MAX_ORDER - 1)`页
+const MAX_ORDER: usize = 32;
+
+/// 伙伴系统物理页帧管理策略
+///
+/// 空闲内存被划分为按自身大小对齐的2的幂页大小的块, 第k阶的块大小为`1 << k`页.
+/// 申请时从足够大的块中切出需要的部分, 释放时与同阶的空闲伙伴块合并
+pub struct BuddyFrameAllocator {
+    // 管理的物理页号范围
+    start: usize,
     end: usize,
-    // 回收列表
-    recycled: Vec<usize>,
+    // 每一阶的空闲块的起始物理页号
+    free_lists: Vec<BTreeSet<usize>>,
 }
 
-impl StackFrameAllocator {
+impl BuddyFrameAllocator {
     pub fn init(&mut self, l: PhysPageNum, r: PhysPageNum) {
         info!("frame memory left {:?}, right {:?}", l, r);
-        self.current = l.0;
+        self.start = l.0;
         self.end = r.0;
-        info!("last {} Physical Frames.", self.end - self.current);
+        self.free_range(l.0, r.0);
+        info!("last {} Physical Frames.", self.end - self.start);
+    }
+    /// 把`[l, r)`拆成尽可能大的对齐的块放回空闲链表
+    fn free_range(&mut self, mut l: usize, r: usize) {
+        while l < r {
+            let mut order = (l.trailing_zeros() as usize).min(MAX_ORDER - 1);
+            while l + (1 << order) > r {
+                order -= 1;
+            }
+            self.free_block(l, order);
+            l += 1 << order;
+        }
+    }
+    /// 释放起始页号为`ppn`的`order`阶的块, 并与空闲的伙伴块合并
+    fn free_block(&mut self, mut ppn: usize, mut order: usize) {
+        while order < MAX_ORDER - 1 && self.free_lists[order].remove(&(ppn ^ (1 << order))) {
+            ppn &= !(1 << order);
+            order += 1;
+        }
+        self.free_lists[order].insert(ppn);
+    }
+    /// `ppn`是否在某个空闲块中
+    fn is_free(&self, ppn: usize) -> bool {
+        (0..MAX_ORDER).any(|order| self.free_lists[order].contains(&(ppn & !((1 << order) - 1))))
     }
 }
 
-impl FrameAllocator for StackFrameAllocator {
+impl FrameAllocator for BuddyFrameAllocator {
     fn new() -> Self {
         Self {
-            current: 0,
+            start: 0,
             end: 0,
-            recycled: Vec::new(),
+            free_lists: (0..MAX_ORDER).map(|_| BTreeSet::new()).collect(),
         }
     }
 
     fn alloc(&mut self) -> Option<PhysPageNum> {
-        if let Some(ppn) = self.recycled.pop() {
-            // 优先从回收列表里pop一个
-            Some(ppn.into())
-        } else {
-            // 回收列表里没有检查还有没有内存
-            if self.current == self.end {
-                None
-            } else {
-                // 还有从头部取一个
-                self.current += 1;
-                Some((self.current - 1).into())
-            }
+        self.alloc_contiguous(0)
+    }
+
+    fn alloc_contiguous(&mut self, order: usize) -> Option<PhysPageNum> {
+        // 找到第一个有空闲块的阶, 把大块一分为二直到需要的阶
+        let mut current = (order..MAX_ORDER).find(|o| !self.free_lists[*o].is_empty())?;
+        let ppn = *self.free_lists[current].iter().next().unwrap();
+        self.free_lists[current].remove(&ppn);
+        while current > order {
+            current -= 1;
+            self.free_lists[current].insert(ppn + (1 << current));
         }
+        Some(ppn.into())
     }
 
     fn dealloc(&mut self, ppn: PhysPageNum) {
+        self.dealloc_contiguous(ppn, 0);
+    }
+
+    fn dealloc_contiguous(&mut self, ppn: PhysPageNum, order: usize) {
         let ppn = ppn.0;
         // validity check
-        if ppn >= self.current || self.recycled.iter().find(|&v| *v == ppn).is_some() {
+        if ppn < self.start
+            || ppn + (1 << order) > self.end
+            || ppn & ((1 << order) - 1) != 0
+            || (ppn..ppn + (1 << order)).any(|ppn| self.is_free(ppn))
+        {
             // 不符合alloc的内存条件啊.
-            panic!("Frame ppn={:#x} has not been allocated!", ppn);
+            panic!(
+                "Frame block ppn={:#x} order={} has not been allocated!",
+                ppn, order
+            );
         }
-        // recycle
-        self.recycled.push(ppn);
+        self.free_block(ppn, order);
     }
 }
 
-/// frame 申请器实现选择 栈式
-type FrameAllocatorImpl = StackFrameAllocator;
+/// frame 申请器实现选择 伙伴系统
+type FrameAllocatorImpl = BuddyFrameAllocator;
 
 lazy_static! {
     /// 全局对象
-    pub static ref FRAME_ALLOCATOR: UPSafeCell<FrameAllocatorImpl> = unsafe {
-        UPSafeCell::new(FrameAllocatorImpl::new())
-    };
+    pub static ref FRAME_ALLOCATOR: SpinLock<FrameAllocatorImpl> = SpinLock::new(FrameAllocatorImpl::new());
+    /// 被多个地址空间共享的物理页的引用计数, 不在表中的已分配物理页引用计数为1
+    static ref FRAME_REF_COUNTS: SpinLock<BTreeMap<usize, usize>> = SpinLock::new(BTreeMap::new());
 }
 
 /// 初始化 frame allocator
@@ -141,11 +235,58 @@ pub fn frame_alloc() -> Option<FrameTracker> {
         .map(|ppn| FrameTracker::new(ppn))
 }
 
+/// 申请至少`pages`页连续的frame, 起始物理页号按`align`页对齐(`align`为2的幂).
+///
+/// 返回满足大小和对齐要求的最小的伙伴块, 它被 drop 时整块释放
+pub fn frame_alloc_contiguous(pages: usize, align: usize) -> Option<FrameBlock> {
+    assert!(align.is_power_of_two());
+    // 块按自身大小对齐, 取满足大小和对齐要求的最小阶
+    let order = pages.max(align).next_power_of_two().trailing_zeros() as usize;
+    FRAME_ALLOCATOR
+        .exclusive_access()
+        .alloc_contiguous(order)
+        .map(|ppn| FrameBlock::new(ppn, order))
+}
+
 /// 释放一块frame
 pub fn frame_dealloc(ppn: PhysPageNum) {
     FRAME_ALLOCATOR.exclusive_access().dealloc(ppn);
 }
 
+/// 物理页`ppn`的引用计数
+pub fn frame_ref_count(ppn: PhysPageNum) -> usize {
+    FRAME_REF_COUNTS
+        .exclusive_access()
+        .get(&ppn.0)
+        .copied()
+        .unwrap_or(1)
+}
+
+/// 物理页`ppn`的引用计数加1
+fn frame_add_ref(ppn: PhysPageNum) {
+    *FRAME_REF_COUNTS
+        .exclusive_access()
+        .entry(ppn.0)
+        .or_insert(1) += 1;
+}
+
+/// 物理页`ppn`的引用计数减1, 返回剩余的引用计数
+fn frame_dec_ref(ppn: PhysPageNum) -> usize {
+    let mut ref_counts = FRAME_REF_COUNTS.exclusive_access();
+    match ref_counts.get_mut(&ppn.0) {
+        Some(count) if *count > 2 => {
+            *count -= 1;
+            *count
+        }
+        Some(_) => {
+            // 只剩一个引用, 回到默认状态
+            ref_counts.remove(&ppn.0);
+            1
+        }
+        None => 0,
+    }
+}
+
 // ----------------------- unit tests -----------------------
 
 #[allow(unused)]
@@ -163,5 +304,27 @@ pub fn frame_allocator_test() {
         v.push(frame);
     }
     drop(v);
+    let frame = frame_alloc().unwrap();
+    let shared = frame.share();
+    assert_eq!(frame_ref_count(frame.ppn), 2);
+    drop(frame);
+    assert_eq!(frame_ref_count(shared.ppn), 1);
+    drop(shared);
+    // 制造碎片后仍然可以申请到连续且对齐的物理页
+    let mut v: Vec<FrameTracker> = (0..64).map(|_| frame_alloc().unwrap()).collect();
+    let mut i = 0;
+    v.retain(|_| {
+        i += 1;
+        i % 2 == 0
+    });
+    let block = frame_alloc_contiguous(5, 8).unwrap();
+    debug!("{:?}", block);
+    assert_eq!(block.ppn.0 % 8, 0);
+    assert_eq!(block.pages(), 8);
+    // 整块释放后可以再次申请到同一个块
+    let ppn = block.ppn;
+    drop(block);
+    assert_eq!(frame_alloc_contiguous(8, 1).unwrap().ppn, ppn);
+    drop(v);
     debug!("frame_allocator_test passed!");
 }
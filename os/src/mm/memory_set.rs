@@ -1,16 +1,23 @@
 //! 地址空间 实现
 
-use crate::config::{MEMORY_END, MMIO, PAGE_SIZE, TRAMPOLINE, TRAP_CONTEXT, USER_STACK_SIZE};
+use crate::config::{
+    MEMORY_END, MMAP_BASE, MMAP_TOP, MMIO, PAGE_SIZE, SIGRETURN_TRAMPOLINE, TRAMPOLINE,
+};
 use crate::mm::address::{PhysAddr, PhysPageNum, VPNRange, VirtAddr, VirtPageNum};
-use crate::mm::frame_allocator::{frame_alloc, FrameTracker};
+use crate::mm::frame_allocator::{frame_alloc, frame_ref_count, FrameTracker};
 use crate::mm::page_table::{PTEFlags, PageTable};
+use crate::mm::swap::{swap_out, SwapSlot};
 use crate::mm::PageTableEntry;
-use crate::sync::UPSafeCell;
+use crate::sbi::remote_sfence_vma_all;
+use crate::sync::SpinLock;
+use crate::task::swap_out_any;
 use crate::util::range::StepByOne;
 use alloc::collections::BTreeMap;
 use alloc::sync::Arc;
 use alloc::vec::Vec;
 use core::arch::asm;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use easy_fs::Inode;
 use lazy_static::lazy_static;
 use riscv::register::satp;
 
@@ -25,61 +32,120 @@ extern "C" {
     fn ebss();
     fn ekernel();
     fn strampoline();
+    fn ssigreturn();
 }
 
 lazy_static! {
     /// global kernel space
-    pub static ref KERNEL_SPACE: Arc<UPSafeCell<MemorySet>> = Arc::new(
-        unsafe {
-            UPSafeCell::new(MemorySet::new_kernel())
-        }
+    pub static ref KERNEL_SPACE: Arc<SpinLock<MemorySet>> = Arc::new(
+        SpinLock::new(MemorySet::new_kernel())
     );
 }
 
+/// 解除映射或者降低权限之后刷新所有 hart 的 TLB:
+/// 同一个地址空间可能正被其它 hart 使用(内核地址空间, 或者同一个进程的其它线程)
+fn flush_tlb() {
+    remote_sfence_vma_all();
+}
+
+/// 内核地址空间的 token, 在[`activate_kernel_space`]中记录, 之后不再变化
+static KERNEL_TOKEN: AtomicUsize = AtomicUsize::new(0);
+
 ///Get kernelspace root ppn
+///
+/// 不对 KERNEL_SPACE 加锁: 持有它时申请物理页可能要换出页面, 块设备驱动需要用它翻译地址
 pub fn kernel_token() -> usize {
-    KERNEL_SPACE.exclusive_access().token()
+    KERNEL_TOKEN.load(Ordering::Relaxed)
+}
+
+/// 在当前 hart 上启用内核地址空间, 并记录它的 token
+pub fn activate_kernel_space() {
+    let kernel_space = KERNEL_SPACE.exclusive_access();
+    KERNEL_TOKEN.store(kernel_space.token(), Ordering::Relaxed);
+    kernel_space.activate();
+}
+
+/// 申请一个物理页, 物理内存不足时换出某个进程的页面
+fn frame_alloc_swapping() -> Option<FrameTracker> {
+    loop {
+        if let Some(frame) = frame_alloc() {
+            return Some(frame);
+        }
+        if !swap_out_any() {
+            return None;
+        }
+    }
 }
 
+/// 文件不是合法的 ELF 可执行文件
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BadElf;
+
 /// 地址空间
 pub struct MemorySet {
     /// 页表
     page_table: PageTable,
     /// 逻辑段列表
     areas: Vec<MapArea>,
+    /// 页面置换的时钟算法的指针, 下一次从这个页开始扫描
+    clock_hand: VirtPageNum,
+    /// 被固定的页和它们被固定的次数, 这些页不会被换出
+    pinned: BTreeMap<VirtPageNum, usize>,
 }
 
 impl MemorySet {
     /// 创建新的地址空间
     pub fn new_bare() -> Self {
+        let root = frame_alloc_swapping().expect("out of memory for page table");
         Self {
-            page_table: PageTable::new(),
+            page_table: PageTable::new(root),
             areas: Vec::new(),
+            clock_hand: VirtPageNum(0),
+            pinned: BTreeMap::new(),
         }
     }
     /// 获取页表的token
     pub fn token(&self) -> usize {
         self.page_table.token()
     }
-    /// 在当前地址空间插入一个新的逻辑段
-    fn push(&mut self, mut map_area: MapArea, data: Option<&[u8]>) {
-        map_area.map(&mut self.page_table);
+    /// 在当前地址空间插入一个新的逻辑段, 物理内存耗尽时 panic
+    fn push(&mut self, map_area: MapArea, data: Option<&[u8]>) {
+        assert!(self.try_push(map_area, data), "out of memory");
+    }
+    /// 在当前地址空间插入一个新的逻辑段, 物理内存耗尽时不插入并返回`false`
+    fn try_push(&mut self, map_area: MapArea, data: Option<&[u8]>) -> bool {
+        let (start, end) = (map_area.vpn_range.get_start(), map_area.vpn_range.get_end());
+        self.areas.push(map_area);
+        let idx = self.areas.len() - 1;
+        if !self.map_range(idx, start, end) {
+            self.areas.pop();
+            return false;
+        }
         if let Some(data) = data {
-            map_area.copy_data(&mut self.page_table, data);
+            self.areas[idx].copy_data(&mut self.page_table, data);
         }
-        self.areas.push(map_area);
+        true
     }
-    /// Assume that no conflicts.
-    pub fn insert_framed_area(
+    /// 插入一段惰性分配的逻辑段, 第一次访问时才分配物理页. Assume that no conflicts.
+    pub fn insert_lazy_area(
         &mut self,
         start_va: VirtAddr,
         end_va: VirtAddr,
         permission: MapPermission,
     ) {
-        self.push(
+        self.push(MapArea::new_lazy(start_va, end_va, permission, None), None);
+    }
+    /// Assume that no conflicts. 物理内存耗尽时返回`false`
+    pub fn insert_framed_area(
+        &mut self,
+        start_va: VirtAddr,
+        end_va: VirtAddr,
+        permission: MapPermission,
+    ) -> bool {
+        self.try_push(
             MapArea::new(start_va, end_va, MapType::Framed, permission),
             None,
-        );
+        )
     }
     ///Remove `MapArea` that starts with `start_vpn`
     pub fn remove_area_with_start_vpn(&mut self, start_vpn: VirtPageNum) {
@@ -91,6 +157,7 @@ impl MemorySet {
         {
             area.unmap(&mut self.page_table);
             self.areas.remove(idx);
+            flush_tlb();
         }
     }
     /// Mention that trampoline is not collected by areas.
@@ -101,6 +168,14 @@ impl MemorySet {
             PTEFlags::R | PTEFlags::X,
         );
     }
+    /// 映射用户态可以执行的信号返回跳板, 与跳板一样不属于任何逻辑段
+    fn map_sigreturn_trampoline(&mut self) {
+        self.page_table.map(
+            VirtAddr::from(SIGRETURN_TRAMPOLINE).into(),
+            PhysAddr::from(ssigreturn as usize).into(),
+            PTEFlags::R | PTEFlags::X | PTEFlags::U,
+        );
+    }
     /// 使用汇编指令 `sfence.vma` 刷新TLB
     pub fn activate(&self) {
         let token = self.page_table.token();
@@ -188,24 +263,35 @@ impl MemorySet {
         }
         memory_set
     }
-    /// Include sections in elf and trampoline and TrapContext and user stack,
-    /// also returns user_sp and entry point.
-    pub fn from_elf(elf_data: &[u8]) -> (Self, usize, usize) {
+    /// Include sections in elf and trampoline, also returns the bottom of heap and entry point.
+    /// 线程的用户栈和 Trap 上下文由线程自己映射.
+    ///
+    /// elf的各个段都是惰性分配的, 第一次访问时才从`elf_file`中读取数据.
+    /// 不是合法的 ELF 文件时返回[`BadElf`]
+    pub fn from_elf(elf_data: &[u8], elf_file: Arc<Inode>) -> Result<(Self, usize, usize), BadElf> {
+        let elf = xmas_elf::ElfFile::new(elf_data).map_err(|_| BadElf)?;
+        let elf_header = elf.header;
+        let magic = elf_header.pt1.magic;
+        if magic != [0x7f, 0x45, 0x4c, 0x46] {
+            return Err(BadElf);
+        }
         let mut memory_set = Self::new_bare();
         // map trampoline
         memory_set.map_trampoline();
+        memory_set.map_sigreturn_trampoline();
         // map program headers of elf, with U flag
-        let elf = xmas_elf::ElfFile::new(elf_data).unwrap();
-        let elf_header = elf.header;
-        let magic = elf_header.pt1.magic;
-        assert_eq!(magic, [0x7f, 0x45, 0x4c, 0x46], "invalid elf!");
         let ph_count = elf_header.pt2.ph_count();
         let mut max_end_vpn = VirtPageNum(0);
         for i in 0..ph_count {
-            let ph = elf.program_header(i).unwrap();
-            if ph.get_type().unwrap() == xmas_elf::program::Type::Load {
-                let start_va: VirtAddr = (ph.virtual_addr() as usize).into();
-                let end_va: VirtAddr = ((ph.virtual_addr() + ph.mem_size()) as usize).into();
+            let ph = elf.program_header(i).map_err(|_| BadElf)?;
+            if ph.get_type().map_err(|_| BadElf)? == xmas_elf::program::Type::Load {
+                let start = ph.virtual_addr() as usize;
+                let end = match start.checked_add(ph.mem_size() as usize) {
+                    Some(end) if end <= MMAP_TOP => end,
+                    _ => return Err(BadElf),
+                };
+                let start_va: VirtAddr = start.into();
+                let end_va: VirtAddr = end.into();
                 let mut map_perm = MapPermission::U;
                 let ph_flags = ph.flags();
                 if ph_flags.is_read() {
@@ -217,71 +303,508 @@ impl MemorySet {
                 if ph_flags.is_execute() {
                     map_perm |= MapPermission::X;
                 }
-                let map_area = MapArea::new(start_va, end_va, MapType::Framed, map_perm);
+                let map_file = MapFile {
+                    inode: elf_file.clone(),
+                    start: ph.virtual_addr() as usize,
+                    offset: ph.offset() as usize,
+                    len: ph.file_size() as usize,
+                    writable: false,
+                };
+                let map_area = MapArea::new_lazy(start_va, end_va, map_perm, Some(map_file));
                 max_end_vpn = map_area.vpn_range.get_end();
-                memory_set.push(
-                    map_area,
-                    Some(&elf.input[ph.offset() as usize..(ph.offset() + ph.file_size()) as usize]),
-                );
+                memory_set.push(map_area, None);
             }
         }
-        // map user stack with U flags
-        let max_end_va: VirtAddr = max_end_vpn.into();
-        let mut user_stack_bottom: usize = max_end_va.into();
-        // guard page
-        user_stack_bottom += PAGE_SIZE;
-        let user_stack_top = user_stack_bottom + USER_STACK_SIZE;
-        memory_set.push(
-            MapArea::new(
-                user_stack_bottom.into(),
-                user_stack_top.into(),
-                MapType::Framed,
-                MapPermission::R | MapPermission::W | MapPermission::U,
-            ),
+        // used in sbrk, the heap grows from the end of elf
+        let heap_bottom: VirtAddr = max_end_vpn.into();
+        let mut heap = MapArea::new_lazy(
+            heap_bottom,
+            heap_bottom,
+            MapPermission::R | MapPermission::W | MapPermission::U,
             None,
         );
-        // map TrapContext
-        memory_set.push(
-            MapArea::new(
-                TRAP_CONTEXT.into(),
-                TRAMPOLINE.into(),
-                MapType::Framed,
-                MapPermission::R | MapPermission::W,
-            ),
-            None,
-        );
-        (
+        heap.heap = true;
+        memory_set.push(heap, None);
+        Ok((
             memory_set,
-            user_stack_top,
+            heap_bottom.into(),
             elf.header.pt2.entry_point() as usize,
-        )
+        ))
     }
     ///Clone a same `MemorySet`
-    pub fn from_existed_user(user_space: &Self) -> Self {
+    ///
+    /// 用户可访问的逻辑段采用写时复制: 父子进程共享物理页, 可写页在双方的页表中
+    /// 都改为只读, 直到某一方第一次写入时才在 [`MemorySet::handle_page_fault`] 中复制
+    ///
+    /// 物理内存耗尽时返回`None`
+    pub fn from_existed_user(user_space: &mut Self) -> Option<Self> {
         let mut memory_set = Self::new_bare();
         // map trampoline
         memory_set.map_trampoline();
-        // copy data sections/trap_context/user_stack
-        for area in user_space.areas.iter() {
-            let new_area = MapArea::from_another(area);
-            memory_set.push(new_area, None);
+        memory_set.map_sigreturn_trampoline();
+        // share data sections/user_stack, copy trap_context
+        for idx in 0..user_space.areas.len() {
+            // 共享映射: 父子进程共享同一组物理页, 尚未分配的页先分配好, 否则双方会各自分配
+            if user_space.areas[idx].shared && !user_space.populate(idx) {
+                return None;
+            }
+            let area = &user_space.areas[idx];
+            let mut new_area = MapArea::from_another(area);
+            if area.shared || area.is_cow() {
+                let pte_flags = if area.shared {
+                    PTEFlags::from_bits(area.map_perm.bits).unwrap()
+                } else {
+                    area.cow_pte_flags()
+                };
+                for vpn in area.data_frames.keys() {
+                    if !memory_set.prepare_pte(*vpn) {
+                        return None;
+                    }
+                }
+                for (vpn, frame) in area.data_frames.iter() {
+                    if !area.shared {
+                        user_space.page_table.remap(*vpn, frame.ppn, pte_flags);
+                    }
+                    memory_set.page_table.map(*vpn, frame.ppn, pte_flags);
+                    new_area.data_frames.insert(*vpn, frame.share());
+                }
+                // 换出到交换区的页面同样共享, 换入时各自复制到新的物理页
+                for (vpn, slot) in area.swapped.iter() {
+                    new_area.swapped.insert(*vpn, slot.clone());
+                }
+                memory_set.areas.push(new_area);
+                continue;
+            }
+            if !memory_set.try_push(new_area, None) {
+                return None;
+            }
             // copy data from another space
             for vpn in area.vpn_range {
-                let src_ppn = user_space.translate(vpn).unwrap().ppn();
+                let src_ppn = user_space.page_table.translate(vpn).unwrap().ppn();
                 let dst_ppn = memory_set.translate(vpn).unwrap().ppn();
                 dst_ppn
                     .get_bytes_array()
                     .copy_from_slice(src_ppn.get_bytes_array());
             }
         }
-        memory_set
+        Some(memory_set)
+    }
+    /// 处理用户对`va`的访问引发的缺页异常, `access`是访问需要的权限(R, W 或 X).
+    ///
+    /// 访问合法时使其可以完成(例如写时复制, 惰性分配, 从交换区换入), 并返回`true`;
+    /// 访问本身就不合法(不在任何用户逻辑段内或权限不足)或内存耗尽时返回`false`
+    pub fn handle_page_fault(&mut self, va: VirtAddr, access: MapPermission) -> bool {
+        let vpn = va.floor();
+        let idx = match self.areas.iter().position(|area| area.contains(vpn)) {
+            Some(idx) if self.areas[idx].map_perm.contains(MapPermission::U) => idx,
+            _ => return false,
+        };
+        let map_perm = self.areas[idx].map_perm;
+        if !map_perm.contains(access) {
+            return false;
+        }
+        let write = access.contains(MapPermission::W);
+        let pte = self.page_table.translate(vpn).filter(|pte| pte.is_valid());
+        match pte {
+            Some(pte)
+                if pte
+                    .flags()
+                    .contains(PTEFlags::from_bits(access.bits).unwrap()) =>
+            {
+                // another thread has handled the fault (e.g. mapped a lazy page or
+                // made the page writable) on another hart, the TLB of this hart is stale
+                unsafe {
+                    asm!("sfence.vma");
+                }
+                return true;
+            }
+            Some(_) if !write => {
+                // the page is accessible, so the fault is caused by permission
+                return false;
+            }
+            Some(pte) if frame_ref_count(pte.ppn()) == 1 => {
+                // 其他地址空间都已经不再共享这个页了, 直接恢复写权限
+                let pte_flags = PTEFlags::from_bits(map_perm.bits).unwrap();
+                self.page_table.remap(vpn, pte.ppn(), pte_flags);
+                return true;
+            }
+            Some(_) => {}
+            None if self.areas[idx].lazy || self.areas[idx].swapped.contains_key(&vpn) => {}
+            None => return false,
+        }
+        // 换出页面不会影响正在处理的页面: 它要么不在内存中, 要么仍被共享
+        let frame = match self.alloc_frame() {
+            Some(frame) => frame,
+            None => return false,
+        };
+        if !self.prepare_pte(vpn) {
+            return false;
+        }
+        let area = &mut self.areas[idx];
+        if pte.is_some() {
+            area.copy_on_write(&mut self.page_table, vpn, frame);
+            flush_tlb();
+        } else {
+            area.fill_page(&mut self.page_table, vpn, frame);
+        }
+        true
+    }
+    /// 申请一个物理页, 物理内存不足时优先换出当前地址空间中的页面, 其次是其它进程的页面
+    fn alloc_frame(&mut self) -> Option<FrameTracker> {
+        loop {
+            if let Some(frame) = frame_alloc() {
+                return Some(frame);
+            }
+            if !self.swap_out_one() && !swap_out_any() {
+                return None;
+            }
+        }
+    }
+    /// 预先创建`vpn`的各级页表节点, 物理内存不足时与[`MemorySet::alloc_frame`]一样换出页面
+    fn prepare_pte(&mut self, vpn: VirtPageNum) -> bool {
+        loop {
+            if self.page_table.create_pte(vpn) {
+                return true;
+            }
+            if !self.swap_out_one() && !swap_out_any() {
+                return false;
+            }
+        }
+    }
+    /// 为逻辑段`idx`中的页面`vpn`建立映射, Framed 逻辑段的物理页通过
+    /// [`MemorySet::alloc_frame`]申请. 物理内存耗尽时返回`false`
+    fn map_one(&mut self, idx: usize, vpn: VirtPageNum) -> bool {
+        let frame = match self.areas[idx].map_type {
+            MapType::Identical => None,
+            MapType::Framed => match self.alloc_frame() {
+                Some(frame) => Some(frame),
+                None => return false,
+            },
+        };
+        if !self.prepare_pte(vpn) {
+            return false;
+        }
+        self.areas[idx].map_one(&mut self.page_table, vpn, frame);
+        true
+    }
+    /// 映射逻辑段`idx`中`[start, end)`的页面, 惰性分配的逻辑段等到访问时才映射.
+    /// 物理内存耗尽时撤销这次映射的页面并返回`false`
+    fn map_range(&mut self, idx: usize, start: VirtPageNum, end: VirtPageNum) -> bool {
+        if self.areas[idx].lazy {
+            return true;
+        }
+        for vpn in VPNRange::new(start, end) {
+            if !self.map_one(idx, vpn) {
+                for mapped in VPNRange::new(start, vpn) {
+                    self.areas[idx].unmap_one(&mut self.page_table, mapped);
+                }
+                return false;
+            }
+        }
+        true
+    }
+    /// 为惰性分配的逻辑段`idx`中还没有访问过的页面分配物理页, 物理内存耗尽时返回`false`
+    fn populate(&mut self, idx: usize) -> bool {
+        for vpn in self.areas[idx].vpn_range {
+            let area = &self.areas[idx];
+            if area.data_frames.contains_key(&vpn) || area.swapped.contains_key(&vpn) {
+                continue;
+            }
+            if !self.map_one(idx, vpn) {
+                return false;
+            }
+            self.areas[idx].load_page(vpn);
+        }
+        true
+    }
+    /// 时钟算法: 从上次停下的位置开始扫描当前地址空间中可以换出的页面,
+    /// 清除最近被访问过的页面的访问位并跳过它, 把第一个没有被访问过的页面换出到交换区
+    pub fn swap_out_one(&mut self) -> bool {
+        let mut candidates: Vec<(usize, VirtPageNum)> = Vec::new();
+        for (idx, area) in self.areas.iter().enumerate() {
+            if !area.swappable() {
+                continue;
+            }
+            for (vpn, frame) in area.data_frames.iter() {
+                // 仍被共享的页换出后并不能释放物理页, 被固定的页正在被内核访问
+                if frame_ref_count(frame.ppn) == 1 && !self.pinned.contains_key(vpn) {
+                    candidates.push((idx, *vpn));
+                }
+            }
+        }
+        if candidates.is_empty() {
+            return false;
+        }
+        candidates.sort_by_key(|(_, vpn)| *vpn);
+        let start = candidates.partition_point(|(_, vpn)| *vpn < self.clock_hand);
+        // 第一圈清除访问位, 第二圈一定能找到没有被访问过的页面
+        for i in 0..candidates.len() * 2 {
+            let (idx, vpn) = candidates[(start + i) % candidates.len()];
+            if self.page_table.translate(vpn).unwrap().accessed() {
+                self.page_table.clear_accessed(vpn);
+                continue;
+            }
+            self.clock_hand = VirtPageNum(vpn.0 + 1);
+            return self.areas[idx].swap_out(&mut self.page_table, vpn);
+        }
+        false
+    }
+    /// 把`[start, start + len)`中的页固定在内存中, 直到调用[`MemorySet::unpin`].
+    ///
+    /// 内核在不持有 PCB 的锁时通过物理地址访问用户缓冲区(例如读写文件时阻塞)之前调用,
+    /// 否则页面可能被换出, 物理页被分配给别人
+    pub fn pin(&mut self, start: usize, len: usize) {
+        for vpn in Self::vpn_range(start, len) {
+            *self.pinned.entry(vpn).or_insert(0) += 1;
+        }
+    }
+    /// 撤销一次[`MemorySet::pin`]
+    pub fn unpin(&mut self, start: usize, len: usize) {
+        for vpn in Self::vpn_range(start, len) {
+            if let Some(count) = self.pinned.get_mut(&vpn) {
+                *count -= 1;
+                if *count == 0 {
+                    self.pinned.remove(&vpn);
+                }
+            }
+        }
+    }
+    /// 覆盖`[start, start + len)`的页
+    fn vpn_range(start: usize, len: usize) -> VPNRange {
+        if len == 0 {
+            return VPNRange::new(VirtPageNum(0), VirtPageNum(0));
+        }
+        VPNRange::new(
+            VirtAddr::from(start).floor(),
+            VirtAddr::from(start + len).ceil(),
+        )
+    }
+    /// 内核访问用户地址`[start, start + len)`之前调用,
+    /// 就像用户自己访问过这些页一样处理其中的缺页(例如打破写时复制).
+    /// 有页面不可访问时返回`false`
+    pub fn fault_in(&mut self, start: usize, len: usize, write: bool) -> bool {
+        if len == 0 {
+            return true;
+        }
+        if start.checked_add(len).map_or(true, |end| end > MMAP_TOP) {
+            return false;
+        }
+        let start_vpn = VirtAddr::from(start).floor();
+        let end_vpn = VirtAddr::from(start + len).ceil();
+        let access = if write {
+            MapPermission::W
+        } else {
+            MapPermission::R
+        };
+        VPNRange::new(start_vpn, end_vpn).into_iter().all(|vpn| {
+            match self.page_table.translate(vpn) {
+                Some(pte) if pte.is_valid() && (!write || pte.writable()) => true,
+                _ => self.handle_page_fault(vpn.into(), access),
+            }
+        })
+    }
+    /// 与[`MemorySet::fault_in`]相同, 范围是从`start`开始以`\0`结尾的字符串
+    pub fn fault_in_str(&mut self, start: usize) -> bool {
+        let mut va = start;
+        loop {
+            if !self.fault_in(va, 1, false) {
+                return false;
+            }
+            let va_start = VirtAddr::from(va);
+            let ppn = self.page_table.translate(va_start.floor()).unwrap().ppn();
+            if ppn.get_bytes_array()[va_start.page_offset()..].contains(&0) {
+                return true;
+            }
+            va += PAGE_SIZE - va_start.page_offset();
+        }
+    }
+    /// `[start, end)`内没有任何逻辑段
+    fn is_free(&self, start: VirtPageNum, end: VirtPageNum) -> bool {
+        !self.areas.iter().any(|area| area.overlaps(start, end))
+    }
+    /// 从`MMAP_BASE`开始寻找第一段能容纳`pages`个页面的空闲虚拟地址
+    fn find_free_range(&self, pages: usize) -> VirtPageNum {
+        let mut start = VirtAddr::from(MMAP_BASE).floor();
+        loop {
+            let end = VirtPageNum(start.0 + pages);
+            match self
+                .areas
+                .iter()
+                .filter(|area| area.overlaps(start, end))
+                .map(|area| area.vpn_range.get_end())
+                .max()
+            {
+                Some(next) => start = next,
+                None => return start,
+            }
+        }
+    }
+    /// 建立一段新的惰性分配的映射, `start`为`None`时自动选择地址.
+    ///
+    /// `file`为背后的文件及映射开始的文件偏移, `shared`表示写入对共享这段映射的进程可见
+    /// 并写回文件. 与已有的逻辑段重叠或超出用户地址空间时失败, 否则返回映射的起始地址
+    pub fn mmap(
+        &mut self,
+        start: Option<usize>,
+        len: usize,
+        map_perm: MapPermission,
+        file: Option<(Arc<Inode>, usize, bool)>,
+        shared: bool,
+    ) -> Option<usize> {
+        if len == 0 || len > MMAP_TOP {
+            return None;
+        }
+        let pages = (len + PAGE_SIZE - 1) / PAGE_SIZE;
+        let start_vpn = match start {
+            Some(start) if start > MMAP_TOP - len => return None,
+            Some(start) => VirtAddr::from(start).floor(),
+            None => self.find_free_range(pages),
+        };
+        let end_vpn = VirtPageNum(start_vpn.0 + pages);
+        let start_va: VirtAddr = start_vpn.into();
+        let end_va: VirtAddr = end_vpn.into();
+        if end_va.0 > MMAP_TOP || !self.is_free(start_vpn, end_vpn) {
+            return None;
+        }
+        let file = file.map(|(inode, offset, writable)| MapFile {
+            start: start_va.0,
+            offset,
+            len: inode.size().saturating_sub(offset).min(len),
+            inode,
+            writable,
+        });
+        let mut map_area = MapArea::new_lazy(start_va, end_va, map_perm, file);
+        map_area.shared = shared;
+        self.push(map_area, None);
+        Some(start_va.0)
+    }
+    /// 把与`[start, end)`部分重叠的逻辑段在边界处拆开, 使每个逻辑段要么完全在范围内,
+    /// 要么完全在范围外. 范围内有用户不可访问的逻辑段(例如TrapContext)或者需要拆开堆时返回`false`
+    fn split_areas(&mut self, start: VirtPageNum, end: VirtPageNum) -> bool {
+        if self.areas.iter().any(|area| {
+            area.overlaps(start, end)
+                && (!area.map_perm.contains(MapPermission::U)
+                    || (area.heap
+                        && (area.vpn_range.get_start() < start || end < area.vpn_range.get_end())))
+        }) {
+            return false;
+        }
+        let mut new_areas = Vec::new();
+        for area in self.areas.iter_mut() {
+            for vpn in [end, start] {
+                if area.vpn_range.get_start() < vpn && vpn < area.vpn_range.get_end() {
+                    new_areas.push(area.split_off(vpn));
+                }
+            }
+        }
+        self.areas.extend(new_areas);
+        true
+    }
+    /// 解除`[start, start + len)`的映射, 可以只解除一个逻辑段的一部分.
+    /// 范围内没有映射的页面被忽略. 堆只能通过`brk`缩小, 范围与堆重叠时返回`false`
+    pub fn munmap(&mut self, start: usize, len: usize) -> bool {
+        let start_vpn = VirtAddr::from(start).floor();
+        let end_vpn = VirtAddr::from(start + len).ceil();
+        if self
+            .areas
+            .iter()
+            .any(|area| area.heap && area.overlaps(start_vpn, end_vpn))
+            || !self.split_areas(start_vpn, end_vpn)
+        {
+            return false;
+        }
+        let mut idx = 0;
+        while idx < self.areas.len() {
+            if self.areas[idx].overlaps(start_vpn, end_vpn) {
+                let mut area = self.areas.remove(idx);
+                area.unmap(&mut self.page_table);
+            } else {
+                idx += 1;
+            }
+        }
+        flush_tlb();
+        true
+    }
+    /// `[start, start + len)`中的共享文件映射是否都可以写回文件(文件以可写方式打开)
+    pub fn file_writable(&self, start: usize, len: usize) -> bool {
+        let start_vpn = VirtAddr::from(start).floor();
+        let end_vpn = VirtAddr::from(start + len).ceil();
+        self.areas
+            .iter()
+            .filter(|area| area.shared && area.overlaps(start_vpn, end_vpn))
+            .all(|area| area.file.as_ref().map_or(true, |file| file.writable))
+    }
+    /// 修改`[start, start + len)`的访问权限, 范围内的每个页面都必须已经映射
+    pub fn mprotect(&mut self, start: usize, len: usize, map_perm: MapPermission) -> bool {
+        let start_vpn = VirtAddr::from(start).floor();
+        let end_vpn = VirtAddr::from(start + len).ceil();
+        let mapped: usize = self
+            .areas
+            .iter()
+            .filter(|area| area.overlaps(start_vpn, end_vpn))
+            .map(|area| {
+                area.vpn_range.get_end().min(end_vpn).0
+                    - area.vpn_range.get_start().max(start_vpn).0
+            })
+            .sum();
+        if mapped != end_vpn.0 - start_vpn.0 || !self.split_areas(start_vpn, end_vpn) {
+            return false;
+        }
+        for area in self.areas.iter_mut() {
+            if area.overlaps(start_vpn, end_vpn) {
+                area.set_perm(&mut self.page_table, map_perm);
+            }
+        }
+        flush_tlb();
+        true
+    }
+    /// 把堆逻辑段缩小到`new_end`, 释放超出的页面
+    pub fn shrink_to(&mut self, new_end: VirtAddr) -> bool {
+        match self.areas.iter_mut().find(|area| area.heap) {
+            Some(area)
+                if area.vpn_range.get_start() <= new_end.ceil()
+                    && new_end.ceil() <= area.vpn_range.get_end() =>
+            {
+                area.shrink_to(&mut self.page_table, new_end.ceil());
+                flush_tlb();
+                true
+            }
+            _ => false,
+        }
+    }
+    /// 把堆逻辑段扩大到`new_end`, 与其他逻辑段重叠时失败
+    pub fn append_to(&mut self, new_end: VirtAddr) -> bool {
+        let idx = match self.areas.iter().position(|area| area.heap) {
+            Some(idx) => idx,
+            None => return false,
+        };
+        let old_end = self.areas[idx].vpn_range.get_end();
+        if new_end.ceil() < old_end || !self.is_free(old_end, new_end.ceil()) {
+            return false;
+        }
+        self.areas[idx].append_to(new_end.ceil());
+        if !self.map_range(idx, old_end, new_end.ceil()) {
+            self.areas[idx].append_to(old_end);
+            return false;
+        }
+        true
     }
     /// vpn -> pte
     pub fn translate(&self, vpn: VirtPageNum) -> Option<PageTableEntry> {
         self.page_table.translate(vpn)
     }
-    /// 回收数据页
+    /// `va`是否在共享映射中. 共享映射的页面不会被换出, 物理地址一直不变
+    pub fn is_shared(&self, va: VirtAddr) -> bool {
+        let vpn = va.floor();
+        self.areas
+            .iter()
+            .any(|area| area.shared && area.contains(vpn))
+    }
+    /// 回收数据页, 共享的文件映射先写回文件
     pub fn recycle_data_pages(&mut self) {
+        for area in self.areas.iter() {
+            area.write_back();
+        }
         self.areas.clear();
     }
 }
@@ -297,6 +820,31 @@ pub struct MapArea {
     map_type: MapType,
     /// 权限许可
     map_perm: MapPermission,
+    /// 是否惰性分配: 物理页在第一次访问引发缺页时才分配
+    lazy: bool,
+    /// 惰性分配时页面数据的来源, `None`表示填0
+    file: Option<MapFile>,
+    /// 共享映射(MAP_SHARED): fork时不写时复制, 父子进程共享物理页, 写入会写回文件
+    shared: bool,
+    /// 堆(由`brk`管理): 不能被拆开或者解除映射, 否则`brk`找不到它
+    heap: bool,
+    /// 被换出到交换区的页面, fork后父子进程共享同一个交换页
+    swapped: BTreeMap<VirtPageNum, Arc<SwapSlot>>,
+}
+
+/// 逻辑段背后的文件: 缺页时从文件中读取对应的数据, 超出文件数据的部分填0
+#[derive(Clone)]
+pub struct MapFile {
+    /// 文件
+    pub inode: Arc<Inode>,
+    /// 文件数据在地址空间中的起始虚拟地址
+    pub start: usize,
+    /// `start`对应的文件偏移
+    pub offset: usize,
+    /// 文件数据的长度
+    pub len: usize,
+    /// 文件是否以可写方式打开, 否则共享映射不能被写回
+    pub writable: bool,
 }
 
 impl MapArea {
@@ -314,6 +862,24 @@ impl MapArea {
             data_frames: BTreeMap::new(),
             map_type,
             map_perm,
+            lazy: false,
+            file: None,
+            shared: false,
+            heap: false,
+            swapped: BTreeMap::new(),
+        }
+    }
+    /// new one, 惰性分配, 页面数据来自`file`(`None`表示填0)
+    pub fn new_lazy(
+        start_va: VirtAddr,
+        end_va: VirtAddr,
+        map_perm: MapPermission,
+        file: Option<MapFile>,
+    ) -> Self {
+        Self {
+            lazy: true,
+            file,
+            ..Self::new(start_va, end_va, MapType::Framed, map_perm)
         }
     }
     /// 从其他进程复制一个逻辑段
@@ -323,17 +889,140 @@ impl MapArea {
             data_frames: BTreeMap::new(),
             map_type: another.map_type,
             map_perm: another.map_perm,
+            lazy: another.lazy,
+            file: another.file.clone(),
+            shared: another.shared,
+            heap: another.heap,
+            swapped: BTreeMap::new(),
         }
     }
-    /// 将当前逻辑段到物理内存的映射从传入的该逻辑段所属的地址空间的多级页表中加入
-    pub fn map(&mut self, page_table: &mut PageTable) {
-        for vpn in self.vpn_range {
-            self.map_one(page_table, vpn);
+    /// `vpn`是否在该逻辑段内
+    pub fn contains(&self, vpn: VirtPageNum) -> bool {
+        self.vpn_range.get_start() <= vpn && vpn < self.vpn_range.get_end()
+    }
+    /// 是否与`[start, end)`有重叠
+    fn overlaps(&self, start: VirtPageNum, end: VirtPageNum) -> bool {
+        self.vpn_range.get_start() < end && start < self.vpn_range.get_end()
+    }
+    /// 在`vpn`处把逻辑段一分为二, 自己保留前半部分, 返回后半部分
+    fn split_off(&mut self, vpn: VirtPageNum) -> Self {
+        let mut another = Self::from_another(self);
+        another.vpn_range = VPNRange::new(vpn, self.vpn_range.get_end());
+        another.data_frames = self.data_frames.split_off(&vpn);
+        another.swapped = self.swapped.split_off(&vpn);
+        self.vpn_range = VPNRange::new(self.vpn_range.get_start(), vpn);
+        another
+    }
+    /// 修改逻辑段的权限, 同时更新已经映射的页表项(仍在写时复制共享的页不恢复写权限)
+    fn set_perm(&mut self, page_table: &mut PageTable, map_perm: MapPermission) {
+        self.map_perm = map_perm;
+        for (vpn, frame) in self.data_frames.iter() {
+            let pte_flags = if self.is_cow() && frame_ref_count(frame.ppn) > 1 {
+                self.cow_pte_flags()
+            } else {
+                PTEFlags::from_bits(map_perm.bits).unwrap()
+            };
+            page_table.remap(*vpn, frame.ppn, pte_flags);
+        }
+    }
+    /// 把逻辑段的结尾缩小到`new_end`
+    fn shrink_to(&mut self, page_table: &mut PageTable, new_end: VirtPageNum) {
+        for vpn in VPNRange::new(new_end, self.vpn_range.get_end()) {
+            self.unmap_one(page_table, vpn);
+        }
+        self.vpn_range = VPNRange::new(self.vpn_range.get_start(), new_end);
+    }
+    /// 把逻辑段的结尾修改为`new_end`, 新增的页面由[`MemorySet`]映射
+    fn append_to(&mut self, new_end: VirtPageNum) {
+        self.vpn_range = VPNRange::new(self.vpn_range.get_start(), new_end);
+    }
+    /// 共享的可写文件映射: 把已经分配的页面中属于文件的部分写回文件
+    fn write_back(&self) {
+        let file = match &self.file {
+            Some(file) if self.shared && self.map_perm.contains(MapPermission::W) => file,
+            _ => return,
+        };
+        for (vpn, frame) in self.data_frames.iter() {
+            let page_start = VirtAddr::from(*vpn).0;
+            let start = page_start.max(file.start);
+            let end = (page_start + PAGE_SIZE).min(file.start + file.len);
+            if start < end {
+                file.inode.write_at(
+                    file.offset + start - file.start,
+                    &frame.ppn.get_bytes_array()[start - page_start..end - page_start],
+                );
+            }
         }
     }
+    /// fork时是否采用写时复制(用户可访问的私有Framed逻辑段)
+    fn is_cow(&self) -> bool {
+        self.map_type == MapType::Framed && self.map_perm.contains(MapPermission::U) && !self.shared
+    }
+    /// 写时复制共享期间的页表项标志位: 去掉写权限
+    fn cow_pte_flags(&self) -> PTEFlags {
+        PTEFlags::from_bits((self.map_perm - MapPermission::W).bits).unwrap()
+    }
+    /// 页面可以被换出到交换区(用户可访问的私有Framed逻辑段)
+    fn swappable(&self) -> bool {
+        self.is_cow()
+    }
+    /// 把页面`vpn`解除映射并换出到交换区, 交换区已满时恢复映射并返回`false`
+    fn swap_out(&mut self, page_table: &mut PageTable, vpn: VirtPageNum) -> bool {
+        let ppn = self.data_frames.get(&vpn).unwrap().ppn;
+        let pte_flags = page_table.translate(vpn).unwrap().flags();
+        // 先解除映射, 其它 hart 上运行的线程不能在页面写入交换区之后再修改它
+        page_table.unmap(vpn);
+        flush_tlb();
+        match swap_out(ppn) {
+            Some(slot) => {
+                self.data_frames.remove(&vpn);
+                self.swapped.insert(vpn, Arc::new(slot));
+                true
+            }
+            None => {
+                page_table.map(vpn, ppn, pte_flags);
+                false
+            }
+        }
+    }
+    /// 为缺页的`vpn`装入物理页`frame`, 数据来自交换区, 背后的文件或者填0
+    fn fill_page(&mut self, page_table: &mut PageTable, vpn: VirtPageNum, frame: FrameTracker) {
+        let ppn = frame.ppn;
+        let slot = self.swapped.remove(&vpn);
+        if let Some(slot) = &slot {
+            slot.read(ppn);
+        }
+        self.data_frames.insert(vpn, frame);
+        // 预先设置访问位, 避免刚换入的页面马上又被换出
+        let pte_flags = PTEFlags::from_bits(self.map_perm.bits).unwrap() | PTEFlags::A;
+        page_table.map(vpn, ppn, pte_flags);
+        if slot.is_none() {
+            self.load_page(vpn);
+        }
+    }
+    /// 写时复制: 为`vpn`换上独占的物理页`new_frame`, 并恢复写权限
+    fn copy_on_write(
+        &mut self,
+        page_table: &mut PageTable,
+        vpn: VirtPageNum,
+        new_frame: FrameTracker,
+    ) {
+        let pte_flags = PTEFlags::from_bits(self.map_perm.bits).unwrap();
+        let frame = self.data_frames.get(&vpn).unwrap();
+        new_frame
+            .ppn
+            .get_bytes_array()
+            .copy_from_slice(frame.ppn.get_bytes_array());
+        page_table.remap(vpn, new_frame.ppn, pte_flags);
+        // 旧的tracker被替换后drop, 引用计数减1
+        self.data_frames.insert(vpn, new_frame);
+    }
     /// 将当前逻辑段到物理内存的映射从传入的该逻辑段所属的地址空间的多级页表中删除
+    ///
+    /// 共享的文件映射会先写回文件
     #[allow(unused)]
     pub fn unmap(&mut self, page_table: &mut PageTable) {
+        self.write_back();
         for vpn in self.vpn_range {
             self.unmap_one(page_table, vpn);
         }
@@ -360,15 +1049,36 @@ impl MapArea {
             current_vpn.step();
         }
     }
-    /// 对逻辑段中的单个虚拟页面进行映射
-    pub fn map_one(&mut self, page_table: &mut PageTable, vpn: VirtPageNum) {
+    /// 从背后的文件中读取惰性分配的页面`vpn`的数据, 页面已经映射且清零
+    fn load_page(&self, vpn: VirtPageNum) {
+        if let Some(file) = &self.file {
+            let page_start = VirtAddr::from(vpn).0;
+            let start = page_start.max(file.start);
+            let end = (page_start + PAGE_SIZE).min(file.start + file.len);
+            if start < end {
+                let ppn = self.data_frames.get(&vpn).unwrap().ppn;
+                file.inode.read_at(
+                    file.offset + start - file.start,
+                    &mut ppn.get_bytes_array()[start - page_start..end - page_start],
+                );
+            }
+        }
+    }
+    /// 对逻辑段中的单个虚拟页面进行映射, Framed 逻辑段使用物理页`frame`.
+    /// `vpn`的各级页表节点必须已经存在
+    pub fn map_one(
+        &mut self,
+        page_table: &mut PageTable,
+        vpn: VirtPageNum,
+        frame: Option<FrameTracker>,
+    ) {
         let ppn: PhysPageNum;
         match self.map_type {
             MapType::Identical => {
                 ppn = PhysPageNum(vpn.0);
             }
             MapType::Framed => {
-                let frame = frame_alloc().unwrap();
+                let frame = frame.unwrap();
                 ppn = frame.ppn;
                 self.data_frames.insert(vpn, frame);
             }
@@ -379,8 +1089,13 @@ impl MapArea {
     /// 对逻辑段中的单个虚拟页面进行解映射
     #[allow(unused)]
     pub fn unmap_one(&mut self, page_table: &mut PageTable, vpn: VirtPageNum) {
-        if self.map_type == MapType::Framed {
-            self.data_frames.remove(&vpn);
+        if self.swapped.remove(&vpn).is_some() {
+            // in the swap area, nothing is mapped
+            return;
+        }
+        if self.map_type == MapType::Framed && self.data_frames.remove(&vpn).is_none() {
+            // never touched or never mapped, nothing is mapped
+            return;
         }
         page_table.unmap(vpn);
     }
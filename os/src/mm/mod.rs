@@ -11,22 +11,33 @@ mod frame_allocator;
 mod heap_allocator;
 mod memory_set;
 mod page_table;
+mod swap;
 
 pub use address::{PhysAddr, PhysPageNum, VirtAddr, VirtPageNum};
 
-pub use frame_allocator::{frame_alloc, frame_dealloc, FrameTracker};
+pub use frame_allocator::{
+    frame_alloc, frame_alloc_contiguous, frame_dealloc, FrameBlock, FrameTracker,
+};
 pub use memory_set::remap_test;
-pub use memory_set::{kernel_token, MapPermission, MemorySet, KERNEL_SPACE};
+pub use memory_set::{
+    activate_kernel_space, kernel_token, BadElf, MapPermission, MemorySet, KERNEL_SPACE,
+};
 pub use page_table::{
-    translated_byte_buffer, translated_ref, translated_refmut, translated_str, PageTable,
-    PageTableEntry, UserBuffer, UserBufferIterator,
+    translated_byte_buffer, translated_ref, translated_refmut, translated_str, try_copy_from_user,
+    try_copy_to_user, try_translated_byte_buffer, try_translated_ref, try_translated_refmut,
+    try_translated_str, BadAddress, PageTable, PageTableEntry, UserBuffer, UserBufferIterator,
 };
 
 /// memory manager 初始化
 pub fn init() {
     heap_allocator::init_heap();
     frame_allocator::init_frame_allocator();
-    KERNEL_SPACE.exclusive_access().activate();
+    activate_kernel_space();
+}
+
+/// 在其它 hart 上启用内核地址空间
+pub fn init_secondary() {
+    activate_kernel_space();
 }
 
 #[allow(dead_code)]
@@ -35,4 +46,5 @@ pub fn unit_tests() {
     heap_allocator::heap_test();
     frame_allocator::frame_allocator_test();
     remap_test();
+    swap::swap_test();
 }
@@ -1,6 +1,7 @@
 //! 页表实现
 //!
 
+use crate::config::{MMAP_TOP, PAGE_SIZE};
 use crate::mm::address::{PhysPageNum, VirtPageNum};
 use crate::mm::frame_allocator::{frame_alloc, FrameTracker};
 use crate::mm::{PhysAddr, VirtAddr};
@@ -85,6 +86,14 @@ impl PageTableEntry {
     pub fn executable(&self) -> bool {
         (self.flags() & PTEFlags::X) != PTEFlags::empty()
     }
+    /// 用户态是否可以访问
+    pub fn is_user(&self) -> bool {
+        (self.flags() & PTEFlags::U) != PTEFlags::empty()
+    }
+    /// 自从访问位被清零之后是否被访问过
+    pub fn accessed(&self) -> bool {
+        (self.flags() & PTEFlags::A) != PTEFlags::empty()
+    }
 }
 
 /// 页表
@@ -96,9 +105,8 @@ pub struct PageTable {
 }
 
 impl PageTable {
-    /// 初始化
-    pub fn new() -> Self {
-        let frame = frame_alloc().unwrap();
+    /// 初始化, 物理页`frame`作为页表根
+    pub fn new(frame: FrameTracker) -> Self {
         PageTable {
             root_ppn: frame.ppn,
             frames: vec![frame],
@@ -125,7 +133,24 @@ impl PageTable {
         assert!(pte.is_valid(), "vpn {:?} is invalid before unmapping", vpn);
         *pte = PageTableEntry::empty();
     }
+    /// 修改一个已映射的键值对(写时复制时换页/改权限)
+    pub fn remap(&mut self, vpn: VirtPageNum, ppn: PhysPageNum, flags: PTEFlags) {
+        let pte = self.find_pte(vpn).unwrap();
+        assert!(pte.is_valid(), "vpn {:?} is invalid before remapping", vpn);
+        *pte = PageTableEntry::new(ppn, flags | PTEFlags::V);
+    }
+    /// 清除页表项的访问位(页面置换的时钟算法)
+    pub fn clear_accessed(&mut self, vpn: VirtPageNum) {
+        let pte = self.find_pte(vpn).unwrap();
+        *pte = PageTableEntry::new(pte.ppn(), pte.flags() - PTEFlags::A);
+    }
+    /// 预先创建`vpn`所需的各级页表节点, 之后[`PageTable::map`]不再需要申请物理页.
+    /// 物理内存不足时返回`false`, 已经创建的节点保留
+    pub fn create_pte(&mut self, vpn: VirtPageNum) -> bool {
+        self.find_pte_create(vpn).is_some()
+    }
     /// 在多级页表找到一个虚拟页号对应的页表项的可变引用。如果在遍历的过程中发现有节点尚未创建则会新建一个节点。
+    /// 物理内存不足以创建节点时返回`None`
     fn find_pte_create(&mut self, vpn: VirtPageNum) -> Option<&mut PageTableEntry> {
         let idxs = vpn.indexes();
         let mut ppn = self.root_ppn;
@@ -137,7 +162,7 @@ impl PageTable {
                 break;
             }
             if !pte.is_valid() {
-                let frame = frame_alloc().unwrap();
+                let frame = frame_alloc()?;
                 *pte = PageTableEntry::new(frame.ppn, PTEFlags::V);
                 self.frames.push(frame);
             }
@@ -248,6 +273,114 @@ pub fn translated_refmut<T>(token: usize, ptr: *mut T) -> &'static mut T {
         .get_mut()
 }
 
+/// 用户传入的地址不可访问: 没有映射, 或者页表项的 U/R/W 位不允许这次访问
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BadAddress;
+
+/// 翻译用户地址`va`所在的虚拟页, 检查页表项的 U/R/W 位
+fn translate_user_page(
+    page_table: &PageTable,
+    va: usize,
+    write: bool,
+) -> Result<PhysPageNum, BadAddress> {
+    // 用户地址都在 SV39 虚拟地址空间的低半部分
+    if va >= MMAP_TOP {
+        return Err(BadAddress);
+    }
+    match page_table.translate(VirtAddr::from(va).floor()) {
+        Some(pte)
+            if pte.is_valid() && pte.is_user() && pte.readable() && (!write || pte.writable()) =>
+        {
+            Ok(pte.ppn())
+        }
+        _ => Err(BadAddress),
+    }
+}
+
+/// [`translated_byte_buffer`]的可失败版本, `write`表示内核是否会写入这段内存
+pub fn try_translated_byte_buffer(
+    token: usize,
+    ptr: *const u8,
+    len: usize,
+    write: bool,
+) -> Result<Vec<&'static mut [u8]>, BadAddress> {
+    let page_table = PageTable::from_token(token);
+    let mut start = ptr as usize;
+    let end = start.checked_add(len).ok_or(BadAddress)?;
+    let mut v = Vec::new();
+    while start < end {
+        let ppn = translate_user_page(&page_table, start, write)?;
+        let offset = start % PAGE_SIZE;
+        let len = (PAGE_SIZE - offset).min(end - start);
+        v.push(&mut ppn.get_bytes_array()[offset..offset + len]);
+        start += len;
+    }
+    Ok(v)
+}
+
+/// [`translated_str`]的可失败版本
+pub fn try_translated_str(token: usize, ptr: *const u8) -> Result<String, BadAddress> {
+    let page_table = PageTable::from_token(token);
+    let mut string = String::new();
+    let mut va = ptr as usize;
+    loop {
+        let offset = va % PAGE_SIZE;
+        let ppn = translate_user_page(&page_table, va, false)?;
+        for &ch in &ppn.get_bytes_array()[offset..] {
+            if ch == 0 {
+                return Ok(string);
+            }
+            string.push(ch as char);
+        }
+        va += PAGE_SIZE - offset;
+    }
+}
+
+/// 翻译一个用户态的`T`, 不允许跨页
+fn translate_user_object<T>(token: usize, ptr: usize, write: bool) -> Result<PhysAddr, BadAddress> {
+    let offset = ptr % PAGE_SIZE;
+    if offset + core::mem::size_of::<T>() > PAGE_SIZE {
+        return Err(BadAddress);
+    }
+    let ppn = translate_user_page(&PageTable::from_token(token), ptr, write)?;
+    Ok((PhysAddr::from(ppn).0 + offset).into())
+}
+
+/// [`translated_ref`]的可失败版本, 跨页的`T`视为不可访问
+pub fn try_translated_ref<T>(token: usize, ptr: *const T) -> Result<&'static T, BadAddress> {
+    translate_user_object::<T>(token, ptr as usize, false).map(|pa| pa.get_ref())
+}
+
+/// [`translated_refmut`]的可失败版本, 跨页的`T`视为不可访问
+pub fn try_translated_refmut<T>(token: usize, ptr: *mut T) -> Result<&'static mut T, BadAddress> {
+    translate_user_object::<T>(token, ptr as usize, true).map(|pa| pa.get_mut())
+}
+
+/// 把`value`复制到用户地址`ptr`, 允许跨页
+pub fn try_copy_to_user<T: Copy>(token: usize, ptr: *mut T, value: &T) -> Result<(), BadAddress> {
+    let len = core::mem::size_of::<T>();
+    let bytes = unsafe { core::slice::from_raw_parts(value as *const T as *const u8, len) };
+    let mut copied = 0;
+    for buffer in try_translated_byte_buffer(token, ptr as *const u8, len, true)? {
+        buffer.copy_from_slice(&bytes[copied..copied + buffer.len()]);
+        copied += buffer.len();
+    }
+    Ok(())
+}
+
+/// 从用户地址`ptr`复制一个`T`, 允许跨页
+pub fn try_copy_from_user<T: Copy>(token: usize, ptr: *const T) -> Result<T, BadAddress> {
+    let len = core::mem::size_of::<T>();
+    let mut value = core::mem::MaybeUninit::<T>::uninit();
+    let bytes = unsafe { core::slice::from_raw_parts_mut(value.as_mut_ptr() as *mut u8, len) };
+    let mut copied = 0;
+    for buffer in try_translated_byte_buffer(token, ptr as *const u8, len, false)? {
+        bytes[copied..copied + buffer.len()].copy_from_slice(buffer);
+        copied += buffer.len();
+    }
+    Ok(unsafe { value.assume_init() })
+}
+
 ///Array of u8 slice that user communicate with os
 pub struct UserBuffer {
     ///U8 vec
@@ -0,0 +1,116 @@
+//! 交换区: 物理内存不足时把用户页面换出到块设备上预留的区域
+//!
+//! 交换区紧跟在文件系统镜像之后, 每个页面占用连续的`PAGE_SIZE / BLOCK_SZ`个块
+
+use crate::config::{PAGE_SIZE, SWAP_PAGES, SWAP_START_BLOCK};
+use crate::drivers::BLOCK_DEVICE;
+use crate::mm::address::PhysPageNum;
+use crate::sync::SpinLock;
+use alloc::vec::Vec;
+use easy_fs::BLOCK_SZ;
+use lazy_static::lazy_static;
+
+/// 每个页面占用的块数
+const BLOCKS_PER_PAGE: usize = PAGE_SIZE / BLOCK_SZ;
+
+/// 使用tracker包装交换区中的一个页面, drop时自动回收
+pub struct SwapSlot {
+    /// 交换区中的页号
+    id: usize,
+}
+
+impl SwapSlot {
+    /// 把物理页`ppn`的内容写入这个交换页
+    fn write(&self, ppn: PhysPageNum) {
+        for (i, block) in ppn.get_bytes_array().chunks(BLOCK_SZ).enumerate() {
+            BLOCK_DEVICE.write_block(self.block_id(i), block);
+        }
+    }
+    /// 把这个交换页的内容读到物理页`ppn`中
+    pub fn read(&self, ppn: PhysPageNum) {
+        for (i, block) in ppn.get_bytes_array().chunks_mut(BLOCK_SZ).enumerate() {
+            BLOCK_DEVICE.read_block(self.block_id(i), block);
+        }
+    }
+    /// 第`i`个块的块号
+    fn block_id(&self, i: usize) -> usize {
+        SWAP_START_BLOCK + self.id * BLOCKS_PER_PAGE + i
+    }
+}
+
+impl Drop for SwapSlot {
+    fn drop(&mut self) {
+        SWAP_ALLOCATOR.exclusive_access().dealloc(self.id);
+    }
+}
+
+/// 交换页分配器, 与栈式物理页帧管理策略相同
+struct SwapAllocator {
+    /// 从未分配过的交换页的起始页号
+    current: usize,
+    /// 回收列表
+    recycled: Vec<usize>,
+}
+
+impl SwapAllocator {
+    fn alloc(&mut self) -> Option<usize> {
+        if let Some(id) = self.recycled.pop() {
+            Some(id)
+        } else if self.current == SWAP_PAGES {
+            None
+        } else {
+            self.current += 1;
+            Some(self.current - 1)
+        }
+    }
+    fn dealloc(&mut self, id: usize) {
+        assert!(
+            id < self.current && !self.recycled.contains(&id),
+            "Swap slot {} has not been allocated!",
+            id
+        );
+        self.recycled.push(id);
+    }
+}
+
+lazy_static! {
+    /// 全局的交换页分配器
+    static ref SWAP_ALLOCATOR: SpinLock<SwapAllocator> = SpinLock::new(SwapAllocator {
+            current: 0,
+            recycled: Vec::new(),
+        });
+}
+
+/// 申请一个交换页
+fn swap_slot_alloc() -> Option<SwapSlot> {
+    SWAP_ALLOCATOR
+        .exclusive_access()
+        .alloc()
+        .map(|id| SwapSlot { id })
+}
+
+/// 把物理页`ppn`的内容换出到交换区, 交换区已满时返回`None`
+pub fn swap_out(ppn: PhysPageNum) -> Option<SwapSlot> {
+    let slot = swap_slot_alloc()?;
+    slot.write(ppn);
+    Some(slot)
+}
+
+/// 单元测试
+#[allow(unused)]
+pub fn swap_test() {
+    use crate::mm::frame_allocator::frame_alloc;
+    let frame = frame_alloc().unwrap();
+    for (i, byte) in frame.ppn.get_bytes_array().iter_mut().enumerate() {
+        *byte = i as u8;
+    }
+    let slot = swap_out(frame.ppn).unwrap();
+    let another = swap_out(frame.ppn).unwrap();
+    assert_ne!(slot.id, another.id);
+    drop(another);
+    let new_frame = frame_alloc().unwrap();
+    slot.read(new_frame.ppn);
+    assert_eq!(new_frame.ppn.get_bytes_array(), frame.ppn.get_bytes_array());
+    drop(slot);
+    debug!("swap_test passed!");
+}
@@ -12,6 +12,11 @@ const SBI_REMOTE_FENCE_I: usize = 5;
 const SBI_REMOTE_SFENCE_VMA: usize = 6;
 const SBI_REMOTE_SFENCE_VMA_ASID: usize = 7;
 const SBI_SHUTDOWN: usize = 8;
+
+const SBI_EXT_HSM: usize = 0x48534d;
+const SBI_HSM_HART_START: usize = 0;
+const SBI_EXT_RFENCE: usize = 0x52464e43;
+const SBI_RFENCE_REMOTE_SFENCE_VMA: usize = 1;
 /// general sbi call
 #[inline(always)]
 fn sbi_call(which: usize, arg0: usize, arg1: usize, arg2: usize) -> usize {
@@ -28,6 +33,30 @@ fn sbi_call(which: usize, arg0: usize, arg1: usize, arg2: usize) -> usize {
     }
     ret
 }
+/// sbi call of the extensions (SBI v0.2+), return the error code
+#[inline(always)]
+fn sbi_call_ext(
+    eid: usize,
+    fid: usize,
+    arg0: usize,
+    arg1: usize,
+    arg2: usize,
+    arg3: usize,
+) -> isize {
+    let mut error;
+    unsafe {
+        asm!(
+        "ecall",
+        inlateout("x10") arg0 => error,
+        inlateout("x11") arg1 => _,
+        in("x12") arg2,
+        in("x13") arg3,
+        in("x16") fid,
+        in("x17") eid,
+        );
+    }
+    error
+}
 /// use sbi call to set timer
 pub fn set_timer(timer: usize) {
     sbi_call(SBI_SET_TIMER, timer, 0, 0);
@@ -45,3 +74,26 @@ pub fn shutdown() -> ! {
     sbi_call(SBI_SHUTDOWN, 0, 0, 0);
     panic!("It should shutdown!");
 }
+/// 使用 HSM 扩展启动`hartid`, 它在 S 态从物理地址`start_addr`开始执行, `a0`为`hartid`, `a1`为`opaque`
+pub fn hart_start(hartid: usize, start_addr: usize, opaque: usize) -> isize {
+    sbi_call_ext(
+        SBI_EXT_HSM,
+        SBI_HSM_HART_START,
+        hartid,
+        start_addr,
+        opaque,
+        0,
+    )
+}
+/// 使用 RFENCE 扩展在所有 hart 上刷新整个 TLB
+pub fn remote_sfence_vma_all() {
+    // hart_mask_base 为 -1 表示所有 hart, size 为 -1 表示整个地址空间
+    sbi_call_ext(
+        SBI_EXT_RFENCE,
+        SBI_RFENCE_REMOTE_SFENCE_VMA,
+        0,
+        usize::MAX,
+        0,
+        usize::MAX,
+    );
+}
@@ -0,0 +1,53 @@
+//! 提供给用户程序的条件变量
+
+use super::{Mutex, SpinLock};
+use crate::task::{block_current_and_run_next, current_has_interrupting_signal, WaitQueue};
+
+/// 条件变量, 总是和一个[`Mutex`]一起使用
+pub struct Condvar {
+    inner: SpinLock<CondvarInner>,
+}
+
+struct CondvarInner {
+    wait_queue: WaitQueue,
+}
+
+impl Condvar {
+    /// 创建条件变量
+    pub fn new() -> Self {
+        Self {
+            inner: SpinLock::new(CondvarInner {
+                wait_queue: WaitQueue::new(),
+            }),
+        }
+    }
+    /// 唤醒一个等待的任务
+    pub fn signal(&self) {
+        self.inner.exclusive_access().wait_queue.wake_one();
+    }
+    /// 释放`mutex`并阻塞到被`signal`唤醒, 返回前重新获得`mutex`.
+    ///
+    /// 和 POSIX 一样允许虚假唤醒(例如被信号唤醒), 调用者需要在循环中重新检查条件.
+    /// `mutex`没有被锁上时返回`false`. `on_unlocked`和`on_locked`分别在释放和重新获得
+    /// `mutex`时调用, 见[`Mutex::unlock`]和[`Mutex::lock`]
+    pub fn wait(
+        &self,
+        mutex: &Mutex,
+        on_unlocked: impl FnOnce(),
+        on_locked: impl FnOnce(),
+    ) -> bool {
+        let mut inner = self.inner.exclusive_access();
+        inner.wait_queue.add_current();
+        drop(inner);
+        if !mutex.unlock(on_unlocked) {
+            self.inner.exclusive_access().wait_queue.remove_current();
+            return false;
+        }
+        if !current_has_interrupting_signal() {
+            block_current_and_run_next();
+        }
+        self.inner.exclusive_access().wait_queue.remove_current();
+        mutex.lock(false, on_locked);
+        true
+    }
+}
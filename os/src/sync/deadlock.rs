@@ -0,0 +1,154 @@
+//! 用银行家算法检测进程中互斥锁和信号量上的死锁
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// 进程中可以被线程持有的资源
+#[derive(Copy, Clone)]
+pub enum Resource {
+    /// id 为`.0`的互斥锁, 只有一个资源
+    Mutex(usize),
+    /// id 为`.0`的信号量, 资源数为它的计数
+    Semaphore(usize),
+}
+
+/// 一类资源的分配状态, 下标为资源 id 和 tid
+#[derive(Default)]
+struct ResourceState {
+    /// 每个资源剩余的数量
+    available: Vec<usize>,
+    /// 每个线程持有的数量
+    allocation: Vec<Vec<usize>>,
+    /// 每个线程正在等待的数量
+    need: Vec<Vec<usize>>,
+}
+
+impl ResourceState {
+    fn add(&mut self, id: usize, count: usize) {
+        if self.available.len() <= id {
+            self.available.resize(id + 1, 0);
+        }
+        self.available[id] = count;
+        for row in self.allocation.iter_mut().chain(self.need.iter_mut()) {
+            if row.len() <= id {
+                row.resize(id + 1, 0);
+            }
+            row[id] = 0;
+        }
+    }
+    fn ensure_thread(&mut self, tid: usize) {
+        let resources = self.available.len();
+        while self.allocation.len() <= tid {
+            self.allocation.push(vec![0; resources]);
+            self.need.push(vec![0; resources]);
+        }
+        self.allocation[tid].resize(resources, 0);
+        self.need[tid].resize(resources, 0);
+    }
+}
+
+/// 进程中所有线程对互斥锁和信号量的分配和等待情况.
+///
+/// 资源的获取和释放总是被记录下来, 只有打开检测之后才在等待资源之前做安全性检查.
+/// 银行家算法假设线程会释放自己获取的资源, 把信号量当作通知使用(由别的线程`up`)时可能误报
+#[derive(Default)]
+pub struct DeadlockDetector {
+    /// 是否在等待资源之前检测死锁
+    pub enabled: bool,
+    mutex: ResourceState,
+    semaphore: ResourceState,
+}
+
+impl DeadlockDetector {
+    fn state(&mut self, resource: Resource) -> (&mut ResourceState, usize) {
+        match resource {
+            Resource::Mutex(id) => (&mut self.mutex, id),
+            Resource::Semaphore(id) => (&mut self.semaphore, id),
+        }
+    }
+    /// 新建了拥有`count`个资源的`resource`
+    pub fn add_resource(&mut self, resource: Resource, count: usize) {
+        let (state, id) = self.state(resource);
+        state.add(id, count);
+    }
+    /// 线程`tid`要获取一个`resource`. 打开检测时, 如果等待它会使系统进入不安全状态,
+    /// 不记录这次请求并返回`false`
+    pub fn request(&mut self, tid: usize, resource: Resource) -> bool {
+        let (state, id) = self.state(resource);
+        state.ensure_thread(tid);
+        state.need[tid][id] += 1;
+        if self.enabled && !self.is_safe() {
+            let (state, id) = self.state(resource);
+            state.need[tid][id] -= 1;
+            return false;
+        }
+        true
+    }
+    /// 线程`tid`得到了一个`resource`, 通常是之前请求过的
+    pub fn acquire(&mut self, tid: usize, resource: Resource) {
+        let (state, id) = self.state(resource);
+        state.ensure_thread(tid);
+        if state.need[tid][id] > 0 {
+            state.need[tid][id] -= 1;
+        }
+        state.allocation[tid][id] += 1;
+        state.available[id] -= 1;
+    }
+    /// 线程`tid`放弃了之前请求的`resource`(例如被信号打断)
+    pub fn cancel(&mut self, tid: usize, resource: Resource) {
+        let (state, id) = self.state(resource);
+        state.need[tid][id] -= 1;
+    }
+    /// 线程`tid`释放了一个`resource`. 信号量可以由没有持有它的线程释放
+    pub fn release(&mut self, tid: usize, resource: Resource) {
+        let (state, id) = self.state(resource);
+        state.ensure_thread(tid);
+        state.available[id] += 1;
+        if state.allocation[tid][id] > 0 {
+            state.allocation[tid][id] -= 1;
+        }
+    }
+    /// 线程`tid`退出了, 清除它的记录. 它持有的资源不会再被它释放,
+    /// 之后复用这个 tid 的线程也不应该继承这些记录
+    pub fn remove_thread(&mut self, tid: usize) {
+        for state in [&mut self.mutex, &mut self.semaphore] {
+            for rows in [&mut state.allocation, &mut state.need] {
+                if let Some(row) = rows.get_mut(tid) {
+                    row.fill(0);
+                }
+            }
+        }
+    }
+    /// 银行家算法的安全性检查: 是否存在一个顺序, 使所有线程的等待都能被满足
+    fn is_safe(&self) -> bool {
+        let threads = self.mutex.need.len().max(self.semaphore.need.len());
+        let states = [&self.mutex, &self.semaphore];
+        let mut work: Vec<Vec<usize>> = states.iter().map(|s| s.available.clone()).collect();
+        let mut finish = vec![false; threads];
+        let row = |rows: &Vec<Vec<usize>>, tid: usize, id: usize| {
+            rows.get(tid)
+                .and_then(|row| row.get(id))
+                .copied()
+                .unwrap_or(0)
+        };
+        loop {
+            let next = (0..threads).find(|&tid| {
+                !finish[tid]
+                    && states.iter().zip(work.iter()).all(|(state, work)| {
+                        (0..work.len()).all(|id| row(&state.need, tid, id) <= work[id])
+                    })
+            });
+            match next {
+                Some(tid) => {
+                    finish[tid] = true;
+                    for (state, work) in states.iter().zip(work.iter_mut()) {
+                        for (id, count) in work.iter_mut().enumerate() {
+                            *count += row(&state.allocation, tid, id);
+                        }
+                    }
+                }
+                None => return finish.iter().all(|finished| *finished),
+            }
+        }
+    }
+}
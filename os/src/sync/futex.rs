@@ -0,0 +1,113 @@
+//! futex: 用户态锁在竞争时通过内核阻塞和唤醒.
+//!
+//! 共享映射中的 futex 以物理地址为键, 所以可以在进程之间使用
+
+use super::SpinLock;
+use crate::task::{
+    block_current_and_run_next, current_has_interrupting_signal, current_task, WaitQueue,
+};
+use crate::timer::{add_timer, get_time_ms, remove_timer};
+use alloc::collections::BTreeMap;
+use lazy_static::*;
+
+/// 区分 futex 的键
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum FutexKey {
+    /// 私有的 futex: (地址空间的 token, 虚拟地址). 页面被换出再换入时物理地址会变, 虚拟地址不变
+    Private(usize, usize),
+    /// 共享映射中的 futex: 物理地址. 共享映射的页面不会被换出
+    Shared(usize),
+}
+
+lazy_static! {
+    /// futex 的键 -> 在这个 futex 上等待的任务
+    static ref FUTEX_QUEUES: SpinLock<BTreeMap<FutexKey, WaitQueue>> =
+        SpinLock::new(BTreeMap::new());
+}
+
+/// [`futex_wait`]结束等待的原因
+pub enum FutexWaitResult {
+    /// 被[`futex_wake`]唤醒
+    Woken,
+    /// 到了`expire_ms`
+    TimedOut,
+    /// 被信号打断
+    Interrupted,
+    /// futex 的值不等于期望的值, 没有阻塞
+    ValueChanged,
+    /// futex 所在的页面不可访问, 没有阻塞
+    Fault,
+}
+
+/// `load`读到的 futex 的值等于`val`时在`key`上阻塞,
+/// 直到被[`futex_wake`]唤醒, 到达`expire_ms`或者收到信号.
+///
+/// 任务先加入等待队列, 然后在持有队列的锁时读取 futex 的值, 所以修改值之后调用的
+/// [`futex_wake`]不会错过它. `load`在页面不可访问时返回`None`
+pub fn futex_wait(
+    key: FutexKey,
+    val: u32,
+    load: impl FnOnce() -> Option<u32>,
+    expire_ms: Option<usize>,
+) -> FutexWaitResult {
+    if current_has_interrupting_signal() {
+        return FutexWaitResult::Interrupted;
+    }
+    let mut queues = FUTEX_QUEUES.exclusive_access();
+    let queue = queues.entry(key).or_insert_with(WaitQueue::new);
+    queue.add_current();
+    let value = load();
+    if value != Some(val) {
+        queue.remove_current();
+        if queue.is_empty() {
+            queues.remove(&key);
+        }
+        return match value {
+            Some(_) => FutexWaitResult::ValueChanged,
+            None => FutexWaitResult::Fault,
+        };
+    }
+    drop(queues);
+    if let Some(expire_ms) = expire_ms {
+        add_timer(expire_ms, current_task().unwrap());
+    }
+    block_current_and_run_next();
+    // still in the queue if woken up by the timer or a signal
+    let mut queues = FUTEX_QUEUES.exclusive_access();
+    let waiting = match queues.get_mut(&key) {
+        Some(queue) => {
+            let waiting = queue.remove_current();
+            if queue.is_empty() {
+                queues.remove(&key);
+            }
+            waiting
+        }
+        None => false,
+    };
+    drop(queues);
+    if expire_ms.is_some() {
+        remove_timer(&current_task().unwrap());
+    }
+    match expire_ms {
+        _ if !waiting => FutexWaitResult::Woken,
+        Some(expire_ms) if get_time_ms() >= expire_ms => FutexWaitResult::TimedOut,
+        _ => FutexWaitResult::Interrupted,
+    }
+}
+
+/// 唤醒最多`count`个在`key`上等待的任务, 返回唤醒的数量
+pub fn futex_wake(key: FutexKey, count: usize) -> usize {
+    let mut queues = FUTEX_QUEUES.exclusive_access();
+    let queue = match queues.get_mut(&key) {
+        Some(queue) => queue,
+        None => return 0,
+    };
+    let mut woken = 0;
+    while woken < count && queue.wake_one() {
+        woken += 1;
+    }
+    if queue.is_empty() {
+        queues.remove(&key);
+    }
+    woken
+}
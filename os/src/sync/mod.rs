@@ -1,5 +1,15 @@
 //! Synchronization and interior mutability primitives
 
-mod up;
+mod condvar;
+mod deadlock;
+mod futex;
+mod mutex;
+mod semaphore;
+mod spin;
 
-pub use up::UPSafeCell;
+pub use condvar::Condvar;
+pub use deadlock::{DeadlockDetector, Resource};
+pub use futex::{futex_wait, futex_wake, FutexKey, FutexWaitResult};
+pub use mutex::Mutex;
+pub use semaphore::Semaphore;
+pub use spin::{SpinLock, SpinLockGuard};
@@ -0,0 +1,66 @@
+//! 提供给用户程序的阻塞互斥锁
+
+use super::SpinLock;
+use crate::task::{
+    block_current_and_run_next, block_current_uninterruptible_and_run_next,
+    current_has_interrupting_signal, WaitQueue,
+};
+
+/// 阻塞互斥锁: 拿不到锁的任务在等待队列中阻塞, 而不是反复`yield`
+pub struct Mutex {
+    inner: SpinLock<MutexInner>,
+}
+
+struct MutexInner {
+    locked: bool,
+    wait_queue: WaitQueue,
+}
+
+impl Mutex {
+    /// 创建未上锁的互斥锁
+    pub fn new() -> Self {
+        Self {
+            inner: SpinLock::new(MutexInner {
+                locked: false,
+                wait_queue: WaitQueue::new(),
+            }),
+        }
+    }
+    /// 加锁. `interruptible`为`true`时, 阻塞期间收到信号则放弃加锁并返回`false`.
+    ///
+    /// 加锁成功时在释放内部的自旋锁之前调用`on_locked`, 它与锁状态的变化不可分割
+    pub fn lock(&self, interruptible: bool, on_locked: impl FnOnce()) -> bool {
+        loop {
+            let mut inner = self.inner.exclusive_access();
+            inner.wait_queue.remove_current();
+            if !inner.locked {
+                inner.locked = true;
+                on_locked();
+                return true;
+            }
+            if interruptible && current_has_interrupting_signal() {
+                return false;
+            }
+            inner.wait_queue.add_current();
+            drop(inner);
+            if interruptible {
+                block_current_and_run_next();
+            } else {
+                block_current_uninterruptible_and_run_next();
+            }
+        }
+    }
+    /// 解锁并唤醒一个等待的任务, 锁本来就没有被锁上时返回`false`.
+    ///
+    /// 解锁成功时与[`Mutex::lock`]一样调用`on_unlocked`
+    pub fn unlock(&self, on_unlocked: impl FnOnce()) -> bool {
+        let mut inner = self.inner.exclusive_access();
+        if !inner.locked {
+            return false;
+        }
+        inner.locked = false;
+        on_unlocked();
+        inner.wait_queue.wake_one();
+        true
+    }
+}
@@ -0,0 +1,55 @@
+//! 提供给用户程序的信号量
+
+use super::SpinLock;
+use crate::task::{block_current_and_run_next, current_has_interrupting_signal, WaitQueue};
+
+/// 计数信号量, 资源不足时任务在等待队列中阻塞
+pub struct Semaphore {
+    inner: SpinLock<SemaphoreInner>,
+}
+
+struct SemaphoreInner {
+    count: usize,
+    wait_queue: WaitQueue,
+}
+
+impl Semaphore {
+    /// 创建初始资源数为`res_count`的信号量
+    pub fn new(res_count: usize) -> Self {
+        Self {
+            inner: SpinLock::new(SemaphoreInner {
+                count: res_count,
+                wait_queue: WaitQueue::new(),
+            }),
+        }
+    }
+    /// V 操作: 释放一个资源, 唤醒一个等待的任务.
+    ///
+    /// 在释放内部的自旋锁之前调用`on_up`, 它与计数的变化不可分割
+    pub fn up(&self, on_up: impl FnOnce()) {
+        let mut inner = self.inner.exclusive_access();
+        inner.count += 1;
+        on_up();
+        inner.wait_queue.wake_one();
+    }
+    /// P 操作: 获取一个资源, 没有资源时阻塞. 阻塞期间收到信号则放弃并返回`false`.
+    ///
+    /// 获取成功时与[`Semaphore::up`]一样调用`on_acquired`
+    pub fn down(&self, on_acquired: impl FnOnce()) -> bool {
+        loop {
+            let mut inner = self.inner.exclusive_access();
+            inner.wait_queue.remove_current();
+            if inner.count > 0 {
+                inner.count -= 1;
+                on_acquired();
+                return true;
+            }
+            if current_has_interrupting_signal() {
+                return false;
+            }
+            inner.wait_queue.add_current();
+            drop(inner);
+            block_current_and_run_next();
+        }
+    }
+}
@@ -0,0 +1,88 @@
+//! Multiprocessor interior mutability primitives
+
+use crate::hart::hart_id;
+use core::cell::UnsafeCell;
+use core::hint::spin_loop;
+use core::ops::{Deref, DerefMut};
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+/// 没有 hart 持有锁时的 [`SpinLock::owner`]
+const NO_OWNER: usize = usize::MAX;
+
+/// 自旋锁, 保护被多个 hart 共享的数据.
+///
+/// 内核在关中断的状态下运行, 持有锁的代码不会被打断, 所以不需要在加锁时关中断.
+/// 锁不能在`__switch`前后一直持有.
+///
+/// 调用 [`SpinLock::exclusive_access`] 获得数据的可变引用. 同一个 hart 重复加锁一定会死锁,
+/// 这时直接 panic
+pub struct SpinLock<T> {
+    locked: AtomicBool,
+    /// 持有锁的 hart
+    owner: AtomicUsize,
+    /// inner data
+    inner: UnsafeCell<T>,
+}
+
+unsafe impl<T: Send> Sync for SpinLock<T> {}
+unsafe impl<T: Send> Send for SpinLock<T> {}
+
+impl<T> SpinLock<T> {
+    /// 创建未上锁的自旋锁
+    pub const fn new(value: T) -> Self {
+        Self {
+            locked: AtomicBool::new(false),
+            owner: AtomicUsize::new(NO_OWNER),
+            inner: UnsafeCell::new(value),
+        }
+    }
+    /// 加锁并获得数据的可变引用, 锁被其它 hart 持有时自旋等待
+    pub fn exclusive_access(&self) -> SpinLockGuard<'_, T> {
+        let hart = hart_id();
+        while self
+            .locked
+            .compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            if self.owner.load(Ordering::Relaxed) == hart {
+                panic!("SpinLock is already held by hart {}", hart);
+            }
+            spin_loop();
+        }
+        self.owner.store(hart, Ordering::Relaxed);
+        SpinLockGuard { lock: self }
+    }
+    /// 尝试加锁, 锁已经被持有(包括被当前 hart 持有)时不等待, 直接返回`None`
+    pub fn try_exclusive_access(&self) -> Option<SpinLockGuard<'_, T>> {
+        self.locked
+            .compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .ok()?;
+        self.owner.store(hart_id(), Ordering::Relaxed);
+        Some(SpinLockGuard { lock: self })
+    }
+}
+
+/// [`SpinLock::exclusive_access`] 返回的守卫, drop 时解锁
+pub struct SpinLockGuard<'a, T> {
+    lock: &'a SpinLock<T>,
+}
+
+impl<T> Deref for SpinLockGuard<'_, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        unsafe { &*self.lock.inner.get() }
+    }
+}
+
+impl<T> DerefMut for SpinLockGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.lock.inner.get() }
+    }
+}
+
+impl<T> Drop for SpinLockGuard<'_, T> {
+    fn drop(&mut self) {
+        self.lock.owner.store(NO_OWNER, Ordering::Relaxed);
+        self.lock.locked.store(false, Ordering::Release);
+    }
+}
@@ -0,0 +1,69 @@
+//! 系统调用的错误码, 与 Linux (RISC-V) 的编号一致
+//!
+//! 系统调用出错时返回错误码的相反数, 例如`-(Errno::EBADF as isize)`,
+//! 可以直接写成`Errno::EBADF.into()`
+
+/// 系统调用的错误码
+#[allow(unused)]
+#[repr(isize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Errno {
+    /// Operation not permitted
+    EPERM = 1,
+    /// No such file or directory
+    ENOENT = 2,
+    /// No such process
+    ESRCH = 3,
+    /// Interrupted system call
+    EINTR = 4,
+    /// I/O error
+    EIO = 5,
+    /// Argument list too long
+    E2BIG = 7,
+    /// Exec format error
+    ENOEXEC = 8,
+    /// Bad file number
+    EBADF = 9,
+    /// No child processes
+    ECHILD = 10,
+    /// Try again
+    EAGAIN = 11,
+    /// Out of memory
+    ENOMEM = 12,
+    /// Permission denied
+    EACCES = 13,
+    /// Bad address
+    EFAULT = 14,
+    /// Device or resource busy
+    EBUSY = 16,
+    /// File exists
+    EEXIST = 17,
+    /// No such device
+    ENODEV = 19,
+    /// Not a directory
+    ENOTDIR = 20,
+    /// Is a directory
+    EISDIR = 21,
+    /// Invalid argument
+    EINVAL = 22,
+    /// Too many open files
+    EMFILE = 24,
+    /// Illegal seek
+    ESPIPE = 29,
+    /// Broken pipe
+    EPIPE = 32,
+    /// Resource deadlock would occur
+    EDEADLK = 35,
+    /// File name too long
+    ENAMETOOLONG = 36,
+    /// Invalid system call number
+    ENOSYS = 38,
+    /// Connection timed out
+    ETIMEDOUT = 110,
+}
+
+impl From<Errno> for isize {
+    fn from(errno: Errno) -> Self {
+        -(errno as isize)
+    }
+}
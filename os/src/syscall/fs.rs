@@ -1,19 +1,22 @@
 //! File and filesystem-related syscalls
 
+use super::Errno;
 use crate::fs::{list_apps, make_pipe, open_file, OpenFlags};
-use crate::mm::{translated_byte_buffer, translated_refmut, translated_str, UserBuffer};
-use crate::task::{current_task, current_user_token};
+use crate::mm::{
+    try_translated_byte_buffer, try_translated_refmut, try_translated_str, UserBuffer,
+};
+use crate::task::{current_fault_in_str, current_process, current_user_token};
 use alloc::sync::Arc;
 
 /// 重定向
 pub fn sys_dup(fd: usize) -> isize {
-    let task = current_task().unwrap();
-    let mut inner = task.inner_exclusive_access();
+    let process = current_process();
+    let mut inner = process.inner_exclusive_access();
     if fd >= inner.fd_table.len() {
-        return -1;
+        return Errno::EBADF.into();
     }
     if inner.fd_table[fd].is_none() {
-        return -1;
+        return Errno::EBADF.into();
     }
     let new_fd = inner.alloc_fd();
     inner.fd_table[new_fd] = Some(Arc::clone(inner.fd_table[fd].as_ref().unwrap()));
@@ -23,84 +26,136 @@ pub fn sys_dup(fd: usize) -> isize {
 /// 向指定 `fd` 写入 `len` 长度的 `buf`
 pub fn sys_write(fd: usize, buf: *const u8, len: usize) -> isize {
     let token = current_user_token();
-    let task = current_task().unwrap();
-    let inner = task.inner_exclusive_access();
+    let process = current_process();
+    let mut inner = process.inner_exclusive_access();
     if fd >= inner.fd_table.len() {
-        return -1;
+        return Errno::EBADF.into();
     }
     if let Some(file) = &inner.fd_table[fd] {
         if !file.writable() {
-            return -1;
+            return Errno::EBADF.into();
         }
         let file = file.clone();
-        // release current task TCB manually to avoid multi-borrow
+        if !inner.memory_set.fault_in(buf as usize, len, false) {
+            return Errno::EFAULT.into();
+        }
+        let buffers = match try_translated_byte_buffer(token, buf, len, false) {
+            Ok(buffers) => buffers,
+            Err(_) => return Errno::EFAULT.into(),
+        };
+        // the file may block, keep the pages of `buf` from being swapped out meanwhile
+        inner.memory_set.pin(buf as usize, len);
+        // release current process PCB manually to avoid multi-borrow
         drop(inner);
-        file.write(UserBuffer::new(translated_byte_buffer(token, buf, len))) as isize
+        let ret = file.write(UserBuffer::new(buffers));
+        process
+            .inner_exclusive_access()
+            .memory_set
+            .unpin(buf as usize, len);
+        ret as isize
     } else {
-        -1
+        Errno::EBADF.into()
     }
 }
 
 /// 从指定 `fd` 读取 `len` 长度的 `buf`
 pub fn sys_read(fd: usize, buf: *const u8, len: usize) -> isize {
     let token = current_user_token();
-    let task = current_task().unwrap();
-    let inner = task.inner_exclusive_access();
+    let process = current_process();
+    let mut inner = process.inner_exclusive_access();
     if fd >= inner.fd_table.len() {
-        return -1;
+        return Errno::EBADF.into();
     }
     if let Some(file) = &inner.fd_table[fd] {
         let file = file.clone();
         if !file.readable() {
-            return -1;
+            return Errno::EBADF.into();
+        }
+        // the kernel writes into `buf` directly, populate it and break copy-on-write sharing first
+        if !inner.memory_set.fault_in(buf as usize, len, true) {
+            return Errno::EFAULT.into();
         }
-        // release current task TCB manually to avoid multi-borrow
+        let buffers = match try_translated_byte_buffer(token, buf, len, true) {
+            Ok(buffers) => buffers,
+            Err(_) => return Errno::EFAULT.into(),
+        };
+        // the file may block, keep the pages of `buf` from being swapped out meanwhile
+        inner.memory_set.pin(buf as usize, len);
+        // release current process PCB manually to avoid multi-borrow
         drop(inner);
-        file.read(UserBuffer::new(translated_byte_buffer(token, buf, len))) as isize
+        let ret = file.read(UserBuffer::new(buffers));
+        process
+            .inner_exclusive_access()
+            .memory_set
+            .unpin(buf as usize, len);
+        ret as isize
     } else {
-        -1
+        Errno::EBADF.into()
     }
 }
 
 /// 以`flags`为标记,打开路径为`path`的文件
 pub fn sys_open(path: *const u8, flags: u32) -> isize {
-    let task = current_task().unwrap();
+    let process = current_process();
     let token = current_user_token();
-    let path = translated_str(token, path);
-    if let Some(inode) = open_file(path.as_str(), OpenFlags::from_bits(flags).unwrap()) {
-        let mut inner = task.inner_exclusive_access();
+    if !current_fault_in_str(path as usize) {
+        return Errno::EFAULT.into();
+    }
+    let path = match try_translated_str(token, path) {
+        Ok(path) => path,
+        Err(_) => return Errno::EFAULT.into(),
+    };
+    let flags = match OpenFlags::from_bits(flags) {
+        Some(flags) => flags,
+        None => return Errno::EINVAL.into(),
+    };
+    if let Some(inode) = open_file(path.as_str(), flags) {
+        let mut inner = process.inner_exclusive_access();
         let fd = inner.alloc_fd();
         inner.fd_table[fd] = Some(inode);
         fd as isize
     } else {
-        -1
+        Errno::ENOENT.into()
     }
 }
 
 /// 建立pipe
 pub fn sys_pipe(pipe: *mut usize) -> isize {
-    let task = current_task().unwrap();
+    let process = current_process();
     let token = current_user_token();
-    let mut inner = task.inner_exclusive_access();
+    let mut inner = process.inner_exclusive_access();
+    if !inner
+        .memory_set
+        .fault_in(pipe as usize, 2 * core::mem::size_of::<usize>(), true)
+    {
+        return Errno::EFAULT.into();
+    }
+    let (read_end, write_end) = match (
+        try_translated_refmut(token, pipe),
+        try_translated_refmut(token, pipe.wrapping_add(1)),
+    ) {
+        (Ok(read_end), Ok(write_end)) => (read_end, write_end),
+        _ => return Errno::EFAULT.into(),
+    };
     let (pipe_read, pipe_write) = make_pipe();
     let read_fd = inner.alloc_fd();
     inner.fd_table[read_fd] = Some(pipe_read);
     let write_fd = inner.alloc_fd();
     inner.fd_table[write_fd] = Some(pipe_write);
-    *translated_refmut(token, pipe) = read_fd;
-    *translated_refmut(token, unsafe { pipe.add(1) }) = write_fd;
+    *read_end = read_fd;
+    *write_end = write_fd;
     0
 }
 
 /// 关闭文件`fd`
 pub fn sys_close(fd: usize) -> isize {
-    let task = current_task().unwrap();
-    let mut inner = task.inner_exclusive_access();
+    let process = current_process();
+    let mut inner = process.inner_exclusive_access();
     if fd >= inner.fd_table.len() {
-        return -1;
+        return Errno::EBADF.into();
     }
     if inner.fd_table[fd].is_none() {
-        return -1;
+        return Errno::EBADF.into();
     }
     inner.fd_table[fd].take();
     0
@@ -109,7 +164,13 @@ pub fn sys_close(fd: usize) -> isize {
 /// 显示"/"下面的所有文件
 pub fn sys_ls(path: *const u8) -> isize {
     let token = current_user_token();
-    let path = translated_str(token, path);
+    if !current_fault_in_str(path as usize) {
+        return Errno::EFAULT.into();
+    }
+    let path = match try_translated_str(token, path) {
+        Ok(path) => path,
+        Err(_) => return Errno::EFAULT.into(),
+    };
     println!("files under {:?}", path);
     list_apps();
     0
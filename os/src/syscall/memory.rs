@@ -0,0 +1,161 @@
+//! Memory mapping syscalls
+
+use super::Errno;
+use crate::config::{MMAP_TOP, PAGE_SIZE};
+use crate::mm::{MapPermission, VirtAddr};
+use crate::task::current_process;
+use bitflags::*;
+
+bitflags! {
+    /// mmap/mprotect 的访问权限
+    pub struct MmapProt: u32 {
+        /// 可读
+        const READ = 1 << 0;
+        /// 可写
+        const WRITE = 1 << 1;
+        /// 可执行
+        const EXEC = 1 << 2;
+    }
+}
+
+bitflags! {
+    /// mmap 的映射方式
+    pub struct MmapFlags: u32 {
+        /// 写入对共享这段映射的进程可见, 文件映射的写入会写回文件
+        const SHARED = 1 << 0;
+        /// 私有映射, 写入只对自己可见
+        const PRIVATE = 1 << 1;
+        /// 必须映射到指定的地址, 替换范围内已有的映射
+        const FIXED = 1 << 4;
+        /// 匿名映射, 不关联文件, 初始内容为0
+        const ANONYMOUS = 1 << 5;
+    }
+}
+
+impl From<MmapProt> for MapPermission {
+    fn from(prot: MmapProt) -> Self {
+        let mut map_perm = MapPermission::U;
+        // SV39 不支持只写的页, 可写的页一定可读
+        if prot.intersects(MmapProt::READ | MmapProt::WRITE) {
+            map_perm |= MapPermission::R;
+        }
+        if prot.contains(MmapProt::WRITE) {
+            map_perm |= MapPermission::W;
+        }
+        if prot.contains(MmapProt::EXEC) {
+            map_perm |= MapPermission::X;
+        }
+        map_perm
+    }
+}
+
+/// `[start, start + len)`是页对齐的, 非空的用户地址范围
+fn valid_range(start: usize, len: usize) -> bool {
+    start % PAGE_SIZE == 0 && len != 0 && len <= MMAP_TOP && start <= MMAP_TOP - len
+}
+
+/// 建立一段映射, 成功时返回映射的起始地址.
+/// 不带`MAP_FIXED`时忽略`addr`, 由内核选择地址; 带`MAP_FIXED`时先解除范围内已有的映射
+pub fn sys_mmap(addr: usize, len: usize, prot: u32, flags: u32, fd: usize, offset: usize) -> isize {
+    let (prot, flags) = match (MmapProt::from_bits(prot), MmapFlags::from_bits(flags)) {
+        (Some(prot), Some(flags)) => (prot, flags),
+        _ => return Errno::EINVAL.into(),
+    };
+    let shared = flags.contains(MmapFlags::SHARED);
+    if len == 0 || offset % PAGE_SIZE != 0 || shared == flags.contains(MmapFlags::PRIVATE) {
+        return Errno::EINVAL.into();
+    }
+    let start = if flags.contains(MmapFlags::FIXED) {
+        if !valid_range(addr, len) {
+            return Errno::EINVAL.into();
+        }
+        Some(addr)
+    } else {
+        None
+    };
+    let process = current_process();
+    let mut inner = process.inner_exclusive_access();
+    let file = if flags.contains(MmapFlags::ANONYMOUS) {
+        None
+    } else {
+        let file = match inner.fd_table.get(fd) {
+            Some(Some(file)) => file.clone(),
+            _ => return Errno::EBADF.into(),
+        };
+        let inode = match file.backing_inode() {
+            Some(inode) => inode,
+            None => return Errno::ENODEV.into(),
+        };
+        // 共享的可写映射会写回文件, 文件必须以可写方式打开
+        if !file.readable() || (shared && prot.contains(MmapProt::WRITE) && !file.writable()) {
+            return Errno::EACCES.into();
+        }
+        Some((inode, offset, file.writable()))
+    };
+    // `MAP_FIXED`替换重叠部分原有的映射, 范围内有用户不可访问的逻辑段时失败
+    if start.is_some() && !inner.memory_set.munmap(addr, len) {
+        return Errno::EINVAL.into();
+    }
+    match inner.memory_set.mmap(start, len, prot.into(), file, shared) {
+        Some(start) => start as isize,
+        None => Errno::ENOMEM.into(),
+    }
+}
+
+/// 解除`[addr, addr + len)`的映射, 可以只解除一段映射的一部分. 范围与堆重叠时返回`-EINVAL`
+pub fn sys_munmap(addr: usize, len: usize) -> isize {
+    if !valid_range(addr, len) {
+        return Errno::EINVAL.into();
+    }
+    let process = current_process();
+    let mut inner = process.inner_exclusive_access();
+    if inner.memory_set.munmap(addr, len) {
+        0
+    } else {
+        Errno::EINVAL.into()
+    }
+}
+
+/// 修改`[addr, addr + len)`的访问权限
+pub fn sys_mprotect(addr: usize, len: usize, prot: u32) -> isize {
+    let prot = match MmapProt::from_bits(prot) {
+        Some(prot) => prot,
+        None => return Errno::EINVAL.into(),
+    };
+    if !valid_range(addr, len) {
+        return Errno::EINVAL.into();
+    }
+    let process = current_process();
+    let mut inner = process.inner_exclusive_access();
+    // 与`sys_mmap`相同, 只读打开的文件的共享映射不能变为可写
+    if prot.contains(MmapProt::WRITE) && !inner.memory_set.file_writable(addr, len) {
+        return Errno::EACCES.into();
+    }
+    if inner.memory_set.mprotect(addr, len, prot.into()) {
+        0
+    } else {
+        Errno::ENOMEM.into()
+    }
+}
+
+/// 把 program break 设置为`addr`, `addr`为0时只查询.
+/// 返回新的 program break, 失败时返回原来的 program break
+pub fn sys_brk(addr: usize) -> isize {
+    let process = current_process();
+    let mut inner = process.inner_exclusive_access();
+    let old_brk = inner.program_brk;
+    if addr < inner.heap_bottom || addr > MMAP_TOP {
+        return old_brk as isize;
+    }
+    let result = if addr < old_brk {
+        inner.memory_set.shrink_to(VirtAddr::from(addr))
+    } else {
+        inner.memory_set.append_to(VirtAddr::from(addr))
+    };
+    if result {
+        inner.program_brk = addr;
+        addr as isize
+    } else {
+        old_brk as isize
+    }
+}
@@ -17,28 +17,70 @@ const SYSCALL_PIPE: usize = 59;
 const SYSCALL_READ: usize = 63;
 const SYSCALL_WRITE: usize = 64;
 const SYSCALL_EXIT: usize = 93;
+const SYSCALL_FUTEX: usize = 98;
+const SYSCALL_NANOSLEEP: usize = 101;
+const SYSCALL_GETITIMER: usize = 102;
+const SYSCALL_SETITIMER: usize = 103;
 const SYSCALL_YIELD: usize = 124;
 const SYSCALL_KILL: usize = 129;
+const SYSCALL_SIGALTSTACK: usize = 132;
 const SYSCALL_SIGACTION: usize = 134;
 const SYSCALL_SIGPROCMASK: usize = 135;
+const SYSCALL_SIGQUEUE: usize = 138;
 const SYSCALL_SIGRETURN: usize = 139;
+const SYSCALL_SET_PRIORITY: usize = 140;
 const SYSCALL_GET_TIME: usize = 169;
 const SYSCALL_GETPID: usize = 172;
+const SYSCALL_BRK: usize = 214;
+const SYSCALL_MUNMAP: usize = 215;
 const SYSCALL_FORK: usize = 220;
 const SYSCALL_EXEC: usize = 221;
+const SYSCALL_MMAP: usize = 222;
+const SYSCALL_MPROTECT: usize = 226;
 const SYSCALL_WAITPID: usize = 260;
+const SYSCALL_ENABLE_DEADLOCK_DETECT: usize = 469;
+const SYSCALL_THREAD_CREATE: usize = 1000;
+const SYSCALL_GETTID: usize = 1001;
+const SYSCALL_WAITTID: usize = 1002;
+const SYSCALL_MUTEX_CREATE: usize = 1010;
+const SYSCALL_MUTEX_LOCK: usize = 1011;
+const SYSCALL_MUTEX_UNLOCK: usize = 1012;
+const SYSCALL_SEMAPHORE_CREATE: usize = 1020;
+const SYSCALL_SEMAPHORE_UP: usize = 1021;
+const SYSCALL_SEMAPHORE_DOWN: usize = 1022;
+const SYSCALL_CONDVAR_CREATE: usize = 1030;
+const SYSCALL_CONDVAR_SIGNAL: usize = 1031;
+const SYSCALL_CONDVAR_WAIT: usize = 1032;
+// Linux 的 setpriority(140) 已经被 set_priority 使用
+const SYSCALL_SETPRIORITY: usize = 1040;
+const SYSCALL_CPU_TICKS: usize = 1050;
+const SYSCALL_SLEEP: usize = 1060;
+// RISC-V 上的 Linux 没有 alarm
+const SYSCALL_ALARM: usize = 1070;
 
 const SYSCALL_LS: usize = 22;
 
+mod errno;
 mod fs;
+mod memory;
 mod process;
+mod sync;
+mod thread;
 
-use crate::task::SignalAction;
+use crate::config::SIGSYS_ON_UNKNOWN_SYSCALL;
+use crate::task::{
+    current_process, CpuTicks, SignalAction, SignalFlags, SignalInfo, SignalStack, SI_KERNEL,
+};
+use crate::timer::{ITimerVal, TimeSpec};
+use errno::Errno;
 use fs::*;
+use memory::*;
 use process::*;
+use sync::*;
+use thread::*;
 
 /// 根据 `syscall_id` 处理系统调用
-pub fn syscall(syscall_id: usize, args: [usize; 3]) -> isize {
+pub fn syscall(syscall_id: usize, args: [usize; 6]) -> isize {
     match syscall_id {
         SYSCALL_DUP => sys_dup(args[0]),
         SYSCALL_OPEN => sys_open(args[0] as *const u8, args[1] as u32),
@@ -55,15 +97,31 @@ pub fn syscall(syscall_id: usize, args: [usize; 3]) -> isize {
         SYSCALL_YIELD => sys_yield(),
         // signal
         SYSCALL_KILL => sys_kill(args[0], args[1] as i32),
+        SYSCALL_SIGALTSTACK => {
+            sys_sigaltstack(args[0] as *const SignalStack, args[1] as *mut SignalStack)
+        }
         SYSCALL_SIGACTION => sys_sigaction(
             args[0] as i32,
             args[1] as *const SignalAction,
             args[2] as *mut SignalAction,
         ),
-        SYSCALL_SIGPROCMASK => sys_sigprocmask(args[0] as u32),
+        SYSCALL_SIGPROCMASK => sys_sigprocmask(args[0] as u64),
+        SYSCALL_SIGQUEUE => sys_sigqueue(args[0], args[1] as i32, args[2]),
         SYSCALL_SIGRETURN => sys_sigretrun(),
+        SYSCALL_SET_PRIORITY => sys_set_priority(args[0] as isize),
+        SYSCALL_SETPRIORITY => sys_setpriority(args[0], args[1], args[2] as isize),
         // 获取时间
         SYSCALL_GET_TIME => sys_get_time(),
+        SYSCALL_CPU_TICKS => sys_cpu_ticks(args[0] as *mut CpuTicks),
+        SYSCALL_SLEEP => sys_sleep(args[0]),
+        SYSCALL_NANOSLEEP => sys_nanosleep(args[0] as *const TimeSpec, args[1] as *mut TimeSpec),
+        SYSCALL_GETITIMER => sys_getitimer(args[0], args[1] as *mut ITimerVal),
+        SYSCALL_SETITIMER => sys_setitimer(
+            args[0],
+            args[1] as *const ITimerVal,
+            args[2] as *mut ITimerVal,
+        ),
+        SYSCALL_ALARM => sys_alarm(args[0]),
         // 获取pid
         SYSCALL_GETPID => sys_getpid(),
         // fork
@@ -71,8 +129,54 @@ pub fn syscall(syscall_id: usize, args: [usize; 3]) -> isize {
         // exec
         SYSCALL_EXEC => sys_exec(args[0] as *const u8, args[1] as *const usize),
         // waitpid
-        SYSCALL_WAITPID => sys_waitpid(args[0] as isize, args[1] as *mut i32),
+        SYSCALL_WAITPID => sys_waitpid(args[0] as isize, args[1] as *mut i32, args[2]),
+        // 堆
+        SYSCALL_BRK => sys_brk(args[0]),
+        // 内存映射
+        SYSCALL_MMAP => sys_mmap(
+            args[0],
+            args[1],
+            args[2] as u32,
+            args[3] as u32,
+            args[4],
+            args[5],
+        ),
+        SYSCALL_MUNMAP => sys_munmap(args[0], args[1]),
+        SYSCALL_MPROTECT => sys_mprotect(args[0], args[1], args[2] as u32),
+        // 线程
+        SYSCALL_THREAD_CREATE => sys_thread_create(args[0], args[1]),
+        SYSCALL_GETTID => sys_gettid(),
+        SYSCALL_WAITTID => sys_waittid(args[0]),
+        // 同步
+        SYSCALL_MUTEX_CREATE => sys_mutex_create(),
+        SYSCALL_MUTEX_LOCK => sys_mutex_lock(args[0]),
+        SYSCALL_MUTEX_UNLOCK => sys_mutex_unlock(args[0]),
+        SYSCALL_SEMAPHORE_CREATE => sys_semaphore_create(args[0]),
+        SYSCALL_SEMAPHORE_UP => sys_semaphore_up(args[0]),
+        SYSCALL_SEMAPHORE_DOWN => sys_semaphore_down(args[0]),
+        SYSCALL_CONDVAR_CREATE => sys_condvar_create(),
+        SYSCALL_CONDVAR_SIGNAL => sys_condvar_signal(args[0]),
+        SYSCALL_CONDVAR_WAIT => sys_condvar_wait(args[0], args[1]),
+        SYSCALL_ENABLE_DEADLOCK_DETECT => sys_enable_deadlock_detect(args[0]),
+        SYSCALL_FUTEX => sys_futex(args[0] as *const u32, args[1], args[2], args[3]),
         // 无法识别的id
-        _ => panic!("Unsupported syscall_id: {}", syscall_id),
+        _ => sys_unknown(syscall_id),
+    }
+}
+
+/// 拒绝无法识别的系统调用: 记录次数, 按配置发送 SIGSYS, 返回`-ENOSYS`
+fn sys_unknown(syscall_id: usize) -> isize {
+    let process = current_process();
+    let mut inner = process.inner_exclusive_access();
+    inner.rejected_syscalls += 1;
+    warn!(
+        "[kernel] pid {} called unsupported syscall {}, {} rejected so far",
+        process.getpid(),
+        syscall_id,
+        inner.rejected_syscalls
+    );
+    if SIGSYS_ON_UNKNOWN_SYSCALL {
+        inner.add_signal(SignalInfo::new(SignalFlags::SIGSYS, SI_KERNEL));
     }
+    Errno::ENOSYS.into()
 }
@@ -1,16 +1,26 @@
 //! App management syscalls
+use super::Errno;
+use crate::config::USER_STACK_SIZE;
 use crate::fs::{open_file, OpenFlags};
-use crate::mm::{translated_ref, translated_refmut, translated_str};
+use crate::mm::{
+    try_copy_from_user, try_translated_ref, try_translated_refmut, try_translated_str, MemorySet,
+};
 use crate::task::{
-    add_task, current_task, current_user_token, exit_current_and_run_next, pid2task,
-    suspend_current_and_run_next, SignalAction, SignalFlags, MAX_SIG,
+    block_current_and_run_next, cpu_ticks, current_fault_in, current_fault_in_str,
+    current_getitimer, current_process, current_setitimer, current_task, current_trap_cx,
+    current_user_token, exit_current_and_run_next, mark_current_blocked, pid2process,
+    suspend_current_and_run_next, wakeup_process, CpuTicks, ProcessControlBlock, SignalAction,
+    SignalFlags, SignalFrame, SignalInfo, SignalStack, ITIMER_PROF, ITIMER_REAL, MAX_NICE, MAX_SIG,
+    MINSIGSTKSZ, MIN_NICE, SI_QUEUE, SI_USER, SS_DISABLE, SS_ONSTACK,
+};
+use crate::timer::{
+    get_time_ms, sleep_until, ITimerVal, TimeSpec, TimeVal, NSEC_PER_SEC, USEC_PER_SEC,
 };
-use crate::timer::get_time_ms;
 use alloc::string::String;
 use alloc::sync::Arc;
 use alloc::vec::Vec;
 
-/// task exits and submit an exit code
+/// thread exits and submit an exit code, 主线程退出时整个进程退出
 pub fn sys_exit(exit_code: i32) -> ! {
     exit_current_and_run_next(exit_code);
     panic!("Unreachable in sys_exit!");
@@ -22,144 +32,468 @@ pub fn sys_yield() -> isize {
     0
 }
 
+/// 设置当前线程的优先级, `prio`至少为 2. 只有 stride 调度器使用优先级
+pub fn sys_set_priority(prio: isize) -> isize {
+    if prio < 2 {
+        return Errno::EINVAL.into();
+    }
+    let task = current_task().unwrap();
+    task.inner_exclusive_access()
+        .sched
+        .set_priority(prio as usize);
+    prio
+}
+
+/// `sys_setpriority`的`which`, 目前只支持按进程设置
+const PRIO_PROCESS: usize = 0;
+
+/// 设置 nice 值, 超出`MIN_NICE..=MAX_NICE`的值被截断. 只有完全公平调度器使用 nice 值.
+/// `who`为 0 时设置当前线程, 否则设置进程`who`的所有线程
+pub fn sys_setpriority(which: usize, who: usize, nice: isize) -> isize {
+    if which != PRIO_PROCESS {
+        return Errno::EINVAL.into();
+    }
+    let nice = nice.clamp(MIN_NICE as isize, MAX_NICE as isize) as i32;
+    if who == 0 {
+        let task = current_task().unwrap();
+        task.inner_exclusive_access().sched.set_nice(nice);
+        return 0;
+    }
+    let process = match pid2process(who) {
+        Some(process) => process,
+        None => return Errno::ESRCH.into(),
+    };
+    let inner = process.inner_exclusive_access();
+    for task in inner.tasks.iter().flatten() {
+        task.inner_exclusive_access().sched.set_nice(nice);
+    }
+    0
+}
+
 /// 获取时间
 pub fn sys_get_time() -> isize {
     get_time_ms() as isize
 }
 
+/// 睡眠`ms`毫秒, 被信号打断时返回`-EINTR`
+pub fn sys_sleep(ms: usize) -> isize {
+    if sleep_until(get_time_ms().saturating_add(ms)) {
+        0
+    } else {
+        Errno::EINTR.into()
+    }
+}
+
+/// 睡眠`req`指定的时间, 向上取整到毫秒. 被信号打断时返回`-EINTR`,
+/// `rem`不为空时把剩余的时间写到`rem`
+pub fn sys_nanosleep(req: *const TimeSpec, rem: *mut TimeSpec) -> isize {
+    if !current_fault_in(req as usize, core::mem::size_of::<TimeSpec>(), false) {
+        return Errno::EFAULT.into();
+    }
+    let req = match try_translated_ref(current_user_token(), req) {
+        Ok(req) => *req,
+        Err(_) => return Errno::EFAULT.into(),
+    };
+    if req.nsec >= NSEC_PER_SEC {
+        return Errno::EINVAL.into();
+    }
+    let expire_ms = get_time_ms().saturating_add(req.to_ms());
+    if sleep_until(expire_ms) {
+        return 0;
+    }
+    if !rem.is_null() {
+        if !current_fault_in(rem as usize, core::mem::size_of::<TimeSpec>(), true) {
+            return Errno::EFAULT.into();
+        }
+        match try_translated_refmut(current_user_token(), rem) {
+            Ok(rem) => *rem = TimeSpec::from_ms(expire_ms.saturating_sub(get_time_ms())),
+            Err(_) => return Errno::EFAULT.into(),
+        }
+    }
+    Errno::EINTR.into()
+}
+
+/// 把当前进程的间隔定时器`which`写到`curr_value`
+pub fn sys_getitimer(which: usize, curr_value: *mut ITimerVal) -> isize {
+    if which > ITIMER_PROF {
+        return Errno::EINVAL.into();
+    }
+    if !current_fault_in(curr_value as usize, core::mem::size_of::<ITimerVal>(), true) {
+        return Errno::EFAULT.into();
+    }
+    match try_translated_refmut(current_user_token(), curr_value) {
+        Ok(curr_value) => {
+            *curr_value = current_getitimer(which);
+            0
+        }
+        Err(_) => Errno::EFAULT.into(),
+    }
+}
+
+/// 设置当前进程的间隔定时器`which`, `old_value`不为空时把原来的设置写到`old_value`
+pub fn sys_setitimer(
+    which: usize,
+    new_value: *const ITimerVal,
+    old_value: *mut ITimerVal,
+) -> isize {
+    if which > ITIMER_PROF {
+        return Errno::EINVAL.into();
+    }
+    if !current_fault_in(new_value as usize, core::mem::size_of::<ITimerVal>(), false) {
+        return Errno::EFAULT.into();
+    }
+    let new_value = match try_translated_ref(current_user_token(), new_value) {
+        Ok(new_value) => *new_value,
+        Err(_) => return Errno::EFAULT.into(),
+    };
+    if new_value.value.usec >= USEC_PER_SEC || new_value.interval.usec >= USEC_PER_SEC {
+        return Errno::EINVAL.into();
+    }
+    // check the user pointer before the timer is changed
+    let old_value = if old_value.is_null() {
+        None
+    } else {
+        if !current_fault_in(old_value as usize, core::mem::size_of::<ITimerVal>(), true) {
+            return Errno::EFAULT.into();
+        }
+        match try_translated_refmut(current_user_token(), old_value) {
+            Ok(old_value) => Some(old_value),
+            Err(_) => return Errno::EFAULT.into(),
+        }
+    };
+    let old = current_setitimer(which, &new_value);
+    if let Some(old_value) = old_value {
+        *old_value = old;
+    }
+    0
+}
+
+/// `seconds`秒之后给当前进程发送 SIGALRM, 为 0 时取消. 返回之前的 alarm 剩余的秒数(向上取整)
+pub fn sys_alarm(seconds: usize) -> isize {
+    let new = ITimerVal {
+        interval: TimeVal::default(),
+        value: TimeVal {
+            sec: seconds,
+            usec: 0,
+        },
+    };
+    let old = current_setitimer(ITIMER_REAL, &new).value;
+    (old.sec + (old.usec != 0) as usize) as isize
+}
+
+/// 把所有 hart 的空闲和忙碌时钟中断次数写到`ticks`
+pub fn sys_cpu_ticks(ticks: *mut CpuTicks) -> isize {
+    if !current_fault_in(ticks as usize, core::mem::size_of::<CpuTicks>(), true) {
+        return Errno::EFAULT.into();
+    }
+    match try_translated_refmut(current_user_token(), ticks) {
+        Ok(ticks) => {
+            *ticks = cpu_ticks();
+            0
+        }
+        Err(_) => Errno::EFAULT.into(),
+    }
+}
+
 /// 获取pid
 pub fn sys_getpid() -> isize {
-    current_task().unwrap().pid.0 as isize
+    current_process().getpid() as isize
 }
 
-/// fork
+/// fork, 子进程中只有调用的线程的副本, 它成为子进程的主线程; 内存不足时返回`-ENOMEM`
 pub fn sys_fork() -> isize {
-    let current_task = current_task().unwrap();
-    let new_task = current_task.fork();
-    let new_pid = new_task.pid.0;
+    let current_process = current_process();
+    let new_process = match current_process.fork() {
+        Some(new_process) => new_process,
+        None => return Errno::ENOMEM.into(),
+    };
+    let new_pid = new_process.getpid();
     trace!("fork: {}", new_pid);
-    // modify trap context of new_task, because it returns immediately after switching
+    // modify trap context of the main thread of new_process, because it returns immediately after switching
+    let new_task = new_process.inner_exclusive_access().get_task(0).unwrap();
     let trap_cx = new_task.inner_exclusive_access().get_trap_cx();
     // we do not have to move to next instruction since we have done it before
     // for child process, fork returns 0
     trap_cx.x[10] = 0;
-    // add new task to scheduler
-    add_task(new_task);
     // 把子进程的pid回给父进程
     new_pid as isize
 }
 
-/// exec
+/// exec, 进程的其它线程都被终止, 调用的线程成为新程序的主线程
+///
+/// 文件不存在返回`-ENOENT`, 不是 ELF 文件返回`-ENOEXEC`, 参数放不下用户栈返回`-E2BIG`
 pub fn sys_exec(path: *const u8, mut args: *const usize) -> isize {
     let token = current_user_token();
-    let path = translated_str(token, path);
+    if !current_fault_in_str(path as usize) {
+        return Errno::EFAULT.into();
+    }
+    let path = match try_translated_str(token, path) {
+        Ok(path) => path,
+        Err(_) => return Errno::EFAULT.into(),
+    };
     let mut args_vec: Vec<String> = Vec::new();
     loop {
-        let arg_str_ptr = *translated_ref(token, args);
+        if !current_fault_in(args as usize, core::mem::size_of::<usize>(), false) {
+            return Errno::EFAULT.into();
+        }
+        let arg_str_ptr = match try_translated_ref(token, args) {
+            Ok(arg_str_ptr) => *arg_str_ptr,
+            Err(_) => return Errno::EFAULT.into(),
+        };
         if arg_str_ptr == 0 {
             break;
         }
-        args_vec.push(translated_str(token, arg_str_ptr as *const u8));
-        unsafe {
-            args = args.add(1);
+        if !current_fault_in_str(arg_str_ptr) {
+            return Errno::EFAULT.into();
+        }
+        match try_translated_str(token, arg_str_ptr as *const u8) {
+            Ok(arg) => args_vec.push(arg),
+            Err(_) => return Errno::EFAULT.into(),
         }
+        args = args.wrapping_add(1);
     }
     trace!("exec: {}", path);
-    if let Some(app_inode) = open_file(path.as_str(), OpenFlags::RDONLY) {
-        let all_data = app_inode.read_all();
-        let task = current_task().unwrap();
-        let argc = args_vec.len();
-        task.exec(all_data.as_slice(), args_vec);
-        argc as isize
-    } else {
-        -1
+    // 参数和对齐的空间都要放在新程序的用户栈上
+    if ProcessControlBlock::args_size(&args_vec) + core::mem::size_of::<usize>() > USER_STACK_SIZE {
+        return Errno::E2BIG.into();
     }
+    let app_inode = match open_file(path.as_str(), OpenFlags::RDONLY) {
+        Some(app_inode) => app_inode,
+        None => return Errno::ENOENT.into(),
+    };
+    // 在终止其它线程之前构造新程序的地址空间, 不是合法的 ELF 文件时进程不受影响
+    let elf_data = app_inode.read_all();
+    let elf = match MemorySet::from_elf(elf_data.as_slice(), app_inode.inode()) {
+        Ok(elf) => elf,
+        Err(_) => return Errno::ENOEXEC.into(),
+    };
+    let argc = args_vec.len();
+    current_process().exec(elf, args_vec);
+    argc as isize
 }
 
-/// If there is not a child process whose pid is same as given, return -1.
-/// Else if there is a child process but it is still running, return -2.
-pub fn sys_waitpid(pid: isize, exit_code_ptr: *mut i32) -> isize {
-    let task = current_task().unwrap();
-    // find a child process
-
-    // ---- access current TCB exclusively
-    let mut inner = task.inner_exclusive_access();
-    if !inner
-        .children
-        .iter()
-        .any(|p| pid == -1 || pid as usize == p.getpid())
-    {
-        return -1;
-        // ---- release current PCB
-    }
-    let pair = inner.children.iter().enumerate().find(|(_, p)| {
-        // ++++ temporarily access child PCB lock exclusively
-        p.inner_exclusive_access().is_zombie() && (pid == -1 || pid as usize == p.getpid())
-        // ++++ release child PCB
-    });
-    if let Some((idx, _)) = pair {
-        let child = inner.children.remove(idx);
-        // confirm that child will be deallocated after removing from children list
-        assert_eq!(Arc::strong_count(&child), 1);
-        let found_pid = child.getpid();
-        // ++++ temporarily access child TCB exclusively
-        let exit_code = child.inner_exclusive_access().exit_code;
-        // ++++ release child PCB
-        *translated_refmut(inner.memory_set.token(), exit_code_ptr) = exit_code;
-        found_pid as isize
-    } else {
-        -2
+/// `sys_waitpid`的选项: 没有已经结束的子进程时立即返回0, 而不是阻塞
+const WNOHANG: usize = 1;
+
+/// 等待子进程结束, 回收它并把 wait status 写入`exit_code_ptr`(为空时不写).
+///
+/// If there is not a child process whose pid is same as given, return `-ECHILD`.
+/// Else if the children are still running, block until one of them exits,
+/// or return 0 at once with `WNOHANG`.
+/// Return `-EINTR` if a signal arrives while blocking.
+pub fn sys_waitpid(pid: isize, exit_code_ptr: *mut i32, options: usize) -> isize {
+    if options & !WNOHANG != 0 {
+        return Errno::EINVAL.into();
     }
-    // ---- release current PCB lock automatically
-}
+    loop {
+        let process = current_process();
+        // find a child process
 
-/// kill实现
-pub fn sys_kill(pid: usize, signum: i32) -> isize {
-    if let Some(task) = pid2task(pid) {
-        if let Some(flag) = SignalFlags::from_bits(1 << signum) {
-            // insert the signal if legal
-            let mut task_ref = task.inner_exclusive_access();
-            if task_ref.signals.contains(flag) {
-                return -1;
+        // ---- access current PCB exclusively
+        let mut inner = process.inner_exclusive_access();
+        if !inner
+            .children
+            .iter()
+            .any(|p| pid == -1 || pid as usize == p.getpid())
+        {
+            return Errno::ECHILD.into();
+            // ---- release current PCB
+        }
+        let pair = inner.children.iter().enumerate().find(|(_, p)| {
+            // ++++ temporarily access child PCB lock exclusively
+            p.inner_exclusive_access().is_zombie && (pid == -1 || pid as usize == p.getpid())
+            // ++++ release child PCB
+        });
+        if let Some((idx, _)) = pair {
+            // check the user pointer before reaping, so that the child can still be waited for.
+            // a null `exit_code_ptr` means the exit code is not wanted
+            let exit_code_ref = if exit_code_ptr.is_null() {
+                None
+            } else {
+                if !inner.memory_set.fault_in(
+                    exit_code_ptr as usize,
+                    core::mem::size_of::<i32>(),
+                    true,
+                ) {
+                    return Errno::EFAULT.into();
+                }
+                match try_translated_refmut(inner.memory_set.token(), exit_code_ptr) {
+                    Ok(exit_code_ref) => Some(exit_code_ref),
+                    Err(_) => return Errno::EFAULT.into(),
+                }
+            };
+            // the child is deallocated when the last reference is dropped, its exiting thread
+            // on another hart may still be releasing its resources
+            let child = inner.children.remove(idx);
+            let found_pid = child.getpid();
+            // ++++ temporarily access child PCB exclusively
+            if let Some(exit_code_ref) = exit_code_ref {
+                *exit_code_ref = child.inner_exclusive_access().exit_code;
             }
-            task_ref.signals.insert(flag);
-            0
-        } else {
-            -1
+            // ++++ release child PCB
+            return found_pid as isize;
         }
-    } else {
-        -1
+        if options & WNOHANG != 0 {
+            return 0;
+        }
+        if inner.has_interrupting_signal() {
+            return Errno::EINTR.into();
+        }
+        // an exiting child locks this PCB before waking us up
+        mark_current_blocked();
+        drop(inner);
+        drop(process);
+        // woken up by an exiting child or a signal
+        block_current_and_run_next();
     }
 }
 
-/// 设置要阻止的信号
-pub fn sys_sigprocmask(mask: u32) -> isize {
-    if let Some(task) = current_task() {
-        let mut inner = task.inner_exclusive_access();
-        let old_mask = inner.signal_mask;
-        if let Some(flag) = SignalFlags::from_bits(mask) {
-            inner.signal_mask = flag;
-            old_mask.bits() as isize
-        } else {
-            -1
-        }
+/// 给进程`pid`发送信号, 信号队列已满时返回`EAGAIN`
+fn send_signal(pid: usize, info: SignalInfo) -> isize {
+    let process = match pid2process(pid) {
+        Some(process) => process,
+        None => return Errno::ESRCH.into(),
+    };
+    if !process.inner_exclusive_access().add_signal(info) {
+        return Errno::EAGAIN.into();
+    }
+    // interrupt blocking syscalls
+    wakeup_process(&process);
+    0
+}
+
+/// `signum`为 0 时不发送信号, 只检查进程`pid`是否存在
+fn check_process(pid: usize) -> isize {
+    match pid2process(pid) {
+        Some(_) => 0,
+        None => Errno::ESRCH.into(),
+    }
+}
+
+/// kill实现. 普通信号已经在等待时与它合并, 实时信号则排队.
+/// `signum`为 0 时只检查进程是否存在
+pub fn sys_kill(pid: usize, signum: i32) -> isize {
+    if signum as usize > MAX_SIG {
+        return Errno::EINVAL.into();
+    }
+    if signum == 0 {
+        return check_process(pid);
+    }
+    let flag = SignalFlags::from_bits_truncate(1 << signum);
+    send_signal(
+        pid,
+        SignalInfo {
+            pid: current_process().getpid() as u32,
+            ..SignalInfo::new(flag, SI_USER)
+        },
+    )
+}
+
+/// 发送附带整数或者指针`value`的信号, 信号处理函数从`SignalInfo::value`中得到它.
+/// `signum`为 0 时与[`sys_kill`]一样只检查进程是否存在
+pub fn sys_sigqueue(pid: usize, signum: i32, value: usize) -> isize {
+    if signum as usize > MAX_SIG {
+        return Errno::EINVAL.into();
+    }
+    if signum == 0 {
+        return check_process(pid);
+    }
+    let flag = SignalFlags::from_bits_truncate(1 << signum);
+    send_signal(
+        pid,
+        SignalInfo {
+            pid: current_process().getpid() as u32,
+            value,
+            ..SignalInfo::new(flag, SI_QUEUE)
+        },
+    )
+}
+
+/// 设置要阻止的信号. SIGKILL 和 SIGSTOP 不能被阻止
+pub fn sys_sigprocmask(mask: u64) -> isize {
+    let process = current_process();
+    let mut inner = process.inner_exclusive_access();
+    let old_mask = inner.signal_mask;
+    if let Some(flag) = SignalFlags::from_bits(mask) {
+        inner.signal_mask = flag - (SignalFlags::SIGKILL | SignalFlags::SIGSTOP);
+        old_mask.bits() as isize
     } else {
-        -1
+        Errno::EINVAL.into()
     }
 }
 
-/// 清除堆栈帧，从信号处理例程返回
+/// 从信号处理函数返回, 恢复最内层的信号帧中保存的寄存器和信号掩码.
+/// 返回被打断时的`a0`, 使它不被系统调用的返回值覆盖
 pub fn sys_sigretrun() -> isize {
-    if let Some(task) = current_task() {
-        let mut inner = task.inner_exclusive_access();
-        inner.handling_sig = -1;
-        // restore the trap context
-        let trap_ctx = inner.get_trap_cx();
-        *trap_ctx = inner.trap_ctx_backup.pop().unwrap().unwrap();
-        0
-    } else {
-        -1
+    let task = current_task().unwrap();
+    // 不在信号处理例程中
+    let frame_addr = match task.inner_exclusive_access().signal_frames.last() {
+        Some(frame_addr) => *frame_addr,
+        None => return Errno::EINVAL.into(),
+    };
+    let process = current_process();
+    let mut inner = process.inner_exclusive_access();
+    let frame_size = core::mem::size_of::<SignalFrame>();
+    let token = inner.get_user_token();
+    if !inner.memory_set.fault_in(frame_addr, frame_size, false) {
+        return Errno::EFAULT.into();
+    }
+    let ucontext = match try_copy_from_user(token, frame_addr as *const SignalFrame) {
+        Ok(frame) => frame.ucontext,
+        Err(_) => return Errno::EFAULT.into(),
+    };
+    // 信号帧读取成功之后才弹出, 失败时仍然可以重试
+    task.inner_exclusive_access().signal_frames.pop();
+    inner.signal_mask = ucontext.sigmask - (SignalFlags::SIGKILL | SignalFlags::SIGSTOP);
+    // restore the trap context
+    let trap_ctx = current_trap_cx();
+    trap_ctx.x[1..].copy_from_slice(&ucontext.gregs[1..]);
+    trap_ctx.sepc = ucontext.gregs[0];
+    trap_ctx.x[10] as isize
+}
+
+/// 设置当前线程的备用信号栈, `ss`不为空时设置新的备用信号栈, `old_ss`不为空时返回原来的设置.
+/// 正在备用信号栈上执行时不能修改它
+pub fn sys_sigaltstack(ss: *const SignalStack, old_ss: *mut SignalStack) -> isize {
+    let user_sp = current_trap_cx().x[2];
+    let task = current_task().unwrap();
+    let old = task.inner_exclusive_access().signal_stack;
+    let on_stack = old.contains(user_sp);
+    if !old_ss.is_null() {
+        if !current_fault_in(old_ss as usize, core::mem::size_of::<SignalStack>(), true) {
+            return Errno::EFAULT.into();
+        }
+        match try_translated_refmut(current_user_token(), old_ss) {
+            Ok(old_ss) => {
+                *old_ss = old;
+                if on_stack {
+                    old_ss.flags = SS_ONSTACK;
+                }
+            }
+            Err(_) => return Errno::EFAULT.into(),
+        }
+    }
+    if ss.is_null() {
+        return 0;
+    }
+    if !current_fault_in(ss as usize, core::mem::size_of::<SignalStack>(), false) {
+        return Errno::EFAULT.into();
+    }
+    let mut ss = match try_translated_ref(current_user_token(), ss) {
+        Ok(ss) => *ss,
+        Err(_) => return Errno::EFAULT.into(),
+    };
+    if on_stack {
+        return Errno::EPERM.into();
     }
+    match ss.flags {
+        SS_DISABLE => ss = SignalStack::default(),
+        0 if ss.size < MINSIGSTKSZ => return Errno::ENOMEM.into(),
+        0 => {}
+        _ => return Errno::EINVAL.into(),
+    }
+    task.inner_exclusive_access().signal_stack = ss;
+    0
 }
 
 /// 清除堆栈帧.
@@ -174,33 +508,48 @@ fn check_sigaction_error(signal: SignalFlags, action: usize, old_action: usize)
         false
     }
 }
-/// signal绑定.
+/// signal绑定. 处理函数为`SIG_DFL`时执行信号的默认动作, 为`SIG_IGN`时忽略信号
 pub fn sys_sigaction(
     signum: i32,
     action: *const SignalAction,
     old_action: *mut SignalAction,
 ) -> isize {
     let token = current_user_token();
-    if let Some(task) = current_task() {
-        let mut inner = task.inner_exclusive_access();
-        if signum as usize > MAX_SIG {
-            return -1;
-        }
-        if let Some(flag) = SignalFlags::from_bits(1 << signum) {
-            if check_sigaction_error(flag, action as usize, old_action as usize) {
-                return -1;
-            }
-            let old_kernel_action = inner.signal_actions.table[signum as usize];
-            if old_kernel_action.mask != SignalFlags::from_bits(40).unwrap() {
-                *translated_refmut(token, old_action) = old_kernel_action;
-            } else {
-                let mut ref_old_action = *translated_refmut(token, old_action);
-                ref_old_action.handler = old_kernel_action.handler;
-            }
-            let ref_action = translated_ref(token, action);
-            inner.signal_actions.table[signum as usize] = *ref_action;
-            return 0;
+    let process = current_process();
+    let mut inner = process.inner_exclusive_access();
+    if signum as usize > MAX_SIG {
+        return Errno::EINVAL.into();
+    }
+    if let Some(flag) = SignalFlags::from_bits(1 << signum) {
+        if check_sigaction_error(flag, action as usize, old_action as usize) {
+            return Errno::EINVAL.into();
+        }
+        if !inner.memory_set.fault_in(
+            old_action as usize,
+            core::mem::size_of::<SignalAction>(),
+            true,
+        ) || !inner.memory_set.fault_in(
+            action as usize,
+            core::mem::size_of::<SignalAction>(),
+            false,
+        ) {
+            return Errno::EFAULT.into();
+        }
+        // `action`和`old_action`可能指向同一个位置, 先读出新的动作再写回原来的动作
+        let new_action = match try_translated_ref(token, action) {
+            Ok(ref_action) => *ref_action,
+            Err(_) => return Errno::EFAULT.into(),
+        };
+        match try_translated_refmut(token, old_action) {
+            Ok(ref_old_action) => *ref_old_action = inner.signal_actions.table[signum as usize],
+            Err(_) => return Errno::EFAULT.into(),
+        }
+        inner.signal_actions.table[signum as usize] = new_action;
+        // a pending signal that becomes ignored is discarded
+        if inner.signal_actions.is_ignored(signum as usize) {
+            inner.discard_signal(signum as usize);
         }
+        return 0;
     }
-    -1
+    Errno::EINVAL.into()
 }
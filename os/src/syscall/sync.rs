@@ -0,0 +1,285 @@
+//! Synchronization syscalls: mutex, semaphore and condition variable
+use super::Errno;
+use crate::mm::{try_translated_ref, PageTable, VirtAddr};
+use crate::sync::{
+    futex_wait, futex_wake, Condvar, FutexKey, FutexWaitResult, Mutex, Resource, Semaphore,
+};
+use crate::task::{current_fault_in, current_process, current_task, current_user_token};
+use crate::timer::get_time_ms;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+
+/// 把`object`放进`list`的第一个空位, 返回它的 id
+fn insert_object<T>(list: &mut Vec<Option<Arc<T>>>, object: T) -> usize {
+    let object = Some(Arc::new(object));
+    if let Some(id) = list.iter().position(|item| item.is_none()) {
+        list[id] = object;
+        id
+    } else {
+        list.push(object);
+        list.len() - 1
+    }
+}
+
+/// 取出`list`中 id 为`id`的对象
+fn get_object<T>(list: &[Option<Arc<T>>], id: usize) -> Option<Arc<T>> {
+    list.get(id).and_then(|item| item.clone())
+}
+
+/// 当前线程请求`resource`, 可能导致死锁时返回`false`
+fn request_resource(resource: Resource) -> bool {
+    let tid = current_task().unwrap().gettid();
+    current_process()
+        .inner_exclusive_access()
+        .deadlock_detector
+        .request(tid, resource)
+}
+
+/// 记录当前线程得到了`resource`.
+///
+/// 资源的获取和释放都在同步原语的状态变化的同时记录, 否则另一个线程可能在两者之间
+/// 得到资源, 记录的剩余数量就会比实际的少
+fn acquire_resource(resource: Resource) {
+    let tid = current_task().unwrap().gettid();
+    current_process()
+        .inner_exclusive_access()
+        .deadlock_detector
+        .acquire(tid, resource);
+}
+
+/// 记录当前线程放弃了对`resource`的请求
+fn cancel_request(resource: Resource) {
+    let tid = current_task().unwrap().gettid();
+    current_process()
+        .inner_exclusive_access()
+        .deadlock_detector
+        .cancel(tid, resource);
+}
+
+/// 记录当前线程释放了`resource`, 与[`acquire_resource`]一样在同步原语的状态变化的同时调用
+fn release_resource(resource: Resource) {
+    let tid = current_task().unwrap().gettid();
+    current_process()
+        .inner_exclusive_access()
+        .deadlock_detector
+        .release(tid, resource);
+}
+
+/// 创建互斥锁, 返回它的 id
+pub fn sys_mutex_create() -> isize {
+    let process = current_process();
+    let mut process_inner = process.inner_exclusive_access();
+    let id = insert_object(&mut process_inner.mutex_list, Mutex::new());
+    process_inner
+        .deadlock_detector
+        .add_resource(Resource::Mutex(id), 1);
+    id as isize
+}
+
+/// 互斥锁加锁, 阻塞时收到信号返回`-EINTR`.
+/// 打开了死锁检测并且等待这个锁会导致死锁时返回`-EDEADLK`
+pub fn sys_mutex_lock(mutex_id: usize) -> isize {
+    let process = current_process();
+    let mutex = get_object(&process.inner_exclusive_access().mutex_list, mutex_id);
+    drop(process);
+    let mutex = match mutex {
+        Some(mutex) => mutex,
+        None => return Errno::EINVAL.into(),
+    };
+    let resource = Resource::Mutex(mutex_id);
+    if !request_resource(resource) {
+        return Errno::EDEADLK.into();
+    }
+    if mutex.lock(true, || acquire_resource(resource)) {
+        0
+    } else {
+        cancel_request(resource);
+        Errno::EINTR.into()
+    }
+}
+
+/// 互斥锁解锁, 锁没有被锁上时返回`-EPERM`
+pub fn sys_mutex_unlock(mutex_id: usize) -> isize {
+    let process = current_process();
+    let mutex = get_object(&process.inner_exclusive_access().mutex_list, mutex_id);
+    drop(process);
+    match mutex {
+        Some(mutex) if mutex.unlock(|| release_resource(Resource::Mutex(mutex_id))) => 0,
+        Some(_) => Errno::EPERM.into(),
+        None => Errno::EINVAL.into(),
+    }
+}
+
+/// 创建初始资源数为`res_count`的信号量, 返回它的 id
+pub fn sys_semaphore_create(res_count: usize) -> isize {
+    let process = current_process();
+    let mut process_inner = process.inner_exclusive_access();
+    let id = insert_object(&mut process_inner.semaphore_list, Semaphore::new(res_count));
+    process_inner
+        .deadlock_detector
+        .add_resource(Resource::Semaphore(id), res_count);
+    id as isize
+}
+
+/// 信号量的 V 操作
+pub fn sys_semaphore_up(sem_id: usize) -> isize {
+    let process = current_process();
+    let sem = get_object(&process.inner_exclusive_access().semaphore_list, sem_id);
+    drop(process);
+    match sem {
+        Some(sem) => {
+            sem.up(|| release_resource(Resource::Semaphore(sem_id)));
+            0
+        }
+        None => Errno::EINVAL.into(),
+    }
+}
+
+/// 信号量的 P 操作, 阻塞时收到信号返回`-EINTR`.
+/// 打开了死锁检测并且等待这个信号量会导致死锁时返回`-EDEADLK`
+pub fn sys_semaphore_down(sem_id: usize) -> isize {
+    let process = current_process();
+    let sem = get_object(&process.inner_exclusive_access().semaphore_list, sem_id);
+    drop(process);
+    let sem = match sem {
+        Some(sem) => sem,
+        None => return Errno::EINVAL.into(),
+    };
+    let resource = Resource::Semaphore(sem_id);
+    if !request_resource(resource) {
+        return Errno::EDEADLK.into();
+    }
+    if sem.down(|| acquire_resource(resource)) {
+        0
+    } else {
+        cancel_request(resource);
+        Errno::EINTR.into()
+    }
+}
+
+/// 创建条件变量, 返回它的 id
+pub fn sys_condvar_create() -> isize {
+    let process = current_process();
+    let mut process_inner = process.inner_exclusive_access();
+    insert_object(&mut process_inner.condvar_list, Condvar::new()) as isize
+}
+
+/// 唤醒一个在条件变量上等待的线程
+pub fn sys_condvar_signal(condvar_id: usize) -> isize {
+    let process = current_process();
+    let condvar = get_object(&process.inner_exclusive_access().condvar_list, condvar_id);
+    drop(process);
+    match condvar {
+        Some(condvar) => {
+            condvar.signal();
+            0
+        }
+        None => Errno::EINVAL.into(),
+    }
+}
+
+/// 释放互斥锁并在条件变量上等待, 返回前重新获得互斥锁. 互斥锁没有被锁上时返回`-EPERM`
+pub fn sys_condvar_wait(condvar_id: usize, mutex_id: usize) -> isize {
+    let process = current_process();
+    let process_inner = process.inner_exclusive_access();
+    let condvar = get_object(&process_inner.condvar_list, condvar_id);
+    let mutex = get_object(&process_inner.mutex_list, mutex_id);
+    drop(process_inner);
+    drop(process);
+    let (condvar, mutex) = match (condvar, mutex) {
+        (Some(condvar), Some(mutex)) => (condvar, mutex),
+        _ => return Errno::EINVAL.into(),
+    };
+    // the mutex is released while waiting, and locked again before returning
+    let resource = Resource::Mutex(mutex_id);
+    if condvar.wait(
+        &mutex,
+        || release_resource(resource),
+        || acquire_resource(resource),
+    ) {
+        0
+    } else {
+        Errno::EPERM.into()
+    }
+}
+
+/// 打开(`enabled`为 1)或关闭(为 0)当前进程的死锁检测
+pub fn sys_enable_deadlock_detect(enabled: usize) -> isize {
+    let process = current_process();
+    let mut process_inner = process.inner_exclusive_access();
+    match enabled {
+        0 => process_inner.deadlock_detector.enabled = false,
+        1 => process_inner.deadlock_detector.enabled = true,
+        _ => return Errno::EINVAL.into(),
+    }
+    0
+}
+
+/// 处理`uaddr`所在页面的缺页并得到它的 futex 的键, 页面不可访问时返回`None`.
+///
+/// 先打破写时复制共享, 等待之前读到的才是自己的值
+fn futex_key(uaddr: usize) -> Option<FutexKey> {
+    let process = current_process();
+    let mut process_inner = process.inner_exclusive_access();
+    let memory_set = &mut process_inner.memory_set;
+    if !memory_set.fault_in(uaddr, core::mem::size_of::<u32>(), true) {
+        return None;
+    }
+    let token = memory_set.token();
+    if memory_set.is_shared(VirtAddr::from(uaddr)) {
+        let pa = PageTable::from_token(token).translate_va(VirtAddr::from(uaddr))?;
+        Some(FutexKey::Shared(pa.into()))
+    } else {
+        Some(FutexKey::Private(token, uaddr))
+    }
+}
+
+/// `sys_futex`的操作: `*uaddr == val`时阻塞
+const FUTEX_WAIT: usize = 0;
+/// `sys_futex`的操作: 唤醒最多`val`个在`uaddr`上等待的任务
+const FUTEX_WAKE: usize = 1;
+
+/// futex 操作, `uaddr`必须 4 字节对齐.
+///
+/// `FUTEX_WAIT`: `*uaddr != val`时返回`-EAGAIN`, 否则阻塞到被唤醒(返回 0),
+/// 等待超过`timeout_ms`毫秒(返回`-ETIMEDOUT`, 为 0 时不会超时)或者收到信号(返回`-EINTR`).
+/// `FUTEX_WAKE`: 返回唤醒的任务数.
+///
+/// 私有的 futex 以地址空间和虚拟地址区分, 共享映射中的以物理地址区分
+pub fn sys_futex(uaddr: *const u32, op: usize, val: usize, timeout_ms: usize) -> isize {
+    if uaddr as usize % core::mem::size_of::<u32>() != 0 {
+        return Errno::EINVAL.into();
+    }
+    let key = match futex_key(uaddr as usize) {
+        Some(key) => key,
+        None => return Errno::EFAULT.into(),
+    };
+    let token = current_user_token();
+    match op {
+        FUTEX_WAIT => {
+            let expire_ms = if timeout_ms == 0 {
+                None
+            } else {
+                Some(get_time_ms().saturating_add(timeout_ms))
+            };
+            loop {
+                let load = || try_translated_ref(token, uaddr).ok().copied();
+                return match futex_wait(key, val as u32, load, expire_ms) {
+                    FutexWaitResult::Woken => 0,
+                    FutexWaitResult::TimedOut => Errno::ETIMEDOUT.into(),
+                    FutexWaitResult::Interrupted => Errno::EINTR.into(),
+                    FutexWaitResult::ValueChanged => Errno::EAGAIN.into(),
+                    // the page may have been swapped out again since it was faulted in
+                    FutexWaitResult::Fault
+                        if current_fault_in(uaddr as usize, core::mem::size_of::<u32>(), true) =>
+                    {
+                        continue
+                    }
+                    FutexWaitResult::Fault => Errno::EFAULT.into(),
+                };
+            }
+        }
+        FUTEX_WAKE => futex_wake(key, val) as isize,
+        _ => Errno::EINVAL.into(),
+    }
+}
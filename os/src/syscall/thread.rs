@@ -0,0 +1,86 @@
+//! Thread management syscalls
+use super::Errno;
+use crate::mm::kernel_token;
+use crate::task::{
+    add_task, block_current_and_run_next, current_process, current_task, TaskControlBlock,
+};
+use crate::trap::{trap_handler, TrapContext};
+use alloc::sync::Arc;
+
+/// 在当前进程中创建一个从`entry`开始执行的线程, `arg`通过 a0 传给它. 返回新线程的 tid
+pub fn sys_thread_create(entry: usize, arg: usize) -> isize {
+    let process = current_process();
+    // create a new thread, with its own user stack and trap context
+    let new_task = Arc::new(TaskControlBlock::new(Arc::clone(&process), true));
+    let new_task_inner = new_task.inner_exclusive_access();
+    let new_task_res = new_task_inner.res.as_ref().unwrap();
+    let new_task_tid = new_task_res.tid;
+    let mut process_inner = process.inner_exclusive_access();
+    // add new thread to current process
+    let tasks = &mut process_inner.tasks;
+    while tasks.len() < new_task_tid + 1 {
+        tasks.push(None);
+    }
+    tasks[new_task_tid] = Some(Arc::clone(&new_task));
+    drop(process_inner);
+    let new_task_trap_cx = new_task_inner.get_trap_cx();
+    *new_task_trap_cx = TrapContext::app_init_context(
+        entry,
+        new_task_res.ustack_top(),
+        kernel_token(),
+        new_task.kstack.get_top(),
+        trap_handler as usize,
+    );
+    new_task_trap_cx.x[10] = arg;
+    drop(new_task_inner);
+    // add new task to scheduler
+    add_task(new_task);
+    new_task_tid as isize
+}
+
+/// 获取当前线程的 tid
+pub fn sys_gettid() -> isize {
+    current_task().unwrap().gettid() as isize
+}
+
+/// 等待同一进程中的线程`tid`退出, 回收它的资源并返回它的退出码.
+///
+/// Return `-ESRCH` if there is no such thread or it has been waited,
+/// `-EDEADLK` if the thread waits for itself,
+/// `-EINTR` if a signal arrives while blocking.
+pub fn sys_waittid(tid: usize) -> isize {
+    loop {
+        let task = current_task().unwrap();
+        if task.gettid() == tid {
+            return Errno::EDEADLK.into();
+        }
+        let process = current_process();
+        let mut process_inner = process.inner_exclusive_access();
+        let waited_task = match process_inner.get_task(tid) {
+            Some(waited_task) => waited_task,
+            None => return Errno::ESRCH.into(),
+        };
+        let mut waited_inner = waited_task.inner_exclusive_access();
+        if let Some(exit_code) = waited_inner.exit_code {
+            // dealloc the exited thread
+            let res = waited_inner.res.take();
+            drop(waited_inner);
+            process_inner.tasks[tid] = None;
+            drop(process_inner);
+            // dropping `TaskUserRes` accesses the PCB, so it can not be done while holding it
+            drop(res);
+            return exit_code as isize;
+        }
+        drop(waited_inner);
+        if process_inner.has_interrupting_signal() {
+            return Errno::EINTR.into();
+        }
+        process_inner.thread_exit_wait.add_current();
+        drop(process_inner);
+        drop(process);
+        drop(task);
+        drop(waited_task);
+        // woken up by an exiting thread or a signal
+        block_current_and_run_next();
+    }
+}
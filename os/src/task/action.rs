@@ -1,5 +1,21 @@
 //! impl actions for a signal
-use crate::task::{SignalFlags, MAX_SIG};
+use crate::task::{SignalDefault, SignalFlags, MAX_SIG};
+use bitflags::*;
+
+/// `SignalAction::handler`: 执行信号的默认动作
+pub const SIG_DFL: usize = 0;
+/// `SignalAction::handler`: 忽略信号
+pub const SIG_IGN: usize = 1;
+
+bitflags! {
+    /// `sigaction`的选项
+    pub struct SignalActionFlags: u32 {
+        /// 信号处理函数需要`SignalInfo`和`UContext`参数
+        const SIGINFO = 4;
+        /// 在`sigaltstack`设置的备用信号栈上执行信号处理函数
+        const ONSTACK = 0x0800_0000;
+    }
+}
 
 #[repr(C)]
 #[derive(Debug, Clone, Copy)]
@@ -9,13 +25,16 @@ pub struct SignalAction {
     pub handler: usize,
     /// 信号掩码
     pub mask: SignalFlags,
+    /// 选项
+    pub flags: SignalActionFlags,
 }
 
 impl Default for SignalAction {
     fn default() -> Self {
         Self {
-            handler: 0,
-            mask: SignalFlags::from_bits(40).unwrap(),
+            handler: SIG_DFL,
+            mask: SignalFlags::empty(),
+            flags: SignalActionFlags::empty(),
         }
     }
 }
@@ -27,6 +46,26 @@ pub struct SignalActions {
     pub table: [SignalAction; MAX_SIG + 1],
 }
 
+impl SignalActions {
+    /// 信号`signum`是否会被忽略: 处理函数为`SIG_IGN`, 或者为`SIG_DFL`且默认动作是忽略
+    pub fn is_ignored(&self, signum: usize) -> bool {
+        let signal = SignalFlags::from_bits_truncate(1 << signum);
+        match self.table[signum].handler {
+            SIG_IGN => true,
+            SIG_DFL => signal.default_action() == SignalDefault::Ignore,
+            _ => false,
+        }
+    }
+    /// `exec`之后原来的信号处理函数不再存在, 恢复为默认动作. 被忽略的信号仍然被忽略
+    pub fn reset_handlers(&mut self) {
+        for action in self.table.iter_mut() {
+            if action.handler != SIG_IGN {
+                *action = SignalAction::default();
+            }
+        }
+    }
+}
+
 impl Default for SignalActions {
     fn default() -> Self {
         Self {
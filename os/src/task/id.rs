@@ -0,0 +1,246 @@
+//! pid, tid 和内核栈的分配, 以及线程在用户地址空间中占用的资源
+
+use super::process::ProcessControlBlockInner;
+use super::ProcessControlBlock;
+use crate::config::{
+    KERNEL_STACK_SIZE, PAGE_SIZE, TRAMPOLINE, TRAP_CONTEXT_BASE, USER_STACK_BASE, USER_STACK_SIZE,
+};
+use crate::mm::{MapPermission, MemorySet, PhysPageNum, VirtAddr, KERNEL_SPACE};
+use crate::sync::SpinLock;
+use alloc::sync::{Arc, Weak};
+use alloc::vec::Vec;
+use lazy_static::lazy_static;
+
+///可回收的 id 申请器, pid, tid 和内核栈编号都用它来分配
+pub struct RecycleAllocator {
+    current: usize,
+    recycled: Vec<usize>,
+}
+
+impl RecycleAllocator {
+    ///Create an empty `RecycleAllocator`
+    pub fn new() -> Self {
+        RecycleAllocator {
+            current: 0,
+            recycled: Vec::new(),
+        }
+    }
+    ///Allocate an id
+    pub fn alloc(&mut self) -> usize {
+        if let Some(id) = self.recycled.pop() {
+            id
+        } else {
+            self.current += 1;
+            self.current - 1
+        }
+    }
+    ///Recycle an id
+    pub fn dealloc(&mut self, id: usize) {
+        assert!(id < self.current);
+        assert!(
+            !self.recycled.iter().any(|i| *i == id),
+            "id {} has been deallocated!",
+            id
+        );
+        self.recycled.push(id);
+    }
+}
+
+lazy_static! {
+    static ref PID_ALLOCATOR: SpinLock<RecycleAllocator> = SpinLock::new(RecycleAllocator::new());
+    static ref KSTACK_ALLOCATOR: SpinLock<RecycleAllocator> =
+        SpinLock::new(RecycleAllocator::new());
+}
+
+///Bind pid lifetime to `PidHandle`
+pub struct PidHandle(pub usize);
+
+/// 实现drop,遵循RAII 的思想
+impl Drop for PidHandle {
+    fn drop(&mut self) {
+        //println!("drop pid {}", self.0);
+        PID_ALLOCATOR.exclusive_access().dealloc(self.0);
+    }
+}
+
+/// 申请一个pid
+pub fn pid_alloc() -> PidHandle {
+    PidHandle(PID_ALLOCATOR.exclusive_access().alloc())
+}
+
+/// Return (bottom, top) of a kernel stack in kernel space.
+pub fn kernel_stack_position(kstack_id: usize) -> (usize, usize) {
+    let top = TRAMPOLINE - kstack_id * (KERNEL_STACK_SIZE + PAGE_SIZE);
+    let bottom = top - KERNEL_STACK_SIZE;
+    (bottom, top)
+}
+
+///Kernelstack for a thread, 每个线程有自己的内核栈
+pub struct KernelStack(usize);
+
+///Allocate a kernelstack in kernel space
+pub fn kstack_alloc() -> KernelStack {
+    let kstack_id = KSTACK_ALLOCATOR.exclusive_access().alloc();
+    let (kernel_stack_bottom, kernel_stack_top) = kernel_stack_position(kstack_id);
+    let mapped = KERNEL_SPACE.exclusive_access().insert_framed_area(
+        kernel_stack_bottom.into(),
+        kernel_stack_top.into(),
+        MapPermission::R | MapPermission::W,
+    );
+    assert!(mapped, "out of memory for kernel stack");
+    KernelStack(kstack_id)
+}
+
+impl KernelStack {
+    #[allow(unused)]
+    ///Push a value on top of kernelstack
+    pub fn push_on_top<T>(&self, value: T) -> *mut T
+    where
+        T: Sized,
+    {
+        let kernel_stack_top = self.get_top();
+        let ptr_mut = (kernel_stack_top - core::mem::size_of::<T>()) as *mut T;
+        unsafe {
+            *ptr_mut = value;
+        }
+        ptr_mut
+    }
+    ///Get the value on the top of kernelstack
+    pub fn get_top(&self) -> usize {
+        let (_, kernel_stack_top) = kernel_stack_position(self.0);
+        kernel_stack_top
+    }
+}
+
+impl Drop for KernelStack {
+    fn drop(&mut self) {
+        let (kernel_stack_bottom, _) = kernel_stack_position(self.0);
+        let kernel_stack_bottom_va: VirtAddr = kernel_stack_bottom.into();
+        KERNEL_SPACE
+            .exclusive_access()
+            .remove_area_with_start_vpn(kernel_stack_bottom_va.into());
+        KSTACK_ALLOCATOR.exclusive_access().dealloc(self.0);
+    }
+}
+
+/// 线程`tid`的 Trap 上下文所在的虚拟地址
+fn trap_cx_bottom_from_tid(tid: usize) -> usize {
+    TRAP_CONTEXT_BASE - tid * PAGE_SIZE
+}
+
+/// 线程`tid`的用户栈栈底(低地址), 每个用户栈下方留一个保护页
+fn ustack_bottom_from_tid(tid: usize) -> usize {
+    USER_STACK_BASE - (tid + 1) * (USER_STACK_SIZE + PAGE_SIZE)
+}
+
+/// 线程在所属进程中占用的资源: tid, 用户栈和 Trap 上下文页.
+///
+/// 线程退出后这些资源保留到它被`waittid`回收(或者进程退出)为止, 以免 tid 被新线程复用.
+/// drop 时会访问所属进程的 inner, 所以不能在持有进程 inner 的时候 drop
+pub struct TaskUserRes {
+    /// 线程 id, 主线程为 0
+    pub tid: usize,
+    /// 用户栈栈底, 一般由 tid 决定, 见[`ProcessControlBlockInner::ustack_slot`]
+    ustack_bottom: usize,
+    /// 所属进程
+    pub process: Weak<ProcessControlBlock>,
+}
+
+impl TaskUserRes {
+    /// 为`process`分配一个 tid. `alloc_user_res`为`false`时用户栈和 Trap 上下文已经存在(fork)
+    pub fn new(process: Arc<ProcessControlBlock>, alloc_user_res: bool) -> Self {
+        let mut process_inner = process.inner_exclusive_access();
+        let tid = process_inner.alloc_tid();
+        let ustack_bottom = ustack_bottom_from_tid(process_inner.ustack_slot(tid));
+        drop(process_inner);
+        let task_user_res = Self {
+            tid,
+            ustack_bottom,
+            process: Arc::downgrade(&process),
+        };
+        if alloc_user_res {
+            task_user_res.alloc_user_res();
+        }
+        task_user_res
+    }
+    /// 在进程的地址空间中映射用户栈和 Trap 上下文
+    pub fn alloc_user_res(&self) {
+        let process = self.process.upgrade().unwrap();
+        let mut process_inner = process.inner_exclusive_access();
+        // alloc user stack, populated lazily
+        process_inner.memory_set.insert_lazy_area(
+            self.ustack_bottom.into(),
+            self.ustack_top().into(),
+            MapPermission::R | MapPermission::W | MapPermission::U,
+        );
+        // alloc trap_cx
+        let trap_cx_bottom = trap_cx_bottom_from_tid(self.tid);
+        let mapped = process_inner.memory_set.insert_framed_area(
+            trap_cx_bottom.into(),
+            (trap_cx_bottom + PAGE_SIZE).into(),
+            MapPermission::R | MapPermission::W,
+        );
+        assert!(mapped, "out of memory for trap context");
+    }
+    /// 从`memory_set`中移除这个线程的用户栈和 Trap 上下文, 例如 fork 出的子进程中的副本
+    pub fn unmap_user_res(&self, memory_set: &mut MemorySet) {
+        let ustack_bottom_va: VirtAddr = self.ustack_bottom.into();
+        memory_set.remove_area_with_start_vpn(ustack_bottom_va.into());
+        let trap_cx_bottom_va: VirtAddr = trap_cx_bottom_from_tid(self.tid).into();
+        memory_set.remove_area_with_start_vpn(trap_cx_bottom_va.into());
+    }
+    /// fork 出的子进程的主线程沿用调用 fork 的线程(`self`)的用户栈和 Trap 上下文:
+    /// 把`memory_set`中这个线程的 Trap 上下文搬到主线程的位置, 主线程原来的已经被移除.
+    /// 物理内存不足时返回`false`
+    pub fn move_trap_cx_to_main(&self, memory_set: &mut MemorySet) -> bool {
+        if self.tid == 0 {
+            return true;
+        }
+        let main_va: VirtAddr = trap_cx_bottom_from_tid(0).into();
+        if !memory_set.insert_framed_area(
+            main_va,
+            (trap_cx_bottom_from_tid(0) + PAGE_SIZE).into(),
+            MapPermission::R | MapPermission::W,
+        ) {
+            return false;
+        }
+        let trap_cx_va: VirtAddr = self.trap_cx_user_va().into();
+        let src_ppn = memory_set.translate(trap_cx_va.into()).unwrap().ppn();
+        let dst_ppn = memory_set.translate(main_va.into()).unwrap().ppn();
+        dst_ppn
+            .get_bytes_array()
+            .copy_from_slice(src_ppn.get_bytes_array());
+        memory_set.remove_area_with_start_vpn(trap_cx_va.into());
+        true
+    }
+    /// Trap 上下文在用户地址空间中的虚拟地址
+    pub fn trap_cx_user_va(&self) -> usize {
+        trap_cx_bottom_from_tid(self.tid)
+    }
+    /// Trap 上下文所在的物理页号
+    pub fn trap_cx_ppn(&self) -> PhysPageNum {
+        let process = self.process.upgrade().unwrap();
+        let process_inner = process.inner_exclusive_access();
+        let trap_cx_bottom_va: VirtAddr = self.trap_cx_user_va().into();
+        process_inner
+            .memory_set
+            .translate(trap_cx_bottom_va.into())
+            .unwrap()
+            .ppn()
+    }
+    /// 用户栈栈顶
+    pub fn ustack_top(&self) -> usize {
+        self.ustack_bottom + USER_STACK_SIZE
+    }
+}
+
+impl Drop for TaskUserRes {
+    fn drop(&mut self) {
+        // 进程已经被回收时, 地址空间和 tid 都随之释放了
+        if let Some(process) = self.process.upgrade() {
+            let mut process_inner = process.inner_exclusive_access();
+            self.unmap_user_res(&mut process_inner.memory_set);
+            process_inner.dealloc_tid(self.tid);
+        }
+    }
+}
@@ -0,0 +1,204 @@
+//! 线程的运行时间统计和进程的间隔定时器
+//!
+//! 线程在用户态和内核态运行的时间在进出内核和切换时记录, 时钟中断时计入所在进程的
+//! ITIMER_VIRTUAL 和 ITIMER_PROF. ITIMER_REAL 按照墙上时间计时, 启用它的进程被记录下来,
+//! 由时钟中断和空闲的 hart 检查
+
+use super::manager::pid2process;
+use super::{
+    current_process, current_task, wakeup_process, ProcessControlBlock, SignalFlags, SignalInfo,
+    SI_TIMER,
+};
+use crate::sync::SpinLock;
+use crate::timer::{get_time, ITimerVal, TimeVal};
+use alloc::collections::BTreeSet;
+use alloc::sync::Arc;
+use lazy_static::*;
+
+/// 按照墙上时间计时, 到期时发送 SIGALRM
+pub const ITIMER_REAL: usize = 0;
+/// 按照进程在用户态运行的时间计时, 到期时发送 SIGVTALRM
+pub const ITIMER_VIRTUAL: usize = 1;
+/// 按照进程运行的全部时间计时, 到期时发送 SIGPROF
+pub const ITIMER_PROF: usize = 2;
+
+/// 线程的运行时间, 单位是`mtime`的计数
+#[derive(Default)]
+pub struct TaskTimes {
+    /// 在用户态运行的时间
+    pub utime: usize,
+    /// 在内核态运行的时间
+    pub stime: usize,
+    /// 上一次记录的时刻
+    timestamp: usize,
+    /// 还没有计入间隔定时器的用户态时间
+    pending_utime: usize,
+    /// 还没有计入间隔定时器的内核态时间
+    pending_stime: usize,
+}
+
+impl TaskTimes {
+    fn elapsed(&mut self) -> usize {
+        let now = get_time();
+        let elapsed = now.saturating_sub(self.timestamp);
+        self.timestamp = now;
+        elapsed
+    }
+    /// 线程开始在处理器上运行
+    pub fn switch_in(&mut self) {
+        self.timestamp = get_time();
+    }
+    /// 从用户态进入内核, 之前的时间算作用户态时间
+    pub fn enter_kernel(&mut self) {
+        let elapsed = self.elapsed();
+        self.utime += elapsed;
+        self.pending_utime += elapsed;
+    }
+    /// 回到用户态或者离开处理器, 之前的时间算作内核态时间
+    pub fn leave_kernel(&mut self) {
+        let elapsed = self.elapsed();
+        self.stime += elapsed;
+        self.pending_stime += elapsed;
+    }
+    /// 取出还没有计入间隔定时器的用户态和内核态时间
+    fn take_pending(&mut self) -> (usize, usize) {
+        let pending = (self.pending_utime, self.pending_stime);
+        self.pending_utime = 0;
+        self.pending_stime = 0;
+        pending
+    }
+}
+
+/// 当前线程从用户态进入内核
+pub fn current_enter_kernel() {
+    let task = current_task().unwrap();
+    task.inner_exclusive_access().times.enter_kernel();
+}
+
+/// 当前线程回到用户态
+pub fn current_leave_kernel() {
+    let task = current_task().unwrap();
+    task.inner_exclusive_access().times.leave_kernel();
+}
+
+/// 一个间隔定时器, 单位是`mtime`的计数
+#[derive(Clone, Copy, Default)]
+pub struct ITimer {
+    /// 到期之后重新开始计时的时间, 为 0 时只触发一次
+    pub interval: usize,
+    /// 剩余的时间, 为 0 时没有启用. ITIMER_REAL 记录的是到期的时刻
+    pub value: usize,
+}
+
+impl ITimer {
+    /// 经过`elapsed`之后是否到期, 到期时重新开始计时
+    fn charge(&mut self, elapsed: usize) -> bool {
+        if self.value == 0 {
+            return false;
+        }
+        if self.value > elapsed {
+            self.value -= elapsed;
+            return false;
+        }
+        self.value = self.interval;
+        true
+    }
+}
+
+lazy_static! {
+    /// 启用了 ITIMER_REAL 的进程
+    static ref REAL_ITIMER_PIDS: SpinLock<BTreeSet<usize>> = SpinLock::new(BTreeSet::new());
+}
+
+const ITIMER_SIGNALS: [SignalFlags; 3] = [
+    SignalFlags::SIGALRM,
+    SignalFlags::SIGVTALRM,
+    SignalFlags::SIGPROF,
+];
+
+fn raise(process: &Arc<ProcessControlBlock>, signal: SignalFlags) {
+    process
+        .inner_exclusive_access()
+        .add_signal(SignalInfo::new(signal, SI_TIMER));
+    wakeup_process(process);
+}
+
+/// 读取当前进程的间隔定时器`which`
+pub fn current_getitimer(which: usize) -> ITimerVal {
+    let process = current_process();
+    let itimer = process.inner_exclusive_access().itimers[which];
+    let value = match which {
+        ITIMER_REAL if itimer.value != 0 => itimer.value.saturating_sub(get_time()).max(1),
+        _ => itimer.value,
+    };
+    ITimerVal {
+        interval: TimeVal::from_time(itimer.interval),
+        value: TimeVal::from_time(value),
+    }
+}
+
+/// 设置当前进程的间隔定时器`which`, 返回原来的设置
+pub fn current_setitimer(which: usize, new: &ITimerVal) -> ITimerVal {
+    let old = current_getitimer(which);
+    let process = current_process();
+    let value = new.value.to_time();
+    let itimer = ITimer {
+        interval: new.interval.to_time(),
+        value: match which {
+            ITIMER_REAL if value != 0 => get_time().saturating_add(value),
+            _ => value,
+        },
+    };
+    process.inner_exclusive_access().itimers[which] = itimer;
+    // registered after the timer is set, so that `check_real_itimers` never drops it
+    if which == ITIMER_REAL && itimer.value != 0 {
+        REAL_ITIMER_PIDS.exclusive_access().insert(process.getpid());
+    }
+    old
+}
+
+/// 把当前线程新增的运行时间计入所在进程的 ITIMER_VIRTUAL 和 ITIMER_PROF, 在时钟中断时调用
+pub fn charge_current_itimers() {
+    let task = current_task().unwrap();
+    let (utime, stime) = task.inner_exclusive_access().times.take_pending();
+    let process = task.process.upgrade().unwrap();
+    let mut inner = process.inner_exclusive_access();
+    let virtual_expired = inner.itimers[ITIMER_VIRTUAL].charge(utime);
+    let prof_expired = inner.itimers[ITIMER_PROF].charge(utime + stime);
+    drop(inner);
+    if virtual_expired {
+        raise(&process, ITIMER_SIGNALS[ITIMER_VIRTUAL]);
+    }
+    if prof_expired {
+        raise(&process, ITIMER_SIGNALS[ITIMER_PROF]);
+    }
+}
+
+/// 给 ITIMER_REAL 到期的进程发送 SIGALRM, 在时钟中断和处理器空闲时调用
+pub fn check_real_itimers() {
+    let mut pids = REAL_ITIMER_PIDS.exclusive_access();
+    let now = get_time();
+    pids.retain(|pid| {
+        let process = match pid2process(*pid) {
+            Some(process) => process,
+            None => return false,
+        };
+        let mut inner = process.inner_exclusive_access();
+        let itimer = &mut inner.itimers[ITIMER_REAL];
+        if itimer.value == 0 {
+            return false;
+        }
+        if itimer.value > now {
+            return true;
+        }
+        itimer.value = if itimer.interval == 0 {
+            0
+        } else {
+            now + itimer.interval
+        };
+        let armed = itimer.value != 0;
+        drop(inner);
+        raise(&process, ITIMER_SIGNALS[ITIMER_REAL]);
+        armed
+    });
+}
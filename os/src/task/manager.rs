@@ -1,64 +1,104 @@
 //! 任务管理器的实现
 
-use super::TaskControlBlock;
-use crate::sync::UPSafeCell;
-use alloc::collections::{BTreeMap, VecDeque};
+use super::scheduler::{DefaultScheduler, Scheduler};
+use super::{ProcessControlBlock, TaskControlBlock};
+use crate::sync::SpinLock;
+use alloc::collections::BTreeMap;
 use alloc::sync::Arc;
+use core::sync::atomic::{AtomicUsize, Ordering};
 use lazy_static::*;
 
 /// A array of `TaskControlBlock` that is thread-safe
 pub struct TaskManager {
-    // 双端队列,方便实现fifo
-    ready_queue: VecDeque<Arc<TaskControlBlock>>,
+    // 编译时选择的调度器
+    scheduler: DefaultScheduler,
 }
 
-/// A simple FIFO scheduler.
+/// 就绪队列, 调度策略由 [`DefaultScheduler`] 决定
 impl TaskManager {
     /// Creat an empty TaskManager
     pub fn new() -> Self {
         Self {
-            ready_queue: VecDeque::new(),
+            scheduler: DefaultScheduler::new(),
         }
     }
     /// Add a task to `TaskManager`
     pub fn add(&mut self, task: Arc<TaskControlBlock>) {
-        self.ready_queue.push_back(task);
+        self.scheduler.push(task);
     }
-    /// Remove the first task and return it,or `None` if `TaskManager` is empty
+    /// Remove the next task to run and return it,or `None` if `TaskManager` is empty
     pub fn fetch(&mut self) -> Option<Arc<TaskControlBlock>> {
-        self.ready_queue.pop_front()
+        self.scheduler.pop()
+    }
+    /// 把`task`从就绪队列中移除
+    pub fn remove(&mut self, task: &Arc<TaskControlBlock>) {
+        self.scheduler.remove(task);
+    }
+    /// 时钟中断时调用, 返回是否要抢占正在运行的`task`
+    pub fn tick(&mut self, task: &Arc<TaskControlBlock>) -> bool {
+        self.scheduler.tick(task)
     }
 }
 
 lazy_static! {
-    pub static ref TASK_MANAGER: UPSafeCell<TaskManager> =
-        unsafe { UPSafeCell::new(TaskManager::new()) };
-    pub static ref PID2TCB: UPSafeCell<BTreeMap<usize, Arc<TaskControlBlock>>> =
-        unsafe { UPSafeCell::new(BTreeMap::new()) };
+    pub static ref TASK_MANAGER: SpinLock<TaskManager> = SpinLock::new(TaskManager::new());
+    pub static ref PID2PCB: SpinLock<BTreeMap<usize, Arc<ProcessControlBlock>>> =
+        SpinLock::new(BTreeMap::new());
 }
 /// Interface offered to add task
 pub fn add_task(task: Arc<TaskControlBlock>) {
-    // info!("Adding task, pid: {}", task.pid.0);
-    PID2TCB
-        .exclusive_access()
-        .insert(task.getpid(), Arc::clone(&task));
     TASK_MANAGER.exclusive_access().add(task);
 }
-/// Interface offered to pop the first task
+/// 把`task`从就绪队列中移除, 用于进程退出时其它线程还在就绪队列中的情况
+pub fn remove_task(task: &Arc<TaskControlBlock>) {
+    TASK_MANAGER.exclusive_access().remove(task);
+}
+/// 时钟中断时由调度器决定是否抢占正在运行的`task`
+pub fn tick_task(task: &Arc<TaskControlBlock>) -> bool {
+    TASK_MANAGER.exclusive_access().tick(task)
+}
+/// Interface offered to pop the next task
 pub fn fetch_task() -> Option<Arc<TaskControlBlock>> {
     TASK_MANAGER.exclusive_access().fetch()
 }
 
-/// 根据pid获取TCB
-pub fn pid2task(pid: usize) -> Option<Arc<TaskControlBlock>> {
-    let map = PID2TCB.exclusive_access();
+/// 下一次换出页面时最先扫描的进程的pid
+static SWAP_CURSOR: AtomicUsize = AtomicUsize::new(0);
+
+/// 物理内存不足时从某个进程的地址空间中换出一个页面: 从上次换出页面的进程的下一个进程开始
+/// 轮流扫描, 进程内部由时钟算法选择页面. 正被加锁的进程(包括调用者自己持有的)被跳过,
+/// 所以持有进程的锁时也可以调用. 没有页面可以换出时返回`false`
+pub fn swap_out_any() -> bool {
+    let map = PID2PCB.exclusive_access();
+    let cursor = SWAP_CURSOR.load(Ordering::Relaxed);
+    for (pid, process) in map.range(cursor..).chain(map.range(..cursor)) {
+        let mut inner = match process.try_inner_exclusive_access() {
+            Some(inner) => inner,
+            None => continue,
+        };
+        if inner.memory_set.swap_out_one() {
+            SWAP_CURSOR.store(pid + 1, Ordering::Relaxed);
+            return true;
+        }
+    }
+    false
+}
+
+/// 根据pid获取PCB
+pub fn pid2process(pid: usize) -> Option<Arc<ProcessControlBlock>> {
+    let map = PID2PCB.exclusive_access();
     map.get(&pid).map(Arc::clone)
 }
 
+/// 将进程 `pid` 加入字典
+pub fn insert_into_pid2process(pid: usize, process: Arc<ProcessControlBlock>) {
+    PID2PCB.exclusive_access().insert(pid, process);
+}
+
 /// 将进程 `pid` 从字典中删除
-pub fn remove_from_pid2task(pid: usize) {
-    let mut map = PID2TCB.exclusive_access();
+pub fn remove_from_pid2process(pid: usize) {
+    let mut map = PID2PCB.exclusive_access();
     if map.remove(&pid).is_none() {
-        panic!("cannot find pid {} in pid2task!", pid);
+        panic!("cannot find pid {} in pid2process!", pid);
     }
 }
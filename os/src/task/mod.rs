@@ -13,28 +13,56 @@ mod context;
 mod switch;
 
 mod action;
+mod id;
+mod itimer;
 mod manager;
-mod pid;
+mod process;
 mod processor;
+mod scheduler;
 mod signal;
 #[allow(clippy::module_inception)]
 mod task;
+mod wait_queue;
 
+use id::TaskUserRes;
 use lazy_static::*;
-use task::{TaskControlBlock, TaskStatus};
+use manager::{remove_from_pid2process, remove_task, tick_task};
+use process::ProcessControlBlockInner;
+use signal::SIGSEGV;
+use task::TaskStatus;
 
+use crate::config::SIGRETURN_TRAMPOLINE;
 use crate::fs::{open_file, OpenFlags};
-pub use action::{SignalAction, SignalActions};
+use crate::mm::try_copy_to_user;
+use crate::sync::SpinLockGuard;
+pub use action::{SignalAction, SignalActionFlags, SignalActions, SIG_DFL, SIG_IGN};
 use alloc::sync::Arc;
+use alloc::vec::Vec;
 pub use context::TaskContext;
-pub use manager::{add_task, pid2task, remove_from_pid2task};
+use core::hint::spin_loop;
+pub use itimer::{
+    charge_current_itimers, check_real_itimers, current_enter_kernel, current_getitimer,
+    current_leave_kernel, current_setitimer, ITimer, TaskTimes, ITIMER_PROF, ITIMER_REAL,
+    ITIMER_VIRTUAL,
+};
+pub use manager::{add_task, pid2process, swap_out_any};
+pub use process::ProcessControlBlock;
 pub use processor::{
-    current_task, current_trap_cx, current_user_token, run_tasks, schedule, take_current_task,
-    Processor,
+    account_busy_tick, cpu_ticks, current_fault_in, current_fault_in_str, current_process,
+    current_task, current_trap_cx, current_trap_cx_user_va, current_user_token, run_tasks,
+    schedule, take_current_task, CpuTicks, Processor,
+};
+pub use scheduler::{MAX_NICE, MIN_NICE};
+pub use signal::{
+    SignalDefault, SignalFlags, SignalFrame, SignalInfo, SignalStack, UContext, CLD_EXITED,
+    ILL_ILLOPC, MAX_SIG, MINSIGSTKSZ, SEGV_MAPERR, SIGRTMAX, SIGRTMIN, SI_KERNEL, SI_QUEUE,
+    SI_TIMER, SI_USER, SS_DISABLE, SS_ONSTACK,
 };
-pub use signal::{SignalFlags, MAX_SIG};
+pub use task::TaskControlBlock;
+pub use wait_queue::WaitQueue;
 
-/// suspend current task, then run next task
+/// suspend current task, then run next task.
+/// the task is put back into the ready queue by [`run_tasks`] after it left the processor
 pub fn suspend_current_and_run_next() {
     // There must be an application running.
     let task = take_current_task().unwrap();
@@ -42,30 +70,173 @@ pub fn suspend_current_and_run_next() {
     // ---- access current TCB exclusively
     let mut task_inner = task.inner_exclusive_access();
     let task_cx_ptr = &mut task_inner.task_cx as *mut TaskContext;
-    // Change status to Ready
-    task_inner.task_status = TaskStatus::Ready;
+    // Change status to Ready, unless its process is exiting
+    if task_inner.task_status == TaskStatus::Running {
+        task_inner.task_status = TaskStatus::Ready;
+    }
     drop(task_inner);
     // ---- stop exclusively accessing current PCB
 
-    // push back to ready queue.
-    add_task(task);
+    drop(task);
     // jump to scheduling cycle
     schedule(task_cx_ptr);
 }
 
+/// 时钟中断时调用, 调度器决定抢占时切换到下一个任务
+pub fn preempt_current_on_tick() {
+    let task = current_task().unwrap();
+    if tick_task(&task) {
+        drop(task);
+        suspend_current_and_run_next();
+    }
+}
+
+/// 把当前任务标记为阻塞, 之后调用 [`block_current_and_run_next`] 才真正让出处理器.
+///
+/// 要在持有保护等待条件的锁时调用: 其它 hart 在释放锁之后改变条件时一定能看到任务已经阻塞,
+/// 从而唤醒它
+pub fn mark_current_blocked() {
+    let task = current_task().unwrap();
+    let mut task_inner = task.inner_exclusive_access();
+    if task_inner.task_status == TaskStatus::Running {
+        task_inner.task_status = TaskStatus::Blocked;
+    }
+}
+
+/// block current task, then run next task.
+/// the task has been marked by [`mark_current_blocked`], it is not in the ready queue
+/// until someone calls [`wakeup_task`] on it.
+///
+/// 已经被唤醒或者有需要打断阻塞的信号时直接返回, 调用者重新检查条件
+pub fn block_current_and_run_next() {
+    block_current(true);
+}
+
+/// 与 [`block_current_and_run_next`] 相同, 但是不会被信号打断
+pub fn block_current_uninterruptible_and_run_next() {
+    block_current(false);
+}
+
+fn block_current(interruptible: bool) {
+    // 信号在检查之后到达时任务已经被标记为阻塞, 会被唤醒
+    let interrupted = interruptible && current_has_interrupting_signal();
+    let task = current_task().unwrap();
+    let mut task_inner = task.inner_exclusive_access();
+    match task_inner.task_status {
+        TaskStatus::Blocked if !interrupted => {}
+        TaskStatus::Zombie => {}
+        _ => {
+            task_inner.task_status = TaskStatus::Running;
+            return;
+        }
+    }
+    drop(task_inner);
+    drop(task);
+    let task = take_current_task().unwrap();
+    let mut task_inner = task.inner_exclusive_access();
+    let task_cx_ptr = &mut task_inner.task_cx as *mut TaskContext;
+    drop(task_inner);
+    drop(task);
+    schedule(task_cx_ptr);
+}
+
+/// put a blocked task back into the ready queue, do nothing if it is not blocked.
+/// a task that has not left its processor yet is put back by [`run_tasks`]
+pub fn wakeup_task(task: Arc<TaskControlBlock>) {
+    let mut task_inner = task.inner_exclusive_access();
+    if task_inner.task_status != TaskStatus::Blocked {
+        return;
+    }
+    task_inner.task_status = TaskStatus::Ready;
+    let on_cpu = task_inner.on_cpu;
+    drop(task_inner);
+    if !on_cpu {
+        add_task(task);
+    }
+}
+
+/// 所在的进程正在退出时(线程已经被标记为僵尸), 当前线程不再返回用户态, 直接离开处理器
+pub fn exit_current_if_zombie() {
+    let task = current_task().unwrap();
+    if task.inner_exclusive_access().task_status != TaskStatus::Zombie {
+        return;
+    }
+    drop(task);
+    take_current_task();
+    // we do not have to save task context
+    let mut _unused = TaskContext::zero_init();
+    schedule(&mut _unused as *mut _);
+}
+
+/// 唤醒进程中所有阻塞的线程, 让它们检查新到达的信号
+pub fn wakeup_process(process: &ProcessControlBlock) {
+    let inner = process.inner_exclusive_access();
+    for task in inner.tasks.iter().flatten() {
+        wakeup_task(Arc::clone(task));
+    }
+}
+
+/// 正常退出时的 wait status: 低 8 位的退出码放在 8~15 位
+pub fn exit_status(exit_code: i32) -> i32 {
+    (exit_code & 0xff) << 8
+}
+
+/// 被信号杀死时的 wait status: 信号编号放在低 7 位
+pub fn signal_status(signum: i32) -> i32 {
+    signum & 0x7f
+}
+
+/// wait status 中表示产生了 core dump 的位
+pub const CORE_DUMP_FLAG: i32 = 0x80;
+
 /// pid of usertests app in make run TEST=1
 pub const IDLE_PID: usize = 0;
 
 #[cfg(feature = "board_qemu")]
 use crate::board::QEMUExit;
 
-/// Exit the current 'Running' task and run the next task in task list.
+/// Exit the current 'Running' thread and run the next task in task list.
+///
+/// 线程的资源保留到它被`waittid`回收为止. 主线程退出时整个进程以`exit_code`退出
 pub fn exit_current_and_run_next(exit_code: i32) {
     // take from Processor
     let task = take_current_task().unwrap();
+    let process = task.process.upgrade().unwrap();
+    if task.gettid() == 0 {
+        exit_process(task, process, exit_status(exit_code));
+    } else {
+        let mut task_inner = task.inner_exclusive_access();
+        task_inner.task_status = TaskStatus::Zombie;
+        task_inner.exit_code = Some(exit_code);
+        drop(task_inner);
+        let mut process_inner = process.inner_exclusive_access();
+        process_inner.deadlock_detector.remove_thread(task.gettid());
+        process_inner.thread_exit_wait.wake_all();
+        drop(process_inner);
+        drop(process);
+        drop(task);
+    }
+    // we do not have to save task context
+    let mut _unused = TaskContext::zero_init();
+    schedule(&mut _unused as *mut _);
+}
 
-    #[cfg(feature = "board_qemu")]
-    let pid = task.getpid();
+/// Exit the process of current task with all its threads, and run the next task in task list.
+///
+/// `exit_code` is the wait status built by [`exit_status`] or [`signal_status`].
+pub fn exit_current_process_and_run_next(exit_code: i32) {
+    let task = take_current_task().unwrap();
+    let process = task.process.upgrade().unwrap();
+    exit_process(task, process, exit_code);
+    // we do not have to save task context
+    let mut _unused = TaskContext::zero_init();
+    schedule(&mut _unused as *mut _);
+}
+
+/// 进程退出: 回收所有线程和进程的资源, 只留下正在运行的`task`(它的内核栈还在使用).
+/// The parent gets a SIGCHLD and is woken up if it is blocked in `waitpid`
+fn exit_process(task: Arc<TaskControlBlock>, process: Arc<ProcessControlBlock>, exit_code: i32) {
+    let pid = process.getpid();
     #[cfg(feature = "board_qemu")]
     if pid == IDLE_PID {
         info!("Idle process exit with exit_code {} ...", exit_code);
@@ -78,154 +249,261 @@ pub fn exit_current_and_run_next(exit_code: i32) {
         }
     }
 
-    // remove from pid2task
-    remove_from_pid2task(task.getpid());
-    // **** access current TCB exclusively
-    let mut inner = task.inner_exclusive_access();
-    // Change status to Zombie
-    inner.task_status = TaskStatus::Zombie;
-    // Record exit code
+    // **** access current PCB exclusively
+    // PCB locks are only nested from a parent to its children (see `sys_waitpid`),
+    // so the parent and children are collected here and locked after this PCB is released
+    let mut inner = process.inner_exclusive_access();
+    if inner.is_zombie || task.inner_exclusive_access().task_status == TaskStatus::Zombie {
+        // another thread on another hart is already tearing the process down or executing
+        // a new program, it has marked this thread as a zombie and waits for it to leave the processor
+        return;
+    }
+    // the exit code is recorded together with the flag, a parent may reap it right away
+    inner.is_zombie = true;
     inner.exit_code = exit_code;
+    let parent = inner.parent.as_ref().and_then(|parent| parent.upgrade());
+    let children = core::mem::take(&mut inner.children);
+    let rejected_syscalls = inner.rejected_syscalls;
+    drop(inner);
+    // **** release current PCB
+    // remove from pid2process
+    remove_from_pid2process(pid);
+    if rejected_syscalls != 0 {
+        warn!(
+            "[kernel] pid {} exited with {} rejected syscalls",
+            pid, rejected_syscalls
+        );
+    }
     // do not move to its parent but under initproc
 
-    // ++++++ access initproc TCB exclusively
+    // ++++++ access initproc PCB exclusively
+    let mut has_zombie = false;
     {
         let mut initproc_inner = INITPROC.inner_exclusive_access();
-        for child in inner.children.iter() {
-            child.inner_exclusive_access().parent = Some(Arc::downgrade(&INITPROC));
-            initproc_inner.children.push(child.clone());
+        for child in children {
+            let mut child_inner = child.inner_exclusive_access();
+            child_inner.parent = Some(Arc::downgrade(&INITPROC));
+            has_zombie |= child_inner.is_zombie;
+            drop(child_inner);
+            initproc_inner.children.push(child);
         }
     }
-    // ++++++ release parent PCB
+    // ++++++ release initproc PCB
+    // zombies handed over to initproc have to be reaped by it
+    if has_zombie {
+        wakeup_process(&INITPROC);
+    }
+    // notify the parent
+    if let Some(parent) = parent {
+        parent.inner_exclusive_access().add_signal(SignalInfo {
+            pid: pid as u32,
+            ..SignalInfo::new(SignalFlags::SIGCHLD, CLD_EXITED)
+        });
+        wakeup_process(&parent);
+    }
+
+    // the current thread is kept until the process is reaped
+    let mut recycle_res = stop_other_threads(process.inner_exclusive_access(), &task);
+    let mut task_inner = task.inner_exclusive_access();
+    task_inner.task_status = TaskStatus::Zombie;
+    recycle_res.extend(task_inner.res.take());
+    drop(task_inner);
+    // dropping `TaskUserRes` accesses the PCB, so it can not be done while holding it
+    recycle_res.clear();
 
-    inner.children.clear();
+    let mut inner = process.inner_exclusive_access();
     // deallocate user space
     inner.memory_set.recycle_data_pages();
     // drop file descriptors
     inner.fd_table.clear();
     drop(inner);
     // **** release current PCB
-    // drop task manually to maintain rc correctly
-    drop(task);
-    // we do not have to save task context
-    let mut _unused = TaskContext::zero_init();
-    schedule(&mut _unused as *mut _);
+}
+
+/// 停止进程中除`task`之外的所有线程并把它们从`tasks`中移除, 返回它们的用户资源.
+/// 调用者持有 PCB 的锁`inner`检查进程的状态, 这里在等待之前释放它.
+///
+/// 线程被标记为僵尸并移出就绪队列, 阻塞的线程可能还被等待队列引用;
+/// 在其它 hart 上运行的线程下一次进入内核时离开处理器, 这里等到它离开为止.
+/// 用户资源 drop 时会访问 PCB, 调用者要在不持有 PCB 的锁时 drop 它们
+fn stop_other_threads(
+    mut inner: SpinLockGuard<'_, ProcessControlBlockInner>,
+    task: &Arc<TaskControlBlock>,
+) -> Vec<TaskUserRes> {
+    let mut recycle_res = Vec::new();
+    let mut running = Vec::new();
+    for slot in inner.tasks.iter_mut() {
+        let thread = match slot {
+            Some(thread) if !Arc::ptr_eq(thread, task) => slot.take().unwrap(),
+            _ => continue,
+        };
+        remove_task(&thread);
+        let mut thread_inner = thread.inner_exclusive_access();
+        thread_inner.task_status = TaskStatus::Zombie;
+        if thread_inner.on_cpu {
+            drop(thread_inner);
+            running.push(thread);
+        } else {
+            recycle_res.extend(thread_inner.res.take());
+        }
+    }
+    drop(inner);
+    for thread in running {
+        while thread.inner_exclusive_access().on_cpu {
+            spin_loop();
+        }
+        recycle_res.extend(thread.inner_exclusive_access().res.take());
+    }
+    recycle_res
 }
 
 lazy_static! {
     /// 初始进程
-    pub static ref INITPROC: Arc<TaskControlBlock> = Arc::new({
+    pub static ref INITPROC: Arc<ProcessControlBlock> = {
         let inode = open_file("initproc", OpenFlags::RDONLY).unwrap();
-        let v = inode.read_all();
-        TaskControlBlock::new(v.as_slice())
-    });
+        ProcessControlBlock::new(inode.as_ref())
+    };
 }
 
-/// 将初始进程添加进任务管理器
+/// 创建初始进程, 它的主线程被添加进任务管理器
 pub fn add_initproc() {
-    add_task(INITPROC.clone());
+    let _initproc = INITPROC.clone();
 }
 
-/// 检查当前process的signals状态.
-pub fn check_signals_error_of_current() -> Option<(i32, &'static str)> {
-    let task = current_task().unwrap();
-    let task_inner = task.inner_exclusive_access();
-    // println!(
-    //     "[K] check_signals_error_of_current {:?}",
-    //     task_inner.signals
-    // );
-    task_inner.signals.check_error()
+/// 当前进程是否被信号杀死了, 返回要记录的 wait status
+pub fn check_signals_killed_of_current() -> Option<i32> {
+    current_process().inner_exclusive_access().killed
 }
 
 /// 添加信号到task的signals中
-pub fn current_add_signal(signal: SignalFlags) {
-    let task = current_task().unwrap();
-    let mut task_inner = task.inner_exclusive_access();
-    task_inner.signals |= signal;
+pub fn current_add_signal(info: SignalInfo) {
+    let process = current_process();
+    let mut process_inner = process.inner_exclusive_access();
+    process_inner.add_signal(info);
     // println!(
-    //     "[K] current_add_signal:: current task sigflag {:?}",
-    //     task_inner.signals
+    //     "[K] current_add_signal:: current process sigflag {:?}",
+    //     process_inner.signals
     // );
 }
 
-/// 内核信号处理器.
-fn call_kernel_signal_handler(signal: SignalFlags) {
-    let task = current_task().unwrap();
-    let mut task_inner = task.inner_exclusive_access();
-    match signal {
-        SignalFlags::SIGSTOP => {
-            task_inner.frozen = true;
-            task_inner.signals ^= SignalFlags::SIGSTOP;
-        }
-        SignalFlags::SIGCONT => {
-            if task_inner.signals.contains(SignalFlags::SIGCONT) {
-                task_inner.signals ^= SignalFlags::SIGCONT;
-                task_inner.frozen = false;
-            }
+/// 添加一个由当前线程的异常引起的信号, 见[`ProcessControlBlockInner::force_signal`]
+pub fn current_force_signal(info: SignalInfo) {
+    current_process()
+        .inner_exclusive_access()
+        .force_signal(info);
+}
+
+/// 当前任务是否有需要打断阻塞操作的信号
+pub fn current_has_interrupting_signal() -> bool {
+    current_process()
+        .inner_exclusive_access()
+        .has_interrupting_signal()
+}
+
+/// 执行信号的默认动作. SIGKILL 和 SIGSTOP 不能被捕获, 总是执行默认动作
+fn call_kernel_signal_handler(sig: usize, signal: SignalFlags) {
+    let process = current_process();
+    let mut process_inner = process.inner_exclusive_access();
+    process_inner.take_signal(sig);
+    match signal.default_action() {
+        SignalDefault::Terminate => {
+            process_inner.killed = Some(signal_status(sig as i32));
         }
-        _ => {
-            // println!(
-            //     "[K] call_kernel_signal_handler:: current task sigflag {:?}",
-            //     task_inner.signals
-            // );
-            task_inner.killed = true;
+        SignalDefault::Core => {
+            process_inner.killed = Some(signal_status(sig as i32) | CORE_DUMP_FLAG);
         }
+        SignalDefault::Stop => process_inner.frozen = true,
+        // SIGCONT has already resumed the process when it was sent
+        SignalDefault::Continue | SignalDefault::Ignore => {}
     }
 }
 
-/// 用户信号处理器.
+/// 用户信号处理器, 在当前线程上执行.
+///
+/// 在用户栈上压入信号帧, 保存被打断时的寄存器和信号掩码, 然后跳转到信号处理函数.
+/// 设置了`SignalActionFlags::ONSTACK`时, 信号帧压入备用信号栈.
+/// 信号处理函数返回到[`SIGRETURN_TRAMPOLINE`], 由`sigreturn`恢复信号帧中的上下文
 fn call_user_signal_handler(sig: usize, signal: SignalFlags) {
-    let task = current_task().unwrap();
-    let mut task_inner = task.inner_exclusive_access();
-
-    let handler = task_inner.signal_actions.table[sig].handler;
-    if handler != 0 {
-        // user handler
+    let process = current_process();
+    let mut process_inner = process.inner_exclusive_access();
 
-        // change current mask
-        task_inner.signal_mask = task_inner.signal_actions.table[sig].mask;
-        // handle flag
-        task_inner.handling_sig = sig as isize;
-        task_inner.signals ^= signal;
-
-        // backup trapframe
-        let mut trap_ctx = task_inner.get_trap_cx();
-        task_inner.trap_ctx_backup.push(Some(*trap_ctx));
+    let action = process_inner.signal_actions.table[sig];
+    let info = process_inner.take_signal(sig);
+    let trap_ctx = current_trap_cx();
+    let mut gregs = trap_ctx.x;
+    gregs[0] = trap_ctx.sepc;
+    let frame = SignalFrame {
+        info,
+        ucontext: UContext {
+            sigmask: process_inner.signal_mask,
+            gregs,
+        },
+    };
+    let frame_size = core::mem::size_of::<SignalFrame>();
+    let mut stack_top = trap_ctx.x[2];
+    if action.flags.contains(SignalActionFlags::ONSTACK) {
+        let task = current_task().unwrap();
+        let signal_stack = task.inner_exclusive_access().signal_stack;
+        // a nested signal keeps using the alternate stack it is already on
+        if signal_stack.flags != SS_DISABLE && !signal_stack.contains(stack_top) {
+            stack_top = signal_stack.sp + signal_stack.size;
+        }
+    }
+    // the stack pointer stays 16-byte aligned
+    let frame_addr = (stack_top - frame_size) & !0xf;
+    let token = process_inner.get_user_token();
+    if !process_inner
+        .memory_set
+        .fault_in(frame_addr, frame_size, true)
+        || try_copy_to_user(token, frame_addr as *mut SignalFrame, &frame).is_err()
+    {
+        // the stack is unusable, the process is killed by SIGSEGV
+        process_inner.signal_actions.table[SIGSEGV] = SignalAction::default();
+        process_inner.force_signal(SignalInfo {
+            addr: frame_addr,
+            ..SignalInfo::new(SignalFlags::SIGSEGV, SI_KERNEL)
+        });
+        return;
+    }
+    current_task()
+        .unwrap()
+        .inner_exclusive_access()
+        .signal_frames
+        .push(frame_addr);
 
-        // modify trapframe
-        trap_ctx.sepc = handler;
+    // block the signal itself and the signals in the mask of the action
+    process_inner.signal_mask |= action.mask | signal;
+    process_inner.signal_mask -= SignalFlags::SIGKILL | SignalFlags::SIGSTOP;
 
-        // put args (a0)
-        trap_ctx.x[10] = sig;
-    } else {
-        // default action
-        println!("[K] task/call_user_signal_handler: default action: ignore it or kill process");
+    // handler(signo, info, ucontext), returns to the trampoline
+    trap_ctx.sepc = action.handler;
+    trap_ctx.x[1] = SIGRETURN_TRAMPOLINE;
+    trap_ctx.x[2] = frame_addr;
+    trap_ctx.x[10] = sig;
+    if action.flags.contains(SignalActionFlags::SIGINFO) {
+        // `info` is the first field of the frame
+        trap_ctx.x[11] = frame_addr;
+        trap_ctx.x[12] = frame_addr + core::mem::size_of::<SignalInfo>();
     }
 }
 
-/// 逐个检查等待中的signals
+/// 逐个检查等待中的signals. 信号处理函数执行期间, 它的信号和`SignalAction::mask`中的信号被屏蔽
 fn check_pending_signals() {
     for sig in 0..(MAX_SIG + 1) {
-        let task = current_task().unwrap();
-        let task_inner = task.inner_exclusive_access();
+        let process = current_process();
+        let process_inner = process.inner_exclusive_access();
         let signal = SignalFlags::from_bits(1 << sig).unwrap();
-        if task_inner.signals.contains(signal) && (!task_inner.signal_mask.contains(signal)) {
-            if task_inner.handling_sig == -1
-                || !task_inner.signal_actions.table[task_inner.handling_sig as usize]
-                    .mask
-                    .contains(signal)
-            {
-                drop(task_inner);
-                drop(task);
-                if signal == SignalFlags::SIGKILL
-                    || signal == SignalFlags::SIGSTOP
-                    || signal == SignalFlags::SIGCONT
-                    || signal == SignalFlags::SIGDEF
-                {
-                    // signal is a kernel signal
-                    call_kernel_signal_handler(signal);
-                } else {
-                    // signal is a user signal
+        if process_inner.signals.contains(signal) && (!process_inner.signal_mask.contains(signal)) {
+            let handler = process_inner.signal_actions.table[sig].handler;
+            drop(process_inner);
+            drop(process);
+            match handler {
+                SIG_DFL => call_kernel_signal_handler(sig, signal),
+                // ignored signals are normally dropped when they are sent
+                SIG_IGN => current_process()
+                    .inner_exclusive_access()
+                    .discard_signal(sig),
+                _ => {
                     call_user_signal_handler(sig, signal);
                     return;
                 }
@@ -238,12 +516,12 @@ fn check_pending_signals() {
 pub fn handle_signals() {
     check_pending_signals();
     loop {
-        let task = current_task().unwrap();
-        let task_inner = task.inner_exclusive_access();
-        let frozen_flag = task_inner.frozen;
-        let killed_flag = task_inner.killed;
-        drop(task_inner);
-        drop(task);
+        let process = current_process();
+        let process_inner = process.inner_exclusive_access();
+        let frozen_flag = process_inner.frozen;
+        let killed_flag = process_inner.killed.is_some();
+        drop(process_inner);
+        drop(process);
         if (!frozen_flag) || killed_flag {
             break;
         }
@@ -0,0 +1,481 @@
+//! 进程控制块: 地址空间, 文件描述符表和信号等资源由进程内的所有线程共享
+
+use super::id::{pid_alloc, PidHandle, RecycleAllocator, TaskUserRes};
+use super::manager::insert_into_pid2process;
+use super::signal::{SignalInfo, SIGQUEUE_MAX, SIGRTMIN, STOP_SIGNALS};
+use super::{
+    add_task, current_task, signal_status, stop_other_threads, ITimer, SignalAction, SignalActions,
+    SignalDefault, SignalFlags, SignalStack, TaskControlBlock, TaskStatus, WaitQueue, SIG_IGN,
+};
+use crate::config::USER_STACK_SIZE;
+use crate::fs::{File, OSInode, Stdin, Stdout};
+use crate::mm::{translated_refmut, MemorySet, KERNEL_SPACE};
+use crate::sync::{Condvar, DeadlockDetector, Mutex, Semaphore, SpinLock, SpinLockGuard};
+use crate::trap::{trap_handler, TrapContext};
+use alloc::collections::VecDeque;
+use alloc::string::String;
+use alloc::sync::{Arc, Weak};
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// 进程控制块
+pub struct ProcessControlBlock {
+    // immutable
+    /// 进程 id
+    pub pid: PidHandle,
+    // mutable
+    inner: SpinLock<ProcessControlBlockInner>,
+}
+
+/// 进程控制块中可变的部分
+pub struct ProcessControlBlockInner {
+    /// 进程是否已经退出, 等待父进程回收
+    pub is_zombie: bool,
+    /// 应用的地址空间
+    pub memory_set: MemorySet,
+    /// 父进程(weak引用,不影响父进程的回收)
+    pub parent: Option<Weak<ProcessControlBlock>>,
+    /// 子进程列表
+    pub children: Vec<Arc<ProcessControlBlock>>,
+    /// 退出状态, 编码方式与 POSIX 的 wait status 相同
+    pub exit_code: i32,
+    /// 文件描述符表
+    pub fd_table: Vec<Option<Arc<dyn File + Send + Sync>>>,
+    /// 要响应的信号
+    pub signals: SignalFlags,
+    /// 要屏蔽的信号
+    pub signal_mask: SignalFlags,
+    /// 等待处理的普通信号的附加信息, 下标为信号编号
+    pub signal_infos: [SignalInfo; SIGRTMIN],
+    /// 排队等待处理的实时信号, 按照产生的顺序
+    pub signal_queue: VecDeque<SignalInfo>,
+    /// 信号处理例程表
+    pub signal_actions: SignalActions,
+    /// 被信号杀死时的 wait status, 由`trap_handler`在返回用户态之前让进程退出
+    pub killed: Option<i32>,
+    /// 进程是否已经被暂停了
+    pub frozen: bool,
+    /// 堆的起始地址
+    pub heap_bottom: usize,
+    /// 堆的结尾(program break)
+    pub program_brk: usize,
+    /// 被拒绝的(无法识别的)系统调用次数, 用于调试
+    pub rejected_syscalls: usize,
+    /// 线程表, 下标为 tid. 线程被`waittid`回收之后对应的项为`None`
+    pub tasks: Vec<Option<Arc<TaskControlBlock>>>,
+    /// tid 申请器
+    pub task_res_allocator: RecycleAllocator,
+    /// 主线程的用户栈所在的位置(以 tid 编号). 由其它线程 fork 出的进程中,
+    /// 主线程沿用调用 fork 的线程的用户栈, 这个位置与主线程的位置互换
+    pub main_ustack_slot: usize,
+    /// 在`waittid`中等待其它线程退出的线程
+    pub thread_exit_wait: WaitQueue,
+    /// 互斥锁表, 下标为用户程序使用的 id
+    pub mutex_list: Vec<Option<Arc<Mutex>>>,
+    /// 信号量表
+    pub semaphore_list: Vec<Option<Arc<Semaphore>>>,
+    /// 条件变量表
+    pub condvar_list: Vec<Option<Arc<Condvar>>>,
+    /// 互斥锁和信号量的分配情况, 用于死锁检测
+    pub deadlock_detector: DeadlockDetector,
+    /// 间隔定时器, 下标为`ITIMER_REAL`, `ITIMER_VIRTUAL`和`ITIMER_PROF`
+    pub itimers: [ITimer; 3],
+}
+
+impl ProcessControlBlockInner {
+    /// 地址空间的 token
+    pub fn get_user_token(&self) -> usize {
+        self.memory_set.token()
+    }
+    /// 是否有未被屏蔽的信号需要打断阻塞的系统调用.
+    /// 被忽略的信号在产生时就被丢弃了, 不会打断
+    pub fn has_interrupting_signal(&self) -> bool {
+        !(self.signals - self.signal_mask).is_empty()
+    }
+    /// 添加一个等待处理的信号. 普通信号已经在等待时只保留最新的附加信息,
+    /// 实时信号则排在队列的末尾, 队列已满时返回`false`.
+    ///
+    /// SIGCONT 在产生时就让进程继续运行并丢弃等待中的暂停信号, 暂停信号则丢弃等待中的 SIGCONT.
+    /// 被忽略的信号直接丢弃
+    pub fn add_signal(&mut self, info: SignalInfo) -> bool {
+        let signum = info.signo as usize;
+        let signal = SignalFlags::from_bits_truncate(1 << signum);
+        match signal.default_action() {
+            SignalDefault::Continue => {
+                self.frozen = false;
+                self.signals -= STOP_SIGNALS;
+            }
+            SignalDefault::Stop => self.signals -= SignalFlags::SIGCONT,
+            _ => {}
+        }
+        if self.signal_actions.is_ignored(signum) {
+            return true;
+        }
+        if signum >= SIGRTMIN {
+            if self.signal_queue.len() >= SIGQUEUE_MAX {
+                return false;
+            }
+            self.signal_queue.push_back(info);
+        } else {
+            self.signal_infos[signum] = info;
+        }
+        self.signals |= signal;
+        true
+    }
+    /// 取出一个等待中的信号`signum`的附加信息. 实时信号取出最早排队的一个,
+    /// 队列中没有同样的信号之后才清除等待的标志
+    pub fn take_signal(&mut self, signum: usize) -> SignalInfo {
+        let signal = SignalFlags::from_bits_truncate(1 << signum);
+        if signum < SIGRTMIN {
+            self.signals -= signal;
+            return self.signal_infos[signum];
+        }
+        let index = self
+            .signal_queue
+            .iter()
+            .position(|info| info.signo as usize == signum)
+            .unwrap();
+        let info = self.signal_queue.remove(index).unwrap();
+        if !self
+            .signal_queue
+            .iter()
+            .any(|info| info.signo as usize == signum)
+        {
+            self.signals -= signal;
+        }
+        info
+    }
+    /// 丢弃等待中的信号`signum`, 包括排队的所有实时信号
+    pub fn discard_signal(&mut self, signum: usize) {
+        self.signals -= SignalFlags::from_bits_truncate(1 << signum);
+        self.signal_queue
+            .retain(|info| info.signo as usize != signum);
+    }
+    /// 添加一个由当前线程的异常引起的信号. 如果这个信号被屏蔽或者忽略,
+    /// 它的处理函数恢复为默认动作并解除屏蔽, 使进程不会在同一条指令上反复出错
+    pub fn force_signal(&mut self, info: SignalInfo) {
+        let signum = info.signo as usize;
+        let signal = SignalFlags::from_bits_truncate(1 << signum);
+        if self.signal_mask.contains(signal) || self.signal_actions.table[signum].handler == SIG_IGN
+        {
+            self.signal_actions.table[signum] = SignalAction::default();
+            self.signal_mask -= signal;
+        }
+        // signals raised by exceptions are never real-time signals, so they are never dropped
+        self.add_signal(info);
+    }
+    /// 分配最小的空闲文件描述符
+    pub fn alloc_fd(&mut self) -> usize {
+        if let Some(fd) = (0..self.fd_table.len()).find(|fd| self.fd_table[*fd].is_none()) {
+            fd
+        } else {
+            self.fd_table.push(None);
+            self.fd_table.len() - 1
+        }
+    }
+    /// 线程`tid`的用户栈所在的位置(以 tid 编号), 见[`Self::main_ustack_slot`]
+    pub fn ustack_slot(&self, tid: usize) -> usize {
+        if tid == 0 {
+            self.main_ustack_slot
+        } else if tid == self.main_ustack_slot {
+            0
+        } else {
+            tid
+        }
+    }
+    /// 分配一个 tid
+    pub fn alloc_tid(&mut self) -> usize {
+        self.task_res_allocator.alloc()
+    }
+    /// 回收一个 tid
+    pub fn dealloc_tid(&mut self, tid: usize) {
+        self.task_res_allocator.dealloc(tid)
+    }
+    /// 线程`tid`
+    pub fn get_task(&self, tid: usize) -> Option<Arc<TaskControlBlock>> {
+        self.tasks.get(tid).and_then(|task| task.clone())
+    }
+}
+
+impl ProcessControlBlock {
+    /// 获取inner
+    pub fn inner_exclusive_access(&self) -> SpinLockGuard<'_, ProcessControlBlockInner> {
+        self.inner.exclusive_access()
+    }
+    /// 尝试获取inner, 已经被加锁时返回`None`
+    pub fn try_inner_exclusive_access(
+        &self,
+    ) -> Option<SpinLockGuard<'_, ProcessControlBlockInner>> {
+        self.inner.try_exclusive_access()
+    }
+    /// 获取pid
+    pub fn getpid(&self) -> usize {
+        self.pid.0
+    }
+    /// 从 ELF 文件创建只有一个主线程的新进程, 并把主线程加入就绪队列
+    pub fn new(elf_file: &OSInode) -> Arc<Self> {
+        // 解析传入的 ELF 格式数据构造应用的地址空间
+        let elf_data = elf_file.read_all();
+        let (memory_set, heap_bottom, entry_point) =
+            MemorySet::from_elf(elf_data.as_slice(), elf_file.inode()).expect("invalid elf!");
+        let process = Arc::new(Self {
+            pid: pid_alloc(),
+            inner: SpinLock::new(ProcessControlBlockInner {
+                is_zombie: false,
+                memory_set,
+                parent: None,
+                children: Vec::new(),
+                exit_code: 0,
+                fd_table: vec![
+                    // 0 -> stdin
+                    Some(Arc::new(Stdin)),
+                    // 1 -> stdout
+                    Some(Arc::new(Stdout)),
+                    // 2 -> stderr
+                    Some(Arc::new(Stdout)),
+                ],
+                signals: SignalFlags::empty(),
+                signal_mask: SignalFlags::empty(),
+                signal_infos: [SignalInfo::default(); SIGRTMIN],
+                signal_queue: VecDeque::new(),
+                signal_actions: SignalActions::default(),
+                killed: None,
+                frozen: false,
+                heap_bottom,
+                program_brk: heap_bottom,
+                rejected_syscalls: 0,
+                tasks: Vec::new(),
+                task_res_allocator: RecycleAllocator::new(),
+                main_ustack_slot: 0,
+                thread_exit_wait: WaitQueue::new(),
+                mutex_list: Vec::new(),
+                semaphore_list: Vec::new(),
+                condvar_list: Vec::new(),
+                deadlock_detector: DeadlockDetector::default(),
+                itimers: [ITimer::default(); 3],
+            }),
+        });
+        // create the main thread, which allocates the user stack and the trap context
+        let task = Arc::new(TaskControlBlock::new(Arc::clone(&process), true));
+        // prepare TrapContext in user space
+        let task_inner = task.inner_exclusive_access();
+        let ustack_top = task_inner.res.as_ref().unwrap().ustack_top();
+        *task_inner.get_trap_cx() = TrapContext::app_init_context(
+            entry_point,
+            ustack_top,
+            KERNEL_SPACE.exclusive_access().token(),
+            task.kstack.get_top(),
+            trap_handler as usize,
+        );
+        drop(task_inner);
+        process
+            .inner_exclusive_access()
+            .tasks
+            .push(Some(Arc::clone(&task)));
+        insert_into_pid2process(process.getpid(), Arc::clone(&process));
+        add_task(task);
+        process
+    }
+    /// `exec`时参数在用户栈上占用的字节数, 调用者要保证它不超过[`USER_STACK_SIZE`]
+    pub fn args_size(args: &[String]) -> usize {
+        (args.len() + 1) * core::mem::size_of::<usize>()
+            + args.iter().map(|arg| arg.len() + 1).sum::<usize>()
+    }
+    /// implementation of `exec`: 其它线程都被终止, 调用的线程成为新程序的主线程.
+    /// `elf`是[`MemorySet::from_elf`]为新程序构造好的地址空间, 堆底和入口地址.
+    ///
+    /// 调用的线程已经被别的线程终止(进程正在退出, 或者别的线程也在`exec`)时什么都不做
+    pub fn exec(
+        self: &Arc<Self>,
+        (memory_set, heap_bottom, entry_point): (MemorySet, usize, usize),
+        args: Vec<String>,
+    ) {
+        let task = current_task().unwrap();
+        let inner = self.inner_exclusive_access();
+        if inner.is_zombie || task.inner_exclusive_access().task_status == TaskStatus::Zombie {
+            return;
+        }
+        let mut recycle_res = stop_other_threads(inner, &task);
+        recycle_res.extend(task.inner_exclusive_access().res.take());
+        // dropping `TaskUserRes` accesses the PCB, so it can not be done while holding it
+        recycle_res.clear();
+        let token = memory_set.token();
+        // **** access current PCB exclusively
+        let mut inner = self.inner_exclusive_access();
+        // all tids have been recycled, start over so that the calling thread gets tid 0
+        inner.tasks = vec![Some(Arc::clone(&task))];
+        inner.task_res_allocator = RecycleAllocator::new();
+        inner.main_ustack_slot = 0;
+        // substitute memory_set, write back shared file mappings of the old one
+        inner.memory_set.recycle_data_pages();
+        inner.memory_set = memory_set;
+        // reset the heap
+        inner.heap_bottom = heap_bottom;
+        inner.program_brk = heap_bottom;
+        // synchronization objects belong to the old program
+        inner.mutex_list.clear();
+        inner.semaphore_list.clear();
+        inner.condvar_list.clear();
+        inner.deadlock_detector = DeadlockDetector::default();
+        inner.signal_actions.reset_handlers();
+        drop(inner);
+        // **** release current PCB
+        // the main thread gets a new user stack and trap context in the new memory_set
+        let res = TaskUserRes::new(Arc::clone(self), true);
+        let mut task_inner = task.inner_exclusive_access();
+        task_inner.trap_cx_ppn = res.trap_cx_ppn();
+        let mut user_sp = res.ustack_top();
+        task_inner.res = Some(res);
+        task_inner.signal_frames.clear();
+        task_inner.signal_stack = SignalStack::default();
+        drop(task_inner);
+
+        // push arguments on user stack, populate the lazily allocated stack pages first
+        let args_size = Self::args_size(&args);
+        let mut inner = self.inner_exclusive_access();
+        if !inner
+            .memory_set
+            .fault_in(user_sp - args_size, args_size, true)
+        {
+            // out of memory, the old program is gone and the process can only be killed
+            inner.killed = Some(signal_status(
+                SignalFlags::SIGKILL.bits().trailing_zeros() as i32
+            ));
+            return;
+        }
+        drop(inner);
+        user_sp -= (args.len() + 1) * core::mem::size_of::<usize>();
+        let argv_base = user_sp;
+        let mut argv: Vec<_> = (0..=args.len())
+            .map(|arg| {
+                translated_refmut(
+                    token,
+                    (argv_base + arg * core::mem::size_of::<usize>()) as *mut usize,
+                )
+            })
+            .collect();
+        *argv[args.len()] = 0;
+        for i in 0..args.len() {
+            user_sp -= args[i].len() + 1;
+            *argv[i] = user_sp;
+            let mut p = user_sp;
+            for c in args[i].as_bytes() {
+                *translated_refmut(token, p as *mut u8) = *c;
+                p += 1;
+            }
+            *translated_refmut(token, p as *mut u8) = 0;
+        }
+        // make the user_sp aligned to 8B for k210 platform
+        user_sp -= user_sp % core::mem::size_of::<usize>();
+
+        // initialize trap_cx
+        let mut trap_cx = TrapContext::app_init_context(
+            entry_point,
+            user_sp,
+            KERNEL_SPACE.exclusive_access().token(),
+            task.kstack.get_top(),
+            trap_handler as usize,
+        );
+        // 这两个参数在第一次进入对应应用的用户态的时候会被接收并用于还原命令行参数
+        trap_cx.x[10] = args.len();
+        trap_cx.x[11] = argv_base;
+        *task.inner_exclusive_access().get_trap_cx() = trap_cx;
+    }
+    /// implementation for `fork`, 子进程中只有调用的线程的副本, 它成为子进程的主线程.
+    /// 子进程的主线程被加入就绪队列. 物理内存不足以复制地址空间时返回`None`
+    pub fn fork(self: &Arc<Self>) -> Option<Arc<Self>> {
+        let current = current_task().unwrap();
+        // ---- hold parent PCB lock
+        let mut parent_inner = self.inner_exclusive_access();
+        // copy user space(include trap context), user pages are shared copy-on-write
+        let mut memory_set = MemorySet::from_existed_user(&mut parent_inner.memory_set)?;
+        // the other threads are not copied, neither are their user stacks and trap contexts
+        for thread in parent_inner.tasks.iter().flatten() {
+            if Arc::ptr_eq(thread, &current) {
+                continue;
+            }
+            if let Some(res) = thread.inner_exclusive_access().res.as_ref() {
+                res.unmap_user_res(&mut memory_set);
+            }
+        }
+        // the calling thread becomes the main thread of child, keeping its user stack
+        let main_ustack_slot = {
+            let current_inner = current.inner_exclusive_access();
+            let res = current_inner.res.as_ref().unwrap();
+            if !res.move_trap_cx_to_main(&mut memory_set) {
+                return None;
+            }
+            parent_inner.ustack_slot(res.tid)
+        };
+        // copy fd table
+        let mut new_fd_table: Vec<Option<Arc<dyn File + Send + Sync>>> = Vec::new();
+        for fd in parent_inner.fd_table.iter() {
+            if let Some(file) = fd {
+                new_fd_table.push(Some(file.clone()));
+            } else {
+                new_fd_table.push(None);
+            }
+        }
+        let child = Arc::new(Self {
+            pid: pid_alloc(),
+            inner: SpinLock::new(ProcessControlBlockInner {
+                is_zombie: false,
+                memory_set,
+                parent: Some(Arc::downgrade(self)),
+                children: Vec::new(),
+                exit_code: 0,
+                fd_table: new_fd_table,
+                signals: SignalFlags::empty(),
+                // inherit the signal_mask and signal_action
+                signal_mask: parent_inner.signal_mask,
+                signal_infos: [SignalInfo::default(); SIGRTMIN],
+                signal_queue: VecDeque::new(),
+                signal_actions: parent_inner.signal_actions.clone(),
+                killed: None,
+                frozen: false,
+                heap_bottom: parent_inner.heap_bottom,
+                program_brk: parent_inner.program_brk,
+                rejected_syscalls: 0,
+                tasks: Vec::new(),
+                task_res_allocator: RecycleAllocator::new(),
+                main_ustack_slot,
+                thread_exit_wait: WaitQueue::new(),
+                // synchronization objects are not inherited
+                mutex_list: Vec::new(),
+                semaphore_list: Vec::new(),
+                condvar_list: Vec::new(),
+                deadlock_detector: DeadlockDetector::default(),
+                // interval timers are not inherited
+                itimers: [ITimer::default(); 3],
+            }),
+        });
+        // add child
+        parent_inner.children.push(Arc::clone(&child));
+        drop(parent_inner);
+        // ---- release parent PCB
+        // the main thread of child reuses the user stack and trap context copied from parent
+        let task = Arc::new(TaskControlBlock::new(Arc::clone(&child), false));
+        // modify kernel_sp in trap_cx
+        task.inner_exclusive_access().get_trap_cx().kernel_sp = task.kstack.get_top();
+        // a signal handler that forks returns through the same signal frames in the child,
+        // the alternate signal stack is inherited as well
+        let (signal_frames, signal_stack) = {
+            let current_inner = current.inner_exclusive_access();
+            (
+                current_inner.signal_frames.clone(),
+                current_inner.signal_stack,
+            )
+        };
+        let mut task_inner = task.inner_exclusive_access();
+        task_inner.signal_frames = signal_frames;
+        task_inner.signal_stack = signal_stack;
+        drop(task_inner);
+        child
+            .inner_exclusive_access()
+            .tasks
+            .push(Some(Arc::clone(&task)));
+        insert_into_pid2process(child.getpid(), Arc::clone(&child));
+        add_task(task);
+        Some(child)
+    }
+}
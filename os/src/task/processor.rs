@@ -1,13 +1,20 @@
-//! 处理器的实现
+//! 处理器的实现, 每个 hart 有自己的 [`Processor`]
 
-use crate::sync::UPSafeCell;
-use crate::task::manager::fetch_task;
+use crate::config::MAX_HARTS;
+use crate::fs::poll_stdin;
+use crate::hart::hart_id;
+use crate::sync::SpinLock;
+use crate::task::manager::{add_task, fetch_task};
 use crate::task::switch::__switch;
 use crate::task::task::{TaskControlBlock, TaskStatus};
-use crate::task::TaskContext;
+use crate::task::{check_real_itimers, ProcessControlBlock, TaskContext};
+use crate::timer::{check_timer, set_next_trigger};
 use crate::trap::TrapContext;
 use alloc::sync::Arc;
+use alloc::vec::Vec;
 use lazy_static::lazy_static;
+use riscv::asm::wfi;
+use riscv::register::sip;
 
 ///Processor management structure
 pub struct Processor {
@@ -15,6 +22,20 @@ pub struct Processor {
     current: Option<Arc<TaskControlBlock>>,
     ///The basic control flow of each core, helping to select and switch process
     idle_task_cx: TaskContext,
+    ///Timer ticks spent waiting for a task in [`run_tasks`]
+    idle_ticks: usize,
+    ///Timer ticks that interrupted a task in user mode
+    busy_ticks: usize,
+}
+
+/// 所有 hart 的空闲和忙碌时钟中断次数, 用于`sys_cpu_ticks`
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+pub struct CpuTicks {
+    /// 没有任务可以运行时经过的时钟中断次数
+    pub idle: usize,
+    /// 运行任务时经过的时钟中断次数
+    pub busy: usize,
 }
 
 impl Processor {
@@ -23,6 +44,8 @@ impl Processor {
         Self {
             current: None,
             idle_task_cx: TaskContext::zero_init(),
+            idle_ticks: 0,
+            busy_ticks: 0,
         }
     }
     ///Get mutable reference to `idle_task_cx`
@@ -40,22 +63,60 @@ impl Processor {
 }
 
 lazy_static! {
-    pub static ref PROCESSOR: UPSafeCell<Processor> = unsafe { UPSafeCell::new(Processor::new()) };
+    /// 每个 hart 一个处理器, 只被对应的 hart 访问
+    static ref PROCESSORS: Vec<SpinLock<Processor>> =
+        (0..MAX_HARTS).map(|_| SpinLock::new(Processor::new())).collect();
+}
+
+/// 当前 hart 的处理器
+fn processor() -> &'static SpinLock<Processor> {
+    &PROCESSORS[hart_id()]
 }
 
 ///Take the current task,leaving a None in its place
 pub fn take_current_task() -> Option<Arc<TaskControlBlock>> {
-    PROCESSOR.exclusive_access().take_current()
+    processor().exclusive_access().take_current()
+}
+///Count a timer tick that interrupted a task running on the current hart
+pub fn account_busy_tick() {
+    processor().exclusive_access().busy_ticks += 1;
+}
+///Sum of the idle and busy ticks of all harts
+pub fn cpu_ticks() -> CpuTicks {
+    let mut ticks = CpuTicks::default();
+    for processor in PROCESSORS.iter() {
+        let processor = processor.exclusive_access();
+        ticks.idle += processor.idle_ticks;
+        ticks.busy += processor.busy_ticks;
+    }
+    ticks
 }
 ///Get running task
 pub fn current_task() -> Option<Arc<TaskControlBlock>> {
-    PROCESSOR.exclusive_access().current()
+    processor().exclusive_access().current()
+}
+///Get the process that the running task belongs to
+pub fn current_process() -> Arc<ProcessControlBlock> {
+    current_task().unwrap().process.upgrade().unwrap()
 }
 ///Get token of the address space of current task
 pub fn current_user_token() -> usize {
-    let task = current_task().unwrap();
-    let token = task.inner_exclusive_access().get_user_token();
-    token
+    current_process().inner_exclusive_access().get_user_token()
+}
+///Populate the pages of `[start, start + len)` in the address space of current task
+///before the kernel accesses them, return `false` if some page is inaccessible
+pub fn current_fault_in(start: usize, len: usize, write: bool) -> bool {
+    current_process()
+        .inner_exclusive_access()
+        .memory_set
+        .fault_in(start, len, write)
+}
+///Populate the pages of a `\0` terminated string in the address space of current task
+pub fn current_fault_in_str(start: usize) -> bool {
+    current_process()
+        .inner_exclusive_access()
+        .memory_set
+        .fault_in_str(start)
 }
 ///Get the mutable reference to trap context of current task
 pub fn current_trap_cx() -> &'static mut TrapContext {
@@ -64,33 +125,86 @@ pub fn current_trap_cx() -> &'static mut TrapContext {
         .inner_exclusive_access()
         .get_trap_cx()
 }
+///Get the virtual address of trap context of current task in user space
+pub fn current_trap_cx_user_va() -> usize {
+    current_task()
+        .unwrap()
+        .inner_exclusive_access()
+        .res
+        .as_ref()
+        .unwrap()
+        .trap_cx_user_va()
+}
 
 ///The main part of process execution and scheduling
 ///Loop `fetch_task` to get the process that needs to run, and switch the process through `__switch`
+///
+///A task that leaves the processor is put back into the ready queue here, after its context
+///has been saved by `__switch`, so that no other hart can run it with a stale context
 pub fn run_tasks() {
     loop {
-        let mut processor = PROCESSOR.exclusive_access();
+        let mut processor = processor().exclusive_access();
         if let Some(task) = fetch_task() {
             let idle_task_cx_ptr = processor.get_idle_task_cx_ptr();
             // access coming task TCB exclusively
             let mut task_inner = task.inner_exclusive_access();
+            if task_inner.task_status != TaskStatus::Ready {
+                // its process exited after it was fetched
+                continue;
+            }
             let next_task_cx_ptr = &task_inner.task_cx as *const TaskContext;
             task_inner.task_status = TaskStatus::Running;
+            task_inner.on_cpu = true;
+            task_inner.times.switch_in();
             drop(task_inner);
             // release coming task TCB manually
-            processor.current = Some(task);
+            processor.current = Some(Arc::clone(&task));
             // release processor manually
             drop(processor);
             unsafe {
                 __switch(idle_task_cx_ptr, next_task_cx_ptr);
             }
+            // the task has left the processor, it is ready again if it yielded,
+            // or if it was woken up before its context was saved
+            let mut task_inner = task.inner_exclusive_access();
+            task_inner.on_cpu = false;
+            task_inner.times.leave_kernel();
+            task_inner.sched.switch_out();
+            let ready = task_inner.task_status == TaskStatus::Ready;
+            drop(task_inner);
+            if ready {
+                add_task(task);
+            }
+        } else {
+            // every task is blocked, waiting for the console or a timer
+            drop(processor);
+            idle();
         }
     }
 }
 
+///Wait for the next timer tick when there is no task to run
+///
+///The kernel runs with `sstatus.SIE` cleared and has no handler for traps from kernel mode,
+///but `wfi` still wakes up once an interrupt enabled in `sie` is pending. The pending timer
+///interrupt is handled here instead of in a trap handler
+fn idle() {
+    unsafe {
+        wfi();
+    }
+    if sip::read().stimer() {
+        // clears the pending timer interrupt
+        set_next_trigger();
+        processor().exclusive_access().idle_ticks += 1;
+        check_timer();
+        check_real_itimers();
+        poll_stdin();
+    }
+}
+
 ///Return to idle control flow for new scheduling
 pub fn schedule(switched_task_cx_ptr: *mut TaskContext) {
-    let mut processor = PROCESSOR.exclusive_access();
+    let mut processor = processor().exclusive_access();
     let idle_task_cx_ptr = processor.get_idle_task_cx_ptr();
     drop(processor);
     unsafe {
@@ -0,0 +1,111 @@
+//! 类似 CFS 的完全公平调度: 每个线程按 nice 值对应的权重累计虚拟运行时间(vruntime),
+//! 每次选择 vruntime 最小的线程运行. 权重越大 vruntime 增长得越慢, 得到的处理器时间越多
+
+use super::{Scheduler, MIN_NICE};
+use crate::config::CLOCK_FREQ;
+use crate::task::TaskControlBlock;
+use crate::timer::get_time;
+use alloc::collections::BTreeMap;
+use alloc::sync::Arc;
+
+/// nice 值为 0 的权重
+const NICE_0_WEIGHT: u64 = 1024;
+/// nice 值 -20 到 19 对应的权重, 相邻两级相差约 1.25 倍, 和 Linux 相同
+const NICE_TO_WEIGHT: [u64; 40] = [
+    88761, 71755, 56483, 46273, 36291, 29154, 23254, 18705, 14949, 11916, 9548, 7620, 6100, 4904,
+    3906, 3121, 2501, 1991, 1586, 1277, 1024, 820, 655, 526, 423, 335, 272, 215, 172, 137, 110, 87,
+    70, 56, 45, 36, 29, 23, 18, 15,
+];
+/// 调度周期(时钟周期数), 阻塞后被唤醒的线程最多获得半个周期的补偿
+const SCHED_LATENCY: u64 = (CLOCK_FREQ / 50) as u64;
+/// 正在运行的线程的 vruntime 超过最小的 vruntime 这么多时才被抢占, 避免频繁切换
+const MIN_GRANULARITY: u64 = (CLOCK_FREQ / 250) as u64;
+
+/// 线程的完全公平调度状态
+#[derive(Default)]
+pub struct SchedEntity {
+    nice: i32,
+    vruntime: u64,
+    /// 上次记账的时间
+    exec_start: usize,
+}
+
+impl SchedEntity {
+    /// 完全公平调度使用 nice 值, 忽略
+    pub fn set_priority(&mut self, _prio: usize) {}
+    /// 设置 nice 值, 调用者保证它在`MIN_NICE..=MAX_NICE`中
+    pub fn set_nice(&mut self, nice: i32) {
+        self.nice = nice;
+    }
+    /// 线程离开处理器(让出, 阻塞或者被抢占), 把最后一段运行时间计入 vruntime
+    pub fn switch_out(&mut self) {
+        self.update_vruntime();
+    }
+    fn weight(&self) -> u64 {
+        NICE_TO_WEIGHT[(self.nice - MIN_NICE) as usize]
+    }
+    /// 把上次记账以来的运行时间按权重计入 vruntime
+    fn update_vruntime(&mut self) {
+        let now = get_time();
+        let delta = (now - self.exec_start) as u64;
+        self.exec_start = now;
+        self.vruntime += delta * NICE_0_WEIGHT / self.weight();
+    }
+}
+
+/// 完全公平调度器, 就绪队列按 vruntime 排序
+pub struct CfsScheduler {
+    /// 键中的序号用来区分 vruntime 相同的线程
+    ready_queue: BTreeMap<(u64, usize), Arc<TaskControlBlock>>,
+    seq: usize,
+    /// 单调增长, 新加入的线程的 vruntime 从它附近开始
+    min_vruntime: u64,
+}
+
+impl CfsScheduler {
+    /// 创建空的调度器
+    pub fn new() -> Self {
+        Self {
+            ready_queue: BTreeMap::new(),
+            seq: 0,
+            min_vruntime: 0,
+        }
+    }
+}
+
+impl Scheduler for CfsScheduler {
+    fn push(&mut self, task: Arc<TaskControlBlock>) {
+        let mut inner = task.inner_exclusive_access();
+        // 新线程和阻塞了很久的线程不能靠很小的 vruntime 长期独占处理器,
+        // 但是得到一点补偿, 交互式的线程被唤醒后能很快运行
+        let vruntime = inner
+            .sched
+            .vruntime
+            .max(self.min_vruntime.saturating_sub(SCHED_LATENCY / 2));
+        inner.sched.vruntime = vruntime;
+        drop(inner);
+        self.seq += 1;
+        self.ready_queue.insert((vruntime, self.seq), task);
+    }
+    fn pop(&mut self) -> Option<Arc<TaskControlBlock>> {
+        let key = *self.ready_queue.keys().next()?;
+        let task = self.ready_queue.remove(&key).unwrap();
+        let (vruntime, _) = key;
+        self.min_vruntime = self.min_vruntime.max(vruntime);
+        task.inner_exclusive_access().sched.exec_start = get_time();
+        Some(task)
+    }
+    fn remove(&mut self, task: &Arc<TaskControlBlock>) {
+        self.ready_queue.retain(|_, t| !Arc::ptr_eq(t, task));
+    }
+    fn tick(&mut self, task: &Arc<TaskControlBlock>) -> bool {
+        let mut inner = task.inner_exclusive_access();
+        inner.sched.update_vruntime();
+        let vruntime = inner.sched.vruntime;
+        drop(inner);
+        match self.ready_queue.keys().next() {
+            Some(&(leftmost, _)) => vruntime > leftmost + MIN_GRANULARITY,
+            None => false,
+        }
+    }
+}
@@ -0,0 +1,46 @@
+//! 先来先服务的时间片轮转调度
+
+use super::Scheduler;
+use crate::task::TaskControlBlock;
+use alloc::collections::VecDeque;
+use alloc::sync::Arc;
+
+/// FIFO 调度不需要线程的调度状态
+#[derive(Default)]
+pub struct SchedEntity;
+
+impl SchedEntity {
+    /// FIFO 调度没有优先级, 忽略
+    pub fn set_priority(&mut self, _prio: usize) {}
+    /// FIFO 调度不使用 nice 值, 忽略
+    pub fn set_nice(&mut self, _nice: i32) {}
+    /// FIFO 调度不统计运行时间, 忽略
+    pub fn switch_out(&mut self) {}
+}
+
+/// 每次时钟中断都切换到就绪队列中的下一个线程
+pub struct FifoScheduler {
+    // 双端队列,方便实现fifo
+    ready_queue: VecDeque<Arc<TaskControlBlock>>,
+}
+
+impl FifoScheduler {
+    /// 创建空的调度器
+    pub fn new() -> Self {
+        Self {
+            ready_queue: VecDeque::new(),
+        }
+    }
+}
+
+impl Scheduler for FifoScheduler {
+    fn push(&mut self, task: Arc<TaskControlBlock>) {
+        self.ready_queue.push_back(task);
+    }
+    fn pop(&mut self) -> Option<Arc<TaskControlBlock>> {
+        self.ready_queue.pop_front()
+    }
+    fn remove(&mut self, task: &Arc<TaskControlBlock>) {
+        self.ready_queue.retain(|t| !Arc::ptr_eq(t, task));
+    }
+}
@@ -0,0 +1,109 @@
+//! 多级反馈队列: 新线程从最高级开始, 用完本级的时间片后降到下一级,
+//! 级别越低时间片越长. 每隔`BOOST_TICKS`个时钟中断所有线程回到最高级, 避免饥饿
+
+use super::Scheduler;
+use crate::task::TaskControlBlock;
+use alloc::collections::VecDeque;
+use alloc::sync::Arc;
+
+/// 队列的级数
+const LEVELS: usize = 4;
+/// 提升所有线程的间隔, 单位是时钟中断
+const BOOST_TICKS: usize = 100;
+
+/// 第`level`级的时间片, 单位是时钟中断
+fn quantum(level: usize) -> usize {
+    1 << level
+}
+
+/// 线程的多级反馈队列调度状态
+#[derive(Default)]
+pub struct SchedEntity {
+    level: usize,
+    /// 在当前级别已经用掉的时间片, 主动让出处理器不会清零, 防止线程靠频繁让出留在高级别
+    used_ticks: usize,
+    /// 线程最近一次被提升时的 [`MlfqScheduler::epoch`]
+    epoch: usize,
+}
+
+impl SchedEntity {
+    /// 多级反馈队列按运行情况调整优先级, 忽略
+    pub fn set_priority(&mut self, _prio: usize) {}
+    /// 多级反馈队列不使用 nice 值, 忽略
+    pub fn set_nice(&mut self, _nice: i32) {}
+    /// 多级反馈队列按时钟中断统计运行时间, 忽略
+    pub fn switch_out(&mut self) {}
+    fn boost(&mut self, epoch: usize) {
+        self.level = 0;
+        self.used_ticks = 0;
+        self.epoch = epoch;
+    }
+}
+
+/// 多级反馈队列调度器
+pub struct MlfqScheduler {
+    queues: [VecDeque<Arc<TaskControlBlock>>; LEVELS],
+    ticks: usize,
+    /// 提升的次数. 提升时阻塞的线程不在队列中, 重新加入时根据它补上提升
+    epoch: usize,
+}
+
+impl MlfqScheduler {
+    /// 创建空的调度器
+    pub fn new() -> Self {
+        Self {
+            queues: Default::default(),
+            ticks: 0,
+            epoch: 0,
+        }
+    }
+    /// 所有线程回到最高级
+    fn boost(&mut self) {
+        self.epoch += 1;
+        for level in 1..LEVELS {
+            while let Some(task) = self.queues[level].pop_front() {
+                task.inner_exclusive_access().sched.boost(self.epoch);
+                self.queues[0].push_back(task);
+            }
+        }
+    }
+}
+
+impl Scheduler for MlfqScheduler {
+    fn push(&mut self, task: Arc<TaskControlBlock>) {
+        let mut inner = task.inner_exclusive_access();
+        if inner.sched.epoch != self.epoch {
+            inner.sched.boost(self.epoch);
+        }
+        let level = inner.sched.level;
+        drop(inner);
+        self.queues[level].push_back(task);
+    }
+    fn pop(&mut self) -> Option<Arc<TaskControlBlock>> {
+        self.queues.iter_mut().find_map(|queue| queue.pop_front())
+    }
+    fn remove(&mut self, task: &Arc<TaskControlBlock>) {
+        for queue in self.queues.iter_mut() {
+            queue.retain(|t| !Arc::ptr_eq(t, task));
+        }
+    }
+    fn tick(&mut self, task: &Arc<TaskControlBlock>) -> bool {
+        self.ticks += 1;
+        if self.ticks % BOOST_TICKS == 0 {
+            self.boost();
+            task.inner_exclusive_access().sched.boost(self.epoch);
+            return true;
+        }
+        let mut inner = task.inner_exclusive_access();
+        let sched = &mut inner.sched;
+        sched.used_ticks += 1;
+        if sched.used_ticks >= quantum(sched.level) {
+            sched.level = (sched.level + 1).min(LEVELS - 1);
+            sched.used_ticks = 0;
+            return true;
+        }
+        // 有更高级别的线程就绪时立即让给它
+        let level = sched.level;
+        self.queues[..level].iter().any(|queue| !queue.is_empty())
+    }
+}
@@ -0,0 +1,66 @@
+//! 可替换的调度策略
+//!
+//! [`TaskManager`](super::manager::TaskManager) 只负责保存就绪的线程, 选择下一个运行的线程交给
+//! 实现了 [`Scheduler`] 的调度器. 编译时通过 cargo feature 选择调度器:
+//!
+//! - `sched_fifo`: 先来先服务的时间片轮转, 默认
+//! - `sched_stride`: stride 调度, 按`sys_set_priority`设置的优先级分配处理器时间
+//! - `sched_mlfq`: 多级反馈队列, 用完时间片的线程降级, 定期提升回最高级
+//! - `sched_cfs`: 完全公平调度, 按`sys_setpriority`设置的 nice 值分配处理器时间
+
+use super::TaskControlBlock;
+use alloc::sync::Arc;
+
+#[cfg(feature = "sched_cfs")]
+mod cfs;
+#[cfg(not(any(
+    feature = "sched_stride",
+    feature = "sched_mlfq",
+    feature = "sched_cfs"
+)))]
+mod fifo;
+#[cfg(feature = "sched_mlfq")]
+mod mlfq;
+#[cfg(feature = "sched_stride")]
+mod stride;
+
+#[cfg(any(
+    all(feature = "sched_stride", feature = "sched_mlfq"),
+    all(feature = "sched_stride", feature = "sched_cfs"),
+    all(feature = "sched_mlfq", feature = "sched_cfs"),
+))]
+compile_error!("only one scheduler feature can be enabled");
+
+#[cfg(feature = "sched_cfs")]
+pub use cfs::{CfsScheduler as DefaultScheduler, SchedEntity};
+#[cfg(not(any(
+    feature = "sched_stride",
+    feature = "sched_mlfq",
+    feature = "sched_cfs"
+)))]
+pub use fifo::{FifoScheduler as DefaultScheduler, SchedEntity};
+#[cfg(feature = "sched_mlfq")]
+pub use mlfq::{MlfqScheduler as DefaultScheduler, SchedEntity};
+#[cfg(feature = "sched_stride")]
+pub use stride::{SchedEntity, StrideScheduler as DefaultScheduler};
+
+/// 最小的 nice 值, 优先级最高
+pub const MIN_NICE: i32 = -20;
+/// 最大的 nice 值, 优先级最低
+pub const MAX_NICE: i32 = 19;
+
+/// 调度器, 管理就绪的线程并决定下一个运行的线程.
+///
+/// 每个线程的调度状态保存在它的 [`SchedEntity`] 中, 调度器在加入和取出线程时读写它
+pub trait Scheduler {
+    /// 把就绪的`task`加入调度器
+    fn push(&mut self, task: Arc<TaskControlBlock>);
+    /// 取出下一个运行的线程, 没有就绪的线程时返回`None`
+    fn pop(&mut self) -> Option<Arc<TaskControlBlock>>;
+    /// 把`task`从调度器中移除
+    fn remove(&mut self, task: &Arc<TaskControlBlock>);
+    /// 时钟中断时对正在运行的`task`调用, 返回是否要抢占它
+    fn tick(&mut self, _task: &Arc<TaskControlBlock>) -> bool {
+        true
+    }
+}
@@ -0,0 +1,81 @@
+//! stride 调度: 每个线程有一个 pass 值, 每次选择 pass 最小的线程运行,
+//! 运行后 pass 增加`BIG_STRIDE / priority`, 线程得到的处理器时间和优先级成正比
+
+use super::Scheduler;
+use crate::task::TaskControlBlock;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+
+/// 各个优先级的 stride 都由它除以优先级得到
+const BIG_STRIDE: u64 = 1 << 20;
+/// 新线程的默认优先级
+const DEFAULT_PRIORITY: usize = 16;
+
+/// 线程的 stride 调度状态
+pub struct SchedEntity {
+    priority: usize,
+    pass: u64,
+}
+
+impl Default for SchedEntity {
+    fn default() -> Self {
+        Self {
+            priority: DEFAULT_PRIORITY,
+            pass: 0,
+        }
+    }
+}
+
+impl SchedEntity {
+    /// 设置优先级, 调用者保证`prio >= 2`
+    pub fn set_priority(&mut self, prio: usize) {
+        self.priority = prio;
+    }
+    /// stride 调度不使用 nice 值, 忽略
+    pub fn set_nice(&mut self, _nice: i32) {}
+    /// stride 调度在被选中时更新 pass, 忽略
+    pub fn switch_out(&mut self) {}
+}
+
+/// stride 调度器
+pub struct StrideScheduler {
+    ready_queue: Vec<Arc<TaskControlBlock>>,
+    /// 最近一次被选中的线程的 pass.
+    /// 新加入的线程的 pass 至少是它, 刚创建或者阻塞了很久的线程不会因此长期独占处理器
+    min_pass: u64,
+}
+
+impl StrideScheduler {
+    /// 创建空的调度器
+    pub fn new() -> Self {
+        Self {
+            ready_queue: Vec::new(),
+            min_pass: 0,
+        }
+    }
+}
+
+impl Scheduler for StrideScheduler {
+    fn push(&mut self, task: Arc<TaskControlBlock>) {
+        let mut inner = task.inner_exclusive_access();
+        inner.sched.pass = inner.sched.pass.max(self.min_pass);
+        drop(inner);
+        self.ready_queue.push(task);
+    }
+    fn pop(&mut self) -> Option<Arc<TaskControlBlock>> {
+        let (idx, _) = self
+            .ready_queue
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, task)| task.inner_exclusive_access().sched.pass)?;
+        let task = self.ready_queue.swap_remove(idx);
+        let mut inner = task.inner_exclusive_access();
+        self.min_pass = inner.sched.pass;
+        inner.sched.pass += BIG_STRIDE / inner.sched.priority as u64;
+        drop(inner);
+        Some(task)
+    }
+    fn remove(&mut self, task: &Arc<TaskControlBlock>) {
+        self.ready_queue.retain(|t| !Arc::ptr_eq(t, task));
+    }
+}
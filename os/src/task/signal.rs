@@ -1,12 +1,41 @@
-//! 信号枚举
+//! 信号枚举, 以及处理信号时压入用户栈的信号帧
+//!
+//! 编号小于`SIGRTMIN`的普通信号用位图记录, 同一个信号多次产生只处理一次.
+//! 实时信号`SIGRTMIN..=SIGRTMAX`按照产生的顺序排队, 每一次都会被处理
 use bitflags::*;
+use core::arch::global_asm;
+
+global_asm!(include_str!("sigreturn.S"));
 
 /// 最大信号数量
-pub const MAX_SIG: usize = 31;
+pub const MAX_SIG: usize = 63;
+/// 第一个实时信号
+pub const SIGRTMIN: usize = 32;
+/// 最后一个实时信号
+pub const SIGRTMAX: usize = MAX_SIG;
+/// 每个进程最多排队的实时信号数量
+pub const SIGQUEUE_MAX: usize = 32;
+/// 非法内存访问
+pub const SIGSEGV: usize = 11;
+
+/// `si_code`: 由`kill`发送
+pub const SI_USER: i32 = 0;
+/// `si_code`: 由内核发送
+pub const SI_KERNEL: i32 = 0x80;
+/// `si_code`: 由`sigqueue`发送
+pub const SI_QUEUE: i32 = -1;
+/// `si_code`: 由间隔定时器发送
+pub const SI_TIMER: i32 = -2;
+/// `si_code`: SIGILL, 非法的指令
+pub const ILL_ILLOPC: i32 = 1;
+/// `si_code`: SIGSEGV, 地址没有映射或者没有权限
+pub const SEGV_MAPERR: i32 = 1;
+/// `si_code`: SIGCHLD, 子进程退出
+pub const CLD_EXITED: i32 = 1;
 
 bitflags! {
     /// 信号枚举
-    pub struct SignalFlags: u32 {
+    pub struct SignalFlags: u64 {
         /// Default signal handling
         const SIGDEF = 1;
         /// 挂起
@@ -71,27 +100,138 @@ bitflags! {
         const SIGPWR = 1 << 30;
         /// 系统调用异常
         const SIGSYS = 1 << 31;
+        /// 实时信号`SIGRTMIN..=SIGRTMAX`, 默认终止进程
+        const SIGRT = !0xffff_ffff;
     }
 }
 
+/// 没有注册信号处理函数时信号的默认动作
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignalDefault {
+    /// 终止进程
+    Terminate,
+    /// 忽略信号
+    Ignore,
+    /// 暂停进程
+    Stop,
+    /// 继续运行被暂停的进程
+    Continue,
+    /// 终止进程并产生 core dump, 内核只在 wait status 中记录
+    Core,
+}
+
+/// 默认动作为暂停进程的信号
+pub const STOP_SIGNALS: SignalFlags = SignalFlags::from_bits_truncate(
+    SignalFlags::SIGSTOP.bits()
+        | SignalFlags::SIGTSTP.bits()
+        | SignalFlags::SIGTTIN.bits()
+        | SignalFlags::SIGTTOU.bits(),
+);
+
 impl SignalFlags {
-    /// 信号检查
-    pub fn check_error(&self) -> Option<(i32, &'static str)> {
-        if self.contains(Self::SIGINT) {
-            Some((-2, "Killed, SIGINT=2"))
-        } else if self.contains(Self::SIGILL) {
-            Some((-4, "Illegal Instruction, SIGILL=4"))
-        } else if self.contains(Self::SIGABRT) {
-            Some((-6, "Aborted, SIGABRT=6"))
-        } else if self.contains(Self::SIGFPE) {
-            Some((-8, "Erroneous Arithmetic Operation, SIGFPE=8"))
-        } else if self.contains(Self::SIGKILL) {
-            Some((-9, "Killed, SIGKILL=9"))
-        } else if self.contains(Self::SIGSEGV) {
-            Some((-11, "Segmentation Fault, SIGSEGV=11"))
-        } else {
-            //println!("[K] signalflags check_error  {:?}", self);
-            None
+    /// 信号的默认动作, 与 POSIX 相同. `SIGDEF`没有对应的 POSIX 信号, 默认终止进程
+    pub fn default_action(&self) -> SignalDefault {
+        match *self {
+            Self::SIGQUIT
+            | Self::SIGILL
+            | Self::SIGTRAP
+            | Self::SIGABRT
+            | Self::SIGBUS
+            | Self::SIGFPE
+            | Self::SIGSEGV
+            | Self::SIGXCPU
+            | Self::SIGXFSZ
+            | Self::SIGSYS => SignalDefault::Core,
+            Self::SIGCHLD | Self::SIGURG | Self::SIGWINCH => SignalDefault::Ignore,
+            Self::SIGSTOP | Self::SIGTSTP | Self::SIGTTIN | Self::SIGTTOU => SignalDefault::Stop,
+            Self::SIGCONT => SignalDefault::Continue,
+            _ => SignalDefault::Terminate,
         }
     }
 }
+
+/// `SignalStack::flags`: 正在备用信号栈上执行
+pub const SS_ONSTACK: i32 = 1;
+/// `SignalStack::flags`: 不使用备用信号栈
+pub const SS_DISABLE: i32 = 2;
+/// 备用信号栈的最小大小
+pub const MINSIGSTKSZ: usize = 2048;
+
+/// 备用信号栈, 对应 Linux 的`stack_t`
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct SignalStack {
+    /// 栈的起始地址(低地址)
+    pub sp: usize,
+    /// `SS_*`
+    pub flags: i32,
+    /// 栈的大小
+    pub size: usize,
+}
+
+impl Default for SignalStack {
+    fn default() -> Self {
+        Self {
+            sp: 0,
+            flags: SS_DISABLE,
+            size: 0,
+        }
+    }
+}
+
+impl SignalStack {
+    /// 用户栈指针`sp`是否在这个栈上
+    pub fn contains(&self, sp: usize) -> bool {
+        self.flags != SS_DISABLE && sp > self.sp && sp - self.sp <= self.size
+    }
+}
+
+/// 信号的附加信息, 对应 Linux 的`siginfo_t`中常用的字段
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+pub struct SignalInfo {
+    /// 信号编号
+    pub signo: i32,
+    /// 错误码
+    pub errno: i32,
+    /// 信号的来源, `SI_*`或者与信号相关的`*_*`常量
+    pub code: i32,
+    /// 发送信号的进程
+    pub pid: u32,
+    /// 引起 SIGSEGV 和 SIGILL 的地址
+    pub addr: usize,
+    /// `sigqueue`附带的整数或者指针
+    pub value: usize,
+}
+
+impl SignalInfo {
+    /// 来源为`code`的信号`signal`
+    pub fn new(signal: SignalFlags, code: i32) -> Self {
+        Self {
+            signo: signal.bits().trailing_zeros() as i32,
+            code,
+            ..Self::default()
+        }
+    }
+}
+
+/// 被信号打断时的用户上下文, 对应 Linux 的`ucontext_t`
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct UContext {
+    /// 被打断时的信号掩码, sigreturn 时恢复
+    pub sigmask: SignalFlags,
+    /// 被打断时的寄存器, 与 Linux 的`struct sigcontext`相同:
+    /// `gregs[0]`是 pc, `gregs[i]`是`x[i]`
+    pub gregs: [usize; 32],
+}
+
+/// 处理信号时压入用户栈的信号帧, 信号处理函数的第二, 三个参数分别指向`info`和`ucontext`
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct SignalFrame {
+    /// 信号的附加信息
+    pub info: SignalInfo,
+    /// 被打断时的上下文
+    pub ucontext: UContext,
+}
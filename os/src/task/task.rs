@@ -1,18 +1,13 @@
 //! Types related to task management
 
-use super::TaskContext;
-use crate::config::TRAP_CONTEXT;
-use crate::fs::{File, Stdin, Stdout};
-use crate::mm::{translated_refmut, MemorySet, PhysPageNum, VirtAddr, KERNEL_SPACE};
-use crate::sync::UPSafeCell;
-use crate::task::pid::{pid_alloc, KernelStack, PidHandle};
-use crate::task::{SignalActions, SignalFlags};
-use crate::trap::{trap_handler, TrapContext};
-use alloc::string::String;
+use super::id::{kstack_alloc, KernelStack, TaskUserRes};
+use super::scheduler::SchedEntity;
+use super::{ProcessControlBlock, SignalStack, TaskContext, TaskTimes};
+use crate::mm::PhysPageNum;
+use crate::sync::{SpinLock, SpinLockGuard};
+use crate::trap::TrapContext;
 use alloc::sync::{Arc, Weak};
-use alloc::vec;
 use alloc::vec::Vec;
-use core::cell::RefMut;
 
 /// 任务状态
 #[derive(Copy, Clone, PartialEq)]
@@ -23,253 +18,83 @@ pub enum TaskStatus {
     Running,
     /// 僵尸状态,半死不活
     Zombie,
+    /// 阻塞, 等待被唤醒, 不在就绪队列中
+    Blocked,
 }
 
-/// 任务控制 struct
+/// 线程控制块, 线程是调度的单位, 地址空间等资源属于所在的进程
 pub struct TaskControlBlock {
     // immutable
-    pub pid: PidHandle,
-    pub kernel_stack: KernelStack,
+    /// 所属进程
+    pub process: Weak<ProcessControlBlock>,
+    /// 内核栈
+    pub kstack: KernelStack,
     // mutable
-    inner: UPSafeCell<TaskControlBlockInner>,
+    inner: SpinLock<TaskControlBlockInner>,
 }
 
-/// 任务控制 struct
+/// 线程控制块中可变的部分
 pub struct TaskControlBlockInner {
+    /// tid, 用户栈和 Trap 上下文页, 进程退出时被回收
+    pub res: Option<TaskUserRes>,
     /// Trap 上下文被实际存放在物理页帧的物理页号
     pub trap_cx_ppn: PhysPageNum,
-    /// 应用数据的大小
-    pub base_size: usize,
     /// 任务的上下文
     pub task_cx: TaskContext,
     /// 任务运行状态
     pub task_status: TaskStatus,
-    /// 应用的地址空间
-    pub memory_set: MemorySet,
-    /// 父进程(weak引用,不影响父进程的回收)
-    pub parent: Option<Weak<TaskControlBlock>>,
-    /// 子进程列表
-    pub children: Vec<Arc<TaskControlBlock>>,
-    /// 退出code
-    pub exit_code: i32,
-    /// 文件描述符表
-    pub fd_table: Vec<Option<Arc<dyn File + Send + Sync>>>,
-    /// 要响应的信号
-    pub signals: SignalFlags,
-    /// 要屏蔽的信号
-    pub signal_mask: SignalFlags,
-    /// 正在处理的信号
-    pub handling_sig: isize,
-    /// 信号处理例程表
-    pub signal_actions: SignalActions,
-    /// 任务是否已经被杀死了
-    pub killed: bool,
-    /// 任务是否已经被暂停了
-    pub frozen: bool,
-    /// 被打断的trap上下文
-    pub trap_ctx_backup: Vec<Option<TrapContext>>,
+    /// 正在某个 hart 上运行, 或者刚离开而上下文还没有保存完, 这时它不能被加入就绪队列
+    pub on_cpu: bool,
+    /// 线程的退出码, 退出前为`None`
+    pub exit_code: Option<i32>,
+    /// 调度器使用的调度状态
+    pub sched: SchedEntity,
+    /// 在用户态和内核态运行的时间
+    pub times: TaskTimes,
+    /// 正在执行的信号处理函数的信号帧在用户栈上的地址, 最后一个是最内层的
+    pub signal_frames: Vec<usize>,
+    /// `sigaltstack`设置的备用信号栈
+    pub signal_stack: SignalStack,
 }
 
-/// implement
 impl TaskControlBlockInner {
+    /// Trap 上下文
     pub fn get_trap_cx(&self) -> &'static mut TrapContext {
         self.trap_cx_ppn.get_mut()
     }
-    pub fn get_user_token(&self) -> usize {
-        self.memory_set.token()
-    }
-    fn get_status(&self) -> TaskStatus {
-        self.task_status
-    }
-    pub fn is_zombie(&self) -> bool {
-        self.get_status() == TaskStatus::Zombie
-    }
-    pub fn alloc_fd(&mut self) -> usize {
-        if let Some(fd) = (0..self.fd_table.len()).find(|fd| self.fd_table[*fd].is_none()) {
-            fd
-        } else {
-            self.fd_table.push(None);
-            self.fd_table.len() - 1
-        }
-    }
 }
 
 impl TaskControlBlock {
     /// 获取inner
-    pub fn inner_exclusive_access(&self) -> RefMut<'_, TaskControlBlockInner> {
+    pub fn inner_exclusive_access(&self) -> SpinLockGuard<'_, TaskControlBlockInner> {
         self.inner.exclusive_access()
     }
-    /// 获取pid
-    pub fn getpid(&self) -> usize {
-        self.pid.0
-    }
-    /// 创建新的新闻.
-    pub fn new(elf_data: &[u8]) -> Self {
-        // 解析传入的 ELF 格式数据构造应用的地址空间
-        let (memory_set, user_sp, entry_point) = MemorySet::from_elf(elf_data);
-        // 获取trap context 的物理页帧
-        let trap_cx_ppn = memory_set
-            .translate(VirtAddr::from(TRAP_CONTEXT).into())
-            .unwrap()
-            .ppn();
-        // 为该进程分配 PID 以及内核栈，并记录下内核栈在内核地址空间的位置 kernel_stack_top
-        let pid_handle = pid_alloc();
-        let kernel_stack = KernelStack::new(&pid_handle);
-        let kernel_stack_top = kernel_stack.get_top();
-        // push a task context which goes to trap_return to the top of kernel stack
-        let task_control_block = Self {
-            pid: pid_handle,
-            kernel_stack,
-            inner: unsafe {
-                UPSafeCell::new(TaskControlBlockInner {
-                    trap_cx_ppn,
-                    base_size: user_sp,
-                    task_cx: TaskContext::goto_trap_return(kernel_stack_top),
-                    task_status: TaskStatus::Ready,
-                    memory_set,
-                    parent: None,
-                    children: Vec::new(),
-                    exit_code: 0,
-                    fd_table: vec![
-                        // 0 -> stdin
-                        Some(Arc::new(Stdin)),
-                        // 1 -> stdout
-                        Some(Arc::new(Stdout)),
-                        // 2 -> stderr
-                        Some(Arc::new(Stdout)),
-                    ],
-                    signals: SignalFlags::empty(),
-                    signal_mask: SignalFlags::empty(),
-                    handling_sig: -1,
-                    signal_actions: SignalActions::default(),
-                    killed: false,
-                    frozen: false,
-                    trap_ctx_backup: Vec::new(),
-                })
-            },
-        };
-        // prepare TrapContext in user space
-        let trap_cx = task_control_block.inner_exclusive_access().get_trap_cx();
-        *trap_cx = TrapContext::app_init_context(
-            entry_point,
-            user_sp,
-            KERNEL_SPACE.exclusive_access().token(),
-            kernel_stack_top,
-            trap_handler as usize,
-        );
-        task_control_block
-    }
-    /// implementation of `exec`
-    pub fn exec(&self, elf_data: &[u8], args: Vec<String>) {
-        // memory_set with elf program headers/trampoline/trap context/user stack
-        let (memory_set, mut user_sp, entry_point) = MemorySet::from_elf(elf_data);
-        let trap_cx_ppn = memory_set
-            .translate(VirtAddr::from(TRAP_CONTEXT).into())
-            .unwrap()
-            .ppn();
-
-        // push arguments on user stack
-        user_sp -= (args.len() + 1) * core::mem::size_of::<usize>();
-        let argv_base = user_sp;
-        let mut argv: Vec<_> = (0..=args.len())
-            .map(|arg| {
-                translated_refmut(
-                    memory_set.token(),
-                    (argv_base + arg * core::mem::size_of::<usize>()) as *mut usize,
-                )
-            })
-            .collect();
-        *argv[args.len()] = 0;
-        for i in 0..args.len() {
-            user_sp -= args[i].len() + 1;
-            *argv[i] = user_sp;
-            let mut p = user_sp;
-            for c in args[i].as_bytes() {
-                *translated_refmut(memory_set.token(), p as *mut u8) = *c;
-                p += 1;
-            }
-            *translated_refmut(memory_set.token(), p as *mut u8) = 0;
+    /// 在`process`中创建新的线程, 分配 tid 和内核栈.
+    /// `alloc_user_res`为`false`时沿用地址空间中已有的用户栈和 Trap 上下文(fork)
+    pub fn new(process: Arc<ProcessControlBlock>, alloc_user_res: bool) -> Self {
+        let res = TaskUserRes::new(Arc::clone(&process), alloc_user_res);
+        let trap_cx_ppn = res.trap_cx_ppn();
+        let kstack = kstack_alloc();
+        let kstack_top = kstack.get_top();
+        Self {
+            process: Arc::downgrade(&process),
+            kstack,
+            inner: SpinLock::new(TaskControlBlockInner {
+                res: Some(res),
+                trap_cx_ppn,
+                task_cx: TaskContext::goto_trap_return(kstack_top),
+                task_status: TaskStatus::Ready,
+                on_cpu: false,
+                exit_code: None,
+                sched: SchedEntity::default(),
+                times: TaskTimes::default(),
+                signal_frames: Vec::new(),
+                signal_stack: SignalStack::default(),
+            }),
         }
-        // make the user_sp aligned to 8B for k210 platform
-        user_sp -= user_sp % core::mem::size_of::<usize>();
-
-        // **** access current TCB exclusively
-        let mut inner = self.inner_exclusive_access();
-        // substitute memory_set
-        inner.memory_set = memory_set;
-        // update trap_cx ppn
-        inner.trap_cx_ppn = trap_cx_ppn;
-        // initialize trap_cx
-        let mut trap_cx = TrapContext::app_init_context(
-            entry_point,
-            user_sp,
-            KERNEL_SPACE.exclusive_access().token(),
-            self.kernel_stack.get_top(),
-            trap_handler as usize,
-        );
-        // 这两个参数在第一次进入对应应用的用户态的时候会被接收并用于还原命令行参数
-        trap_cx.x[10] = args.len();
-        trap_cx.x[11] = argv_base;
-        *inner.get_trap_cx() = trap_cx;
-        // **** release inner automatically
     }
-    /// implementation for `fork`
-    pub fn fork(self: &Arc<Self>) -> Arc<Self> {
-        // ---- hold parent PCB lock
-        let mut parent_inner = self.inner_exclusive_access();
-        // copy user space(include trap context)
-        let memory_set = MemorySet::from_existed_user(&parent_inner.memory_set);
-        let trap_cx_ppn = memory_set
-            .translate(VirtAddr::from(TRAP_CONTEXT).into())
-            .unwrap()
-            .ppn();
-        // alloc a pid and a kernel stack in kernel space
-        let pid_handle = pid_alloc();
-        let kernel_stack = KernelStack::new(&pid_handle);
-        let kernel_stack_top = kernel_stack.get_top();
-        // copy fd table
-        let mut new_fd_table: Vec<Option<Arc<dyn File + Send + Sync>>> = Vec::new();
-        for fd in parent_inner.fd_table.iter() {
-            if let Some(file) = fd {
-                new_fd_table.push(Some(file.clone()));
-            } else {
-                new_fd_table.push(None);
-            }
-        }
-        let task_control_block = Arc::new(TaskControlBlock {
-            pid: pid_handle,
-            kernel_stack,
-            inner: unsafe {
-                UPSafeCell::new(TaskControlBlockInner {
-                    trap_cx_ppn,
-                    base_size: parent_inner.base_size,
-                    task_cx: TaskContext::goto_trap_return(kernel_stack_top),
-                    task_status: TaskStatus::Ready,
-                    memory_set,
-                    parent: Some(Arc::downgrade(self)),
-                    children: Vec::new(),
-                    exit_code: 0,
-                    fd_table: new_fd_table,
-                    signals: SignalFlags::empty(),
-                    // inherit the signal_mask and signal_action
-                    signal_mask: parent_inner.signal_mask,
-                    handling_sig: -1,
-                    signal_actions: parent_inner.signal_actions.clone(),
-                    killed: false,
-                    frozen: false,
-                    trap_ctx_backup: Vec::new(),
-                })
-            },
-        });
-        // add child
-        parent_inner.children.push(task_control_block.clone());
-        // modify kernel_sp in trap_cx
-        // **** access child PCB exclusively
-        let trap_cx = task_control_block.inner_exclusive_access().get_trap_cx();
-        trap_cx.kernel_sp = kernel_stack_top;
-        // return
-        task_control_block
-        // **** release child PCB
-        // ---- release parent PCB
+    /// 线程 id
+    pub fn gettid(&self) -> usize {
+        self.inner_exclusive_access().res.as_ref().unwrap().tid
     }
 }
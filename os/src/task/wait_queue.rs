@@ -0,0 +1,66 @@
+//! 等待队列: 暂时无法继续执行的任务在这里阻塞, 直到条件改变时被唤醒
+
+use super::{current_task, mark_current_blocked, wakeup_task, TaskControlBlock, TaskStatus};
+use alloc::collections::VecDeque;
+use alloc::sync::Arc;
+
+/// 等待同一个条件的任务.
+///
+/// 等待的一方在持有保护条件的锁时调用[`WaitQueue::add_current`], 释放锁之后调用
+/// `block_current_and_run_next`, 被唤醒后重新检查条件. 在释放锁之前任务就已经被标记为阻塞,
+/// 其它 hart 上的唤醒不会丢失. 条件改变的一方调用
+/// [`WaitQueue::wake_all`]. 任务也可能因为信号被提前唤醒, 所以等待的一方总是要重新检查条件
+///
+/// 使用[`WaitQueue::wake_one`]时, 等待的一方被唤醒后要先调用[`WaitQueue::remove_current`],
+/// 以免队列中残留的项在之后吞掉唤醒
+pub struct WaitQueue {
+    queue: VecDeque<Arc<TaskControlBlock>>,
+}
+
+impl WaitQueue {
+    /// 创建空的等待队列
+    pub fn new() -> Self {
+        Self {
+            queue: VecDeque::new(),
+        }
+    }
+    /// 把当前任务加入等待队列, 并标记为阻塞
+    pub fn add_current(&mut self) {
+        mark_current_blocked();
+        self.queue.push_back(current_task().unwrap());
+    }
+    /// 把当前任务从等待队列中移除, 返回它是否还在队列中(没有被`wake_*`唤醒).
+    /// 加入队列之后没有阻塞就放弃等待时, 任务恢复为运行状态
+    pub fn remove_current(&mut self) -> bool {
+        let task = current_task().unwrap();
+        let len = self.queue.len();
+        self.queue.retain(|t| !Arc::ptr_eq(t, &task));
+        let mut task_inner = task.inner_exclusive_access();
+        if task_inner.task_status == TaskStatus::Blocked {
+            task_inner.task_status = TaskStatus::Running;
+        }
+        self.queue.len() != len
+    }
+    /// 队列中是否没有等待的任务
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+    /// 唤醒所有等待的任务
+    pub fn wake_all(&mut self) {
+        for task in self.queue.drain(..) {
+            wakeup_task(task);
+        }
+    }
+    /// 唤醒最早等待的一个任务, 没有可以唤醒的任务时返回`false`.
+    /// 已经被信号唤醒的任务会自己重新检查条件, 跳过它们
+    pub fn wake_one(&mut self) -> bool {
+        while let Some(task) = self.queue.pop_front() {
+            let blocked = task.inner_exclusive_access().task_status == TaskStatus::Blocked;
+            if blocked {
+                wakeup_task(task);
+                return true;
+            }
+        }
+        false
+    }
+}
@@ -2,10 +2,25 @@
 
 use crate::config::CLOCK_FREQ;
 use crate::sbi::set_timer;
+use crate::sync::SpinLock;
+use crate::task::{
+    block_current_and_run_next, current_has_interrupting_signal, current_task,
+    mark_current_blocked, wakeup_task, TaskControlBlock,
+};
+use alloc::collections::BinaryHeap;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+use lazy_static::*;
 use riscv::register::time;
 
 const TICKS_PER_SEC: usize = 100;
 const MSEC_PER_SEC: usize = 1000;
+/// 每秒的微秒数
+pub const USEC_PER_SEC: usize = 1_000_000;
+/// 每秒的纳秒数
+pub const NSEC_PER_SEC: usize = 1_000_000_000;
+const NSEC_PER_MSEC: usize = NSEC_PER_SEC / MSEC_PER_SEC;
 
 /// read the `mtime` register
 pub fn get_time() -> usize {
@@ -17,7 +32,150 @@ pub fn get_time_ms() -> usize {
     time::read() / (CLOCK_FREQ / MSEC_PER_SEC)
 }
 
+/// 与 Linux 的`struct timespec`相同的时间间隔
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+pub struct TimeSpec {
+    /// 秒
+    pub sec: usize,
+    /// 纳秒, 小于[`NSEC_PER_SEC`]
+    pub nsec: usize,
+}
+
+impl TimeSpec {
+    /// 向上取整到毫秒
+    pub fn to_ms(&self) -> usize {
+        self.sec
+            .saturating_mul(MSEC_PER_SEC)
+            .saturating_add((self.nsec + NSEC_PER_MSEC - 1) / NSEC_PER_MSEC)
+    }
+    /// `ms`毫秒
+    pub fn from_ms(ms: usize) -> Self {
+        Self {
+            sec: ms / MSEC_PER_SEC,
+            nsec: ms % MSEC_PER_SEC * NSEC_PER_MSEC,
+        }
+    }
+}
+
+/// 与 Linux 的`struct timeval`相同的时间间隔
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+pub struct TimeVal {
+    /// 秒
+    pub sec: usize,
+    /// 微秒, 小于[`USEC_PER_SEC`]
+    pub usec: usize,
+}
+
+impl TimeVal {
+    /// 向上取整到`mtime`的计数
+    pub fn to_time(&self) -> usize {
+        self.sec
+            .saturating_mul(CLOCK_FREQ)
+            .saturating_add((self.usec * CLOCK_FREQ + USEC_PER_SEC - 1) / USEC_PER_SEC)
+    }
+    /// `mtime`经过`time`个计数的时间
+    pub fn from_time(time: usize) -> Self {
+        Self {
+            sec: time / CLOCK_FREQ,
+            usec: time % CLOCK_FREQ * USEC_PER_SEC / CLOCK_FREQ,
+        }
+    }
+}
+
+/// 与 Linux 的`struct itimerval`相同的间隔定时器设置
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+pub struct ITimerVal {
+    /// 到期之后重新开始计时的时间, 为 0 时只触发一次
+    pub interval: TimeVal,
+    /// 距离下一次到期的时间, 为 0 时停止计时
+    pub value: TimeVal,
+}
+
 /// set the next timer interrupt
 pub fn set_next_trigger() {
     set_timer(get_time() + CLOCK_FREQ / TICKS_PER_SEC);
 }
+
+/// 定时器队列中的一项: 到`expire_ms`时唤醒`task`
+pub struct TimerCondVar {
+    /// 到期时间(毫秒)
+    pub expire_ms: usize,
+    /// 到期时被唤醒的任务
+    pub task: Arc<TaskControlBlock>,
+}
+
+impl PartialEq for TimerCondVar {
+    fn eq(&self, other: &Self) -> bool {
+        self.expire_ms == other.expire_ms
+    }
+}
+impl Eq for TimerCondVar {}
+impl PartialOrd for TimerCondVar {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+impl Ord for TimerCondVar {
+    /// `BinaryHeap`是大顶堆, 反过来比较使最早到期的在堆顶
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.expire_ms.cmp(&self.expire_ms)
+    }
+}
+
+lazy_static! {
+    /// 按到期时间排序的定时器队列
+    static ref TIMERS: SpinLock<BinaryHeap<TimerCondVar>> =
+        SpinLock::new(BinaryHeap::new());
+}
+
+/// 在`expire_ms`时唤醒阻塞的`task`
+pub fn add_timer(expire_ms: usize, task: Arc<TaskControlBlock>) {
+    TIMERS
+        .exclusive_access()
+        .push(TimerCondVar { expire_ms, task });
+}
+
+/// 取消`task`所有还没有到期的定时器
+pub fn remove_timer(task: &Arc<TaskControlBlock>) {
+    let mut timers = TIMERS.exclusive_access();
+    let remaining: Vec<TimerCondVar> = core::mem::take(&mut *timers)
+        .into_vec()
+        .into_iter()
+        .filter(|timer| !Arc::ptr_eq(&timer.task, task))
+        .collect();
+    *timers = BinaryHeap::from(remaining);
+}
+
+/// 唤醒所有到期的定时器对应的任务, 在时钟中断和处理器空闲时调用
+pub fn check_timer() {
+    let current_ms = get_time_ms();
+    let mut timers = TIMERS.exclusive_access();
+    while let Some(timer) = timers.peek() {
+        if timer.expire_ms > current_ms {
+            break;
+        }
+        let timer = timers.pop().unwrap();
+        wakeup_task(timer.task);
+    }
+}
+
+/// 阻塞当前任务直到`expire_ms`, 到期时返回`true`, 被信号打断时返回`false`.
+/// 到期的定时器在时钟中断中被检查, 所以精度是一个时钟周期
+pub fn sleep_until(expire_ms: usize) -> bool {
+    loop {
+        if get_time_ms() >= expire_ms {
+            return true;
+        }
+        if current_has_interrupting_signal() {
+            return false;
+        }
+        // mark the task blocked before the timer can fire on another hart
+        mark_current_blocked();
+        add_timer(expire_ms, current_task().unwrap());
+        block_current_and_run_next();
+        remove_timer(&current_task().unwrap());
+    }
+}
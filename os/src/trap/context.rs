@@ -18,6 +18,8 @@ pub struct TrapContext {
     pub kernel_sp: usize,
     /// trap handler(virtual address)
     pub trap_handler: usize,
+    /// 返回用户态时所在 hart 的编号, 回到内核时恢复到 tp
+    pub kernel_tp: usize,
 }
 
 impl TrapContext {
@@ -42,6 +44,7 @@ impl TrapContext {
             kernel_satp,  // addr of page table
             kernel_sp,    // kernel stack
             trap_handler, // addr of trap_handler function
+            kernel_tp: 0, // set by __restore
         };
         cx.set_sp(sp); // app's user stack pointer
         cx // return initial Trap Context of app
@@ -13,13 +13,18 @@
 //! to [`syscall()`].
 mod context;
 
-use crate::config::{TRAMPOLINE, TRAP_CONTEXT};
+use crate::config::TRAMPOLINE;
+use crate::fs::poll_stdin;
+use crate::mm::MapPermission;
 use crate::syscall::syscall;
 use crate::task::{
-    check_signals_error_of_current, current_add_signal, current_trap_cx, current_user_token,
-    exit_current_and_run_next, handle_signals, suspend_current_and_run_next, SignalFlags,
+    account_busy_tick, charge_current_itimers, check_real_itimers, check_signals_killed_of_current,
+    current_enter_kernel, current_force_signal, current_leave_kernel, current_process,
+    current_trap_cx, current_trap_cx_user_va, current_user_token, exit_current_if_zombie,
+    exit_current_process_and_run_next, handle_signals, preempt_current_on_tick, SignalFlags,
+    SignalInfo, ILL_ILLOPC, SEGV_MAPERR,
 };
-use crate::timer::set_next_trigger;
+use crate::timer::{check_timer, set_next_trigger};
 use core::arch::{asm, global_asm};
 use riscv::register::{
     mtvec::TrapMode,
@@ -57,6 +62,9 @@ pub fn enable_timer_interrupt() {
 /// handle an interrupt, exception, or system call from user space
 pub fn trap_handler() -> ! {
     set_kernel_trap_entry();
+    // the process may have exited on another hart while this thread was in user space
+    exit_current_if_zombie();
+    current_enter_kernel();
     let scause = scause::read();
     let stval = stval::read();
     match scause.cause() {
@@ -65,7 +73,10 @@ pub fn trap_handler() -> ! {
             let mut cx = current_trap_cx();
             cx.sepc += 4;
             // get system call return value
-            let result = syscall(cx.x[17], [cx.x[10], cx.x[11], cx.x[12]]);
+            let result = syscall(
+                cx.x[17],
+                [cx.x[10], cx.x[11], cx.x[12], cx.x[13], cx.x[14], cx.x[15]],
+            );
             // cx is changed during sys_exec, so we have to call it again
             cx = current_trap_cx();
             cx.x[10] = result as usize;
@@ -76,23 +87,38 @@ pub fn trap_handler() -> ! {
         | Trap::Exception(Exception::InstructionPageFault)
         | Trap::Exception(Exception::LoadFault)
         | Trap::Exception(Exception::LoadPageFault) => {
-            trace!(
-                "[kernel] {:?} in application, bad addr = {:#x}, bad instruction = {:#x}, kernel killed it.",
-                scause.cause(),
-                stval,
-                current_trap_cx().sepc,
-            );
-            // page fault exit code
-            current_add_signal(SignalFlags::SIGSEGV);
+            // copy-on-write and lazily allocated pages fault on the first access,
+            // the instruction is re-executed
+            if !handle_page_fault(scause.cause(), stval) {
+                trace!(
+                    "[kernel] {:?} in application, bad addr = {:#x}, bad instruction = {:#x}, kernel killed it.",
+                    scause.cause(),
+                    stval,
+                    current_trap_cx().sepc,
+                );
+                // page fault exit code
+                current_force_signal(SignalInfo {
+                    addr: stval,
+                    ..SignalInfo::new(SignalFlags::SIGSEGV, SEGV_MAPERR)
+                });
+            }
         }
         Trap::Exception(Exception::IllegalInstruction) => {
             trace!("[kernel] IllegalInstruction in application, kernel killed it.");
             // illegal instruction exit code
-            current_add_signal(SignalFlags::SIGILL);
+            current_force_signal(SignalInfo {
+                addr: current_trap_cx().sepc,
+                ..SignalInfo::new(SignalFlags::SIGILL, ILL_ILLOPC)
+            });
         }
         Trap::Interrupt(Interrupt::SupervisorTimer) => {
             set_next_trigger();
-            suspend_current_and_run_next();
+            account_busy_tick();
+            charge_current_itimers();
+            check_timer();
+            check_real_itimers();
+            poll_stdin();
+            preempt_current_on_tick();
         }
         _ => {
             panic!(
@@ -106,21 +132,36 @@ pub fn trap_handler() -> ! {
     trace!("trap_handler:: handle_signals");
     handle_signals();
 
-    // check error signals (if error then exit)
-    if let Some((errno, msg)) = check_signals_error_of_current() {
-        trace!("{}", msg);
-        exit_current_and_run_next(errno);
+    // exit if the process is killed by a signal
+    if let Some(status) = check_signals_killed_of_current() {
+        trace!("[kernel] killed by signal, wait status = {:#x}", status);
+        exit_current_process_and_run_next(status);
     }
     trap_return();
 }
 
+/// try to resolve a page fault (e.g. copy-on-write, lazy allocation) in the address space of current task
+fn handle_page_fault(cause: Trap, addr: usize) -> bool {
+    let access = match cause {
+        Trap::Exception(Exception::StorePageFault) => MapPermission::W,
+        Trap::Exception(Exception::LoadPageFault) => MapPermission::R,
+        Trap::Exception(Exception::InstructionPageFault) => MapPermission::X,
+        _ => return false,
+    };
+    let process = current_process();
+    let mut inner = process.inner_exclusive_access();
+    inner.memory_set.handle_page_fault(addr.into(), access)
+}
+
 #[no_mangle]
 /// set the new addr of __restore asm function in TRAMPOLINE page,
 /// set the reg a0 = trap_cx_ptr, reg a1 = phy addr of usr page table,
 /// finally, jump to new addr of __restore asm function
 pub fn trap_return() -> ! {
+    exit_current_if_zombie();
+    current_leave_kernel();
     set_user_trap_entry();
-    let trap_cx_ptr = TRAP_CONTEXT;
+    let trap_cx_ptr = current_trap_cx_user_va();
     let user_satp = current_user_token();
     extern "C" {
         fn __alltraps();
@@ -0,0 +1,71 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    exit, fork, mmap, munmap, open, pipe, read, waitpid, waitpid_status, write, Errno, MmapFlags,
+    MmapProt, OpenFlags,
+};
+
+const PAGE_SIZE: usize = 4096;
+
+/// 申请一页再解除映射, 得到一个一定不可访问的用户地址
+fn unmapped_page() -> usize {
+    let start = mmap(
+        0,
+        PAGE_SIZE,
+        MmapProt::READ | MmapProt::WRITE,
+        MmapFlags::PRIVATE | MmapFlags::ANONYMOUS,
+        0,
+        0,
+    );
+    assert!(start > 0);
+    assert_eq!(munmap(start as usize, PAGE_SIZE), 0);
+    start as usize
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let bad = unmapped_page();
+    let buf = unsafe { core::slice::from_raw_parts(bad as *const u8, 16) };
+    assert_eq!(write(1, buf), Errno::EFAULT.into());
+    // 只读的映射不能作为 read 的缓冲区
+    let readonly = mmap(
+        0,
+        PAGE_SIZE,
+        MmapProt::READ,
+        MmapFlags::PRIVATE | MmapFlags::ANONYMOUS,
+        0,
+        0,
+    );
+    assert!(readonly > 0);
+    let mut pipe_fd = [0usize; 2];
+    assert_eq!(pipe(&mut pipe_fd), 0);
+    assert_eq!(write(pipe_fd[1], b"x"), 1);
+    let readonly_buf = unsafe { core::slice::from_raw_parts_mut(readonly as usize as *mut u8, 1) };
+    assert_eq!(read(pipe_fd[0], readonly_buf), Errno::EFAULT.into());
+    // 内核地址空间同样不可访问
+    let kernel_buf = unsafe { core::slice::from_raw_parts(0x8020_0000 as *const u8, 16) };
+    assert_eq!(write(1, kernel_buf), Errno::EFAULT.into());
+    let path =
+        unsafe { core::str::from_utf8_unchecked(core::slice::from_raw_parts(bad as *const u8, 8)) };
+    assert_eq!(open(path, OpenFlags::RDONLY), Errno::EFAULT.into());
+    let pid = fork();
+    if pid == 0 {
+        exit(7);
+    }
+    let bad_status = unsafe { &mut *(bad as *mut i32) };
+    // 阻塞到子进程结束之后才检查指针
+    assert_eq!(
+        waitpid_status(pid as isize, bad_status, 0),
+        Errno::EFAULT.into()
+    );
+    // 出错时子进程没有被回收, 仍然可以等待
+    let mut exit_code = 0;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, 7);
+    println!("bad_pointer passed!");
+    0
+}
@@ -0,0 +1,55 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    condvar_create, condvar_signal, condvar_wait, exit, mutex_create, mutex_lock, mutex_unlock,
+    sleep, thread_create, waittid, Errno,
+};
+
+static mut READY: bool = false;
+static mut MUTEX: usize = 0;
+static mut CONDVAR: usize = 0;
+
+fn setter(_arg: usize) -> ! {
+    sleep(20);
+    unsafe {
+        mutex_lock(MUTEX);
+        READY = true;
+        condvar_signal(CONDVAR);
+        mutex_unlock(MUTEX);
+    }
+    exit(0);
+    unreachable!()
+}
+
+fn waiter(_arg: usize) -> ! {
+    unsafe {
+        mutex_lock(MUTEX);
+        // 可能被虚假唤醒, 在循环中检查条件
+        while !READY {
+            assert_eq!(condvar_wait(CONDVAR, MUTEX), 0);
+        }
+        mutex_unlock(MUTEX);
+    }
+    exit(0);
+    unreachable!()
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    unsafe {
+        MUTEX = mutex_create() as usize;
+        CONDVAR = condvar_create() as usize;
+        // 没有持有互斥锁时不能等待
+        assert_eq!(condvar_wait(CONDVAR, MUTEX), Errno::EPERM.into());
+    }
+    let waiter_tid = thread_create(waiter as usize, 0);
+    let setter_tid = thread_create(setter as usize, 0);
+    assert_eq!(waittid(waiter_tid as usize), 0);
+    assert_eq!(waittid(setter_tid as usize), 0);
+    println!("condvar_test passed!");
+    0
+}
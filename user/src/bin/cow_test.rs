@@ -0,0 +1,47 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{close, exit, fork, pipe, read, wait, write};
+
+const LEN: usize = 4096 * 4;
+
+static mut DATA: [u8; LEN] = [1u8; LEN];
+
+/// fork 后父子进程共享物理页, 任意一方写入都不应该被另一方看到
+#[no_mangle]
+pub fn main() -> i32 {
+    let mut pipe_fd = [0usize; 2];
+    pipe(&mut pipe_fd);
+    let pid = fork();
+    if pid == 0 {
+        close(pipe_fd[1]);
+        // 等父进程写完
+        let mut buf = [0u8; 1];
+        read(pipe_fd[0], &mut buf);
+        close(pipe_fd[0]);
+        unsafe {
+            assert!(DATA.iter().all(|b| *b == 1));
+            DATA.fill(3);
+            assert!(DATA.iter().all(|b| *b == 3));
+        }
+        println!("cow child ok");
+        exit(0);
+    }
+    close(pipe_fd[0]);
+    unsafe {
+        DATA.fill(2);
+    }
+    write(pipe_fd[1], &[0u8]);
+    close(pipe_fd[1]);
+    let mut exit_code: i32 = 0;
+    assert_eq!(wait(&mut exit_code), pid);
+    assert_eq!(exit_code, 0);
+    unsafe {
+        assert!(DATA.iter().all(|b| *b == 2));
+    }
+    println!("cow_test passed!");
+    0
+}
@@ -0,0 +1,51 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    enable_deadlock_detect, exit, mutex_create, mutex_lock, mutex_unlock, semaphore_create,
+    semaphore_down, semaphore_up, sleep, thread_create, waittid, Errno,
+};
+
+static mut MUTEX_A: usize = 0;
+static mut MUTEX_B: usize = 0;
+
+fn locker(_arg: usize) -> ! {
+    unsafe {
+        assert_eq!(mutex_lock(MUTEX_B), 0);
+        // 阻塞, 直到主线程放弃 B 并释放 A
+        assert_eq!(mutex_lock(MUTEX_A), 0);
+        assert_eq!(mutex_unlock(MUTEX_A), 0);
+        assert_eq!(mutex_unlock(MUTEX_B), 0);
+    }
+    exit(0);
+    unreachable!()
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    assert_eq!(enable_deadlock_detect(true), 0);
+    // 两个线程以相反的顺序加锁
+    unsafe {
+        MUTEX_A = mutex_create() as usize;
+        MUTEX_B = mutex_create() as usize;
+        assert_eq!(mutex_lock(MUTEX_A), 0);
+    }
+    let tid = thread_create(locker as usize, 0);
+    sleep(20);
+    unsafe {
+        assert_eq!(mutex_lock(MUTEX_B), Errno::EDEADLK.into());
+        assert_eq!(mutex_unlock(MUTEX_A), 0);
+    }
+    assert_eq!(waittid(tid as usize), 0);
+    // 唯一的线程等待已经耗尽的信号量
+    let sem_id = semaphore_create(1) as usize;
+    assert_eq!(semaphore_down(sem_id), 0);
+    assert_eq!(semaphore_down(sem_id), Errno::EDEADLK.into());
+    assert_eq!(semaphore_up(sem_id), 0);
+    assert_eq!(semaphore_down(sem_id), 0);
+    println!("deadlock_test passed!");
+    0
+}
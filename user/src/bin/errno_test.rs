@@ -0,0 +1,60 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    exec, strerror, try_close, try_dup, try_kill, try_mmap, try_munmap, try_open, try_write, Errno,
+    MmapFlags, MmapProt, OpenFlags, SIGUSR1,
+};
+
+/// 以`\0`结尾的 1000 字节的参数, 用户栈放不下, 放在数据段中
+static LONG_ARG: [u8; 1000] = {
+    let mut arg = [b'a'; 1000];
+    arg[999] = 0;
+    arg
+};
+
+#[no_mangle]
+pub fn main() -> i32 {
+    assert_eq!(try_close(1000), Err(Errno::EBADF));
+    assert_eq!(try_dup(1000), Err(Errno::EBADF));
+    assert_eq!(try_write(0, b"stdin"), Err(Errno::EBADF));
+    assert_eq!(
+        try_open("errno_test_no_such_file\0", OpenFlags::RDONLY),
+        Err(Errno::ENOENT)
+    );
+    assert_eq!(try_kill(usize::MAX, SIGUSR1), Err(Errno::ESRCH));
+    assert_eq!(try_munmap(1, 4096), Err(Errno::EINVAL));
+    assert_eq!(
+        try_mmap(0, 4096, MmapProt::READ, MmapFlags::PRIVATE, 1000, 0),
+        Err(Errno::EBADF)
+    );
+    // 参数放不下新程序的用户栈
+    let mut args = [LONG_ARG.as_ptr(); 11];
+    args[10] = core::ptr::null();
+    assert_eq!(exec("hello_world\0", &args), Errno::E2BIG.into());
+    // 不是 ELF 文件
+    let fd = try_open(
+        "errno_test_not_elf\0",
+        OpenFlags::CREATE | OpenFlags::WRONLY,
+    )
+    .unwrap();
+    assert_eq!(try_write(fd, b"not an elf file"), Ok(15));
+    try_close(fd).unwrap();
+    assert_eq!(
+        exec("errno_test_not_elf\0", &[core::ptr::null()]),
+        Errno::ENOEXEC.into()
+    );
+    assert_eq!(strerror(Errno::ENOENT), "No such file or directory");
+    assert_eq!(Errno::result(-9), Err(Errno::EBADF));
+    assert_eq!(Errno::result(3), Ok(3));
+    // 没有列出的错误码不会 panic
+    assert_eq!(Errno::result(-1000), Err(Errno::Unknown(1000)));
+    assert_eq!(isize::from(Errno::Unknown(1000)), -1000);
+    println!("{}", Errno::Unknown(1000));
+    println!("{}", Errno::ECHILD);
+    println!("errno_test passed!");
+    0
+}
@@ -22,7 +22,8 @@ pub fn main() -> i32 {
     }
     println!("I am the parent, waiting now..");
     let mut xstate: i32 = 0;
-    assert!(waitpid(pid as usize, &mut xstate) == pid && xstate == MAGIC);
+    // 与 Linux 相同, 只保留退出码的低 8 位
+    assert!(waitpid(pid as usize, &mut xstate) == pid && xstate == MAGIC as i8 as i32);
     assert!(waitpid(pid as usize, &mut xstate) < 0 && wait(&mut xstate) <= 0);
     println!("waitpid {} ok.", pid);
     println!("exit pass.");
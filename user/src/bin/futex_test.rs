@@ -0,0 +1,71 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use core::sync::atomic::AtomicU32;
+use user_lib::{
+    exit, futex_wait, futex_wake, get_time, thread_create, waittid, yield_, Errno, FutexMutex,
+};
+
+const THREADS: usize = 4;
+const PER_THREAD: usize = 100;
+
+static mut A: usize = 0;
+static MUTEX: FutexMutex = FutexMutex::new();
+static WORD: AtomicU32 = AtomicU32::new(0);
+
+fn long_waiter(_arg: usize) -> ! {
+    // 很长的超时不会溢出, 只能被唤醒
+    exit(futex_wait(&WORD, 0, usize::MAX) as i32);
+    unreachable!()
+}
+
+fn adder(_arg: usize) -> ! {
+    for _ in 0..PER_THREAD {
+        MUTEX.lock();
+        // 在临界区中让出处理器, 其他线程会在 futex 上阻塞
+        let t = unsafe { A };
+        yield_();
+        unsafe {
+            A = t + 1;
+        }
+        MUTEX.unlock();
+    }
+    exit(0);
+    unreachable!()
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let word = AtomicU32::new(1);
+    // 值不匹配时立即返回
+    assert_eq!(futex_wait(&word, 0, 0), Errno::EAGAIN.into());
+    // 没有等待者时不唤醒任何任务
+    assert_eq!(futex_wake(&word, 1), 0);
+    // 超时返回
+    let start = get_time();
+    assert_eq!(futex_wait(&word, 1, 10), Errno::ETIMEDOUT.into());
+    assert!(get_time() - start >= 10);
+    let tid = thread_create(long_waiter as usize, 0);
+    while futex_wake(&WORD, 1) == 0 {
+        yield_();
+    }
+    assert_eq!(waittid(tid as usize), 0);
+
+    assert!(MUTEX.try_lock());
+    assert!(!MUTEX.try_lock());
+    MUTEX.unlock();
+
+    let mut tids = [0; THREADS];
+    for tid in tids.iter_mut() {
+        *tid = thread_create(adder as usize, 0);
+    }
+    for tid in tids.iter() {
+        assert_eq!(waittid(*tid as usize), 0);
+    }
+    assert_eq!(unsafe { A }, THREADS * PER_THREAD);
+    println!("futex_test passed!");
+    0
+}
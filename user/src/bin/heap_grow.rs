@@ -0,0 +1,42 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+extern crate alloc;
+
+use alloc::vec::Vec;
+use user_lib::{munmap, sbrk, Errno};
+
+const LEN: usize = 64 * 1024;
+
+/// 申请远大于初始堆空间的内存, 堆应该通过 sbrk 自动扩大
+#[no_mangle]
+pub fn main() -> i32 {
+    let old_brk = sbrk(0);
+    assert!(old_brk > 0);
+    let mut v: Vec<usize> = Vec::with_capacity(LEN);
+    for i in 0..LEN {
+        v.push(i);
+    }
+    for (i, x) in v.iter().enumerate() {
+        assert_eq!(*x, i);
+    }
+    assert!(sbrk(0) > old_brk);
+    drop(v);
+    // 直接通过 sbrk 扩大再缩小堆
+    let brk = sbrk(4096);
+    assert!(brk > 0);
+    let p = brk as *mut u8;
+    unsafe {
+        p.write_volatile(1);
+        assert_eq!(p.read_volatile(), 1);
+    }
+    // 堆只能通过 sbrk 缩小, munmap 不能拆开它
+    assert_eq!(munmap(brk as usize & !4095, 4096), Errno::EINVAL.into());
+    assert_eq!(unsafe { p.read_volatile() }, 1);
+    assert_eq!(sbrk(-4096), brk + 4096);
+    assert_eq!(sbrk(0), brk);
+    println!("heap_grow passed!");
+    0
+}
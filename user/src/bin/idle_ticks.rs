@@ -0,0 +1,38 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use core::sync::atomic::AtomicU32;
+use user_lib::{cpu_ticks, futex_wait, get_time, CpuTicks, Errno};
+
+const WAIT_MS: usize = 200;
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let mut before = CpuTicks::default();
+    assert_eq!(cpu_ticks(&mut before), 0);
+
+    // 阻塞等待时没有任务可以运行, hart 空闲
+    let word = AtomicU32::new(0);
+    assert_eq!(futex_wait(&word, 0, WAIT_MS), Errno::ETIMEDOUT.into());
+    let mut blocked = CpuTicks::default();
+    assert_eq!(cpu_ticks(&mut blocked), 0);
+    assert!(blocked.idle > before.idle);
+
+    // 忙等时时钟中断打断的是当前任务
+    let end = get_time() + WAIT_MS as isize;
+    while get_time() < end {}
+    let mut busy = CpuTicks::default();
+    assert_eq!(cpu_ticks(&mut busy), 0);
+    assert!(busy.busy > blocked.busy);
+
+    println!(
+        "idle ticks: {}, busy ticks: {}",
+        busy.idle - before.idle,
+        busy.busy - before.busy
+    );
+    println!("idle_ticks passed!");
+    0
+}
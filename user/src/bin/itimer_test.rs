@@ -0,0 +1,102 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+use user_lib::{
+    alarm, get_time, getitimer, setitimer, signal, sigreturn, sleep, Errno, ITimerVal, TimeVal,
+    ITIMER_PROF, ITIMER_REAL, ITIMER_VIRTUAL, SIGALRM, SIGPROF, SIGVTALRM,
+};
+
+static ALRM: AtomicUsize = AtomicUsize::new(0);
+static VTALRM: AtomicUsize = AtomicUsize::new(0);
+static PROF: AtomicUsize = AtomicUsize::new(0);
+
+fn on_alrm() {
+    ALRM.fetch_add(1, Ordering::SeqCst);
+    sigreturn();
+}
+
+fn on_vtalrm() {
+    VTALRM.fetch_add(1, Ordering::SeqCst);
+    sigreturn();
+}
+
+fn on_prof() {
+    PROF.fetch_add(1, Ordering::SeqCst);
+    sigreturn();
+}
+
+fn ms(ms: usize) -> TimeVal {
+    TimeVal {
+        sec: ms / 1000,
+        usec: ms % 1000 * 1000,
+    }
+}
+
+/// 在用户态忙等, 直到`counter`达到`count`, 超时返回`false`
+fn spin_until(counter: &AtomicUsize, count: usize) -> bool {
+    let end = get_time() + 3000;
+    while counter.load(Ordering::SeqCst) < count {
+        if get_time() > end {
+            return false;
+        }
+    }
+    true
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    assert!(signal(SIGALRM, on_alrm as usize) >= 0);
+    assert!(signal(SIGVTALRM, on_vtalrm as usize) >= 0);
+    assert!(signal(SIGPROF, on_prof as usize) >= 0);
+
+    let mut curr = ITimerVal::default();
+    assert_eq!(getitimer(ITIMER_PROF + 1, &mut curr), Errno::EINVAL.into());
+
+    // alarm 返回之前的设置剩余的秒数
+    assert_eq!(alarm(5), 0);
+    assert_eq!(alarm(0), 5);
+    assert_eq!(getitimer(ITIMER_REAL, &mut curr), 0);
+    assert_eq!(curr.value.sec + curr.value.usec, 0);
+
+    // ITIMER_REAL 打断睡眠
+    let real = ITimerVal {
+        interval: TimeVal::default(),
+        value: ms(100),
+    };
+    assert_eq!(setitimer(ITIMER_REAL, &real, None), 0);
+    let start = get_time();
+    assert_eq!(sleep(1000), Errno::EINTR.into());
+    assert!(get_time() - start >= 100);
+    assert_eq!(ALRM.load(Ordering::SeqCst), 1);
+
+    // ITIMER_VIRTUAL 只在用户态计时
+    let virt = ITimerVal {
+        interval: TimeVal::default(),
+        value: ms(50),
+    };
+    assert_eq!(setitimer(ITIMER_VIRTUAL, &virt, None), 0);
+    assert!(spin_until(&VTALRM, 1));
+    assert_eq!(getitimer(ITIMER_VIRTUAL, &mut curr), 0);
+    assert_eq!(curr.value.sec + curr.value.usec, 0);
+
+    // 周期性的 ITIMER_PROF
+    let prof = ITimerVal {
+        interval: ms(20),
+        value: ms(20),
+    };
+    assert_eq!(setitimer(ITIMER_PROF, &prof, None), 0);
+    assert!(spin_until(&PROF, 3));
+    let mut old = ITimerVal::default();
+    assert_eq!(
+        setitimer(ITIMER_PROF, &ITimerVal::default(), Some(&mut old)),
+        0
+    );
+    assert_eq!(old.interval.usec, 20_000);
+
+    println!("itimer_test passed!");
+    0
+}
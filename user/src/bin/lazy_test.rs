@@ -0,0 +1,31 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+const LEN: usize = 4096 * 64;
+
+static mut BSS: [u8; LEN] = [0u8; LEN];
+
+/// 大的 BSS 段是惰性分配的, 只有访问过的页才会占用物理内存
+#[no_mangle]
+pub fn main() -> i32 {
+    unsafe {
+        // 未写入过的页读出来都是0
+        assert!(BSS.iter().step_by(4096).all(|b| *b == 0));
+        for i in (0..LEN).step_by(4096 * 2) {
+            BSS[i] = (i / 4096) as u8;
+        }
+        for i in (0..LEN).step_by(4096) {
+            let expected = if i % (4096 * 2) == 0 {
+                (i / 4096) as u8
+            } else {
+                0
+            };
+            assert_eq!(BSS[i], expected);
+        }
+    }
+    println!("lazy_test passed!");
+    0
+}
@@ -0,0 +1,34 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{mmap, mprotect, MmapFlags, MmapProt};
+
+const PAGE_SIZE: usize = 4096;
+
+/// 写入被 mprotect 改为只读的页, 应该被内核以 SIGSEGV 杀死
+#[no_mangle]
+pub fn main() -> i32 {
+    let start = mmap(
+        0,
+        PAGE_SIZE,
+        MmapProt::READ | MmapProt::WRITE,
+        MmapFlags::PRIVATE | MmapFlags::ANONYMOUS,
+        0,
+        0,
+    );
+    assert!(start > 0);
+    let data = start as *mut u8;
+    unsafe {
+        data.write_volatile(1);
+    }
+    assert_eq!(mprotect(start as usize, PAGE_SIZE, MmapProt::READ), 0);
+    println!("Into Test mmap protect, we will insert an invalid store operation...");
+    println!("Kernel should kill this application!");
+    unsafe {
+        data.write_volatile(2);
+    }
+    0
+}
@@ -0,0 +1,171 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    close, fork, mmap, mprotect, munmap, open, read, try_mprotect, wait, write, Errno, MmapFlags,
+    MmapProt, OpenFlags,
+};
+
+const PAGE_SIZE: usize = 4096;
+
+fn anonymous() {
+    let len = PAGE_SIZE * 4;
+    let start = mmap(
+        0,
+        len,
+        MmapProt::READ | MmapProt::WRITE,
+        MmapFlags::PRIVATE | MmapFlags::ANONYMOUS,
+        0,
+        0,
+    );
+    assert!(start > 0);
+    let start = start as usize;
+    let data = unsafe { core::slice::from_raw_parts_mut(start as *mut u8, len) };
+    assert!(data.iter().all(|b| *b == 0));
+    data.fill(0x5a);
+    // MAP_FIXED 替换重叠部分原有的映射
+    assert_eq!(
+        mmap(
+            start + PAGE_SIZE,
+            PAGE_SIZE,
+            MmapProt::READ,
+            MmapFlags::PRIVATE | MmapFlags::ANONYMOUS | MmapFlags::FIXED,
+            0,
+            0,
+        ),
+        (start + PAGE_SIZE) as isize
+    );
+    assert_eq!(data[PAGE_SIZE], 0);
+    assert_eq!(data[0], 0x5a);
+    assert_eq!(data[PAGE_SIZE * 2], 0x5a);
+    // 解除中间一页的映射, 两边仍然可以访问
+    assert_eq!(munmap(start + PAGE_SIZE, PAGE_SIZE), 0);
+    assert_eq!(data[0], 0x5a);
+    assert_eq!(data[PAGE_SIZE * 2], 0x5a);
+    // 空出来的一页可以重新映射
+    assert_eq!(
+        mmap(
+            start + PAGE_SIZE,
+            PAGE_SIZE,
+            MmapProt::READ,
+            MmapFlags::PRIVATE | MmapFlags::ANONYMOUS | MmapFlags::FIXED,
+            0,
+            0,
+        ),
+        (start + PAGE_SIZE) as isize
+    );
+    assert_eq!(data[PAGE_SIZE], 0);
+    assert_eq!(mprotect(start, PAGE_SIZE, MmapProt::READ), 0);
+    assert_eq!(data[0], 0x5a);
+    assert_eq!(munmap(start, len), 0);
+    // 范围内有没有映射的页面
+    assert_eq!(
+        try_mprotect(start, PAGE_SIZE, MmapProt::READ),
+        Err(Errno::ENOMEM)
+    );
+}
+
+fn shared_anonymous() {
+    let start = mmap(
+        0,
+        PAGE_SIZE,
+        MmapProt::READ | MmapProt::WRITE,
+        MmapFlags::SHARED | MmapFlags::ANONYMOUS,
+        0,
+        0,
+    );
+    assert!(start > 0);
+    let value = unsafe { &mut *(start as *mut usize) };
+    let pid = fork();
+    if pid == 0 {
+        *value = 42;
+        user_lib::exit(0);
+    }
+    let mut exit_code: i32 = 0;
+    assert_eq!(wait(&mut exit_code), pid);
+    assert_eq!(exit_code, 0);
+    // 子进程的写入对父进程可见
+    assert_eq!(*value, 42);
+    assert_eq!(munmap(start as usize, PAGE_SIZE), 0);
+}
+
+fn file_backed() {
+    let name = "mmap_file\0";
+    let content = b"mmap test file content";
+    let fd = open(name, OpenFlags::CREATE | OpenFlags::WRONLY);
+    assert!(fd > 0);
+    write(fd as usize, content);
+    close(fd as usize);
+
+    // 只读打开的文件不能建立共享的可写映射
+    let fd = open(name, OpenFlags::RDONLY);
+    assert!(fd > 0);
+    assert_eq!(
+        mmap(
+            0,
+            PAGE_SIZE,
+            MmapProt::READ | MmapProt::WRITE,
+            MmapFlags::SHARED,
+            fd as usize,
+            0
+        ),
+        Errno::EACCES.into()
+    );
+    // 也不能通过 mprotect 变为可写
+    let start = mmap(
+        0,
+        PAGE_SIZE,
+        MmapProt::READ,
+        MmapFlags::SHARED,
+        fd as usize,
+        0,
+    );
+    assert!(start > 0);
+    assert_eq!(
+        mprotect(start as usize, PAGE_SIZE, MmapProt::READ | MmapProt::WRITE),
+        Errno::EACCES.into()
+    );
+    munmap(start as usize, PAGE_SIZE);
+    close(fd as usize);
+
+    let fd = open(name, OpenFlags::RDWR);
+    assert!(fd > 0);
+    let start = mmap(
+        0,
+        PAGE_SIZE,
+        MmapProt::READ | MmapProt::WRITE,
+        MmapFlags::SHARED,
+        fd as usize,
+        0,
+    );
+    close(fd as usize);
+    assert!(start > 0);
+    let data = unsafe { core::slice::from_raw_parts_mut(start as *mut u8, PAGE_SIZE) };
+    assert_eq!(&data[..content.len()], content);
+    // 超出文件的部分为0
+    assert!(data[content.len()..].iter().all(|b| *b == 0));
+    data[..4].copy_from_slice(b"MMAP");
+    assert_eq!(munmap(start as usize, PAGE_SIZE), 0);
+
+    // 共享映射的写入在解除映射时写回了文件
+    let fd = open(name, OpenFlags::RDONLY);
+    assert!(fd > 0);
+    let mut buffer = [0u8; 64];
+    let read_len = read(fd as usize, &mut buffer) as usize;
+    close(fd as usize);
+    assert_eq!(read_len, content.len());
+    assert_eq!(&buffer[..4], b"MMAP");
+    assert_eq!(&buffer[4..read_len], &content[4..]);
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    anonymous();
+    shared_anonymous();
+    file_backed();
+    println!("mmap_test passed!");
+    0
+}
@@ -0,0 +1,77 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    exit, mutex_create, mutex_lock, mutex_unlock, semaphore_create, semaphore_down, semaphore_up,
+    thread_create, waittid,
+};
+
+const BUFFER_SIZE: usize = 4;
+const PRODUCERS: usize = 4;
+const ITEMS_PER_PRODUCER: usize = 8;
+
+// 有界缓冲区: `EMPTY`是空位数, `FULL`是数据数, `MUTEX`保护缓冲区本身
+static mut BUFFER: [usize; BUFFER_SIZE] = [0; BUFFER_SIZE];
+static mut FRONT: usize = 0;
+static mut TAIL: usize = 0;
+static mut MUTEX: usize = 0;
+static mut EMPTY: usize = 0;
+static mut FULL: usize = 0;
+
+fn producer(id: usize) -> ! {
+    for i in 0..ITEMS_PER_PRODUCER {
+        unsafe {
+            semaphore_down(EMPTY);
+            mutex_lock(MUTEX);
+            BUFFER[TAIL] = id * ITEMS_PER_PRODUCER + i;
+            TAIL = (TAIL + 1) % BUFFER_SIZE;
+            mutex_unlock(MUTEX);
+            semaphore_up(FULL);
+        }
+    }
+    exit(0);
+    unreachable!()
+}
+
+fn consumer(_arg: usize) -> ! {
+    // 每个数据恰好被消费一次
+    let mut seen = 0u64;
+    for _ in 0..PRODUCERS * ITEMS_PER_PRODUCER {
+        unsafe {
+            semaphore_down(FULL);
+            mutex_lock(MUTEX);
+            let item = BUFFER[FRONT];
+            FRONT = (FRONT + 1) % BUFFER_SIZE;
+            mutex_unlock(MUTEX);
+            semaphore_up(EMPTY);
+            assert_eq!(seen & (1 << item), 0);
+            seen |= 1 << item;
+        }
+    }
+    let all = (1u64 << (PRODUCERS * ITEMS_PER_PRODUCER)) - 1;
+    exit((seen == all) as i32);
+    unreachable!()
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    unsafe {
+        MUTEX = mutex_create() as usize;
+        EMPTY = semaphore_create(BUFFER_SIZE) as usize;
+        FULL = semaphore_create(0) as usize;
+    }
+    let consumer_tid = thread_create(consumer as usize, 0);
+    let mut producer_tids = [0; PRODUCERS];
+    for (id, tid) in producer_tids.iter_mut().enumerate() {
+        *tid = thread_create(producer as usize, id);
+    }
+    for tid in producer_tids.iter() {
+        assert_eq!(waittid(*tid as usize), 0);
+    }
+    assert_eq!(waittid(consumer_tid as usize), 1);
+    println!("mpsc_sem passed!");
+    0
+}
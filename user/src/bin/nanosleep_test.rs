@@ -0,0 +1,51 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    exit, fork, get_time, kill, nanosleep, signal, sigreturn, sleep, waitpid, Errno, TimeSpec,
+    SIGUSR1,
+};
+
+fn on_usr1() {
+    sigreturn();
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    // 睡眠时间至少是请求的时间
+    let req = TimeSpec {
+        sec: 0,
+        nsec: 150_000_000,
+    };
+    let start = get_time();
+    assert_eq!(nanosleep(&req, None), 0);
+    assert!(get_time() - start >= 150);
+
+    let bad = TimeSpec {
+        sec: 0,
+        nsec: 1_000_000_000,
+    };
+    assert_eq!(nanosleep(&bad, None), Errno::EINVAL.into());
+
+    // 被信号打断时返回剩余的时间
+    let pid = fork();
+    if pid == 0 {
+        assert!(signal(SIGUSR1, on_usr1 as usize) >= 0);
+        let req = TimeSpec { sec: 2, nsec: 0 };
+        let mut rem = TimeSpec::default();
+        assert_eq!(nanosleep(&req, Some(&mut rem)), Errno::EINTR.into());
+        assert!(rem.sec < 2 && (rem.sec > 0 || rem.nsec > 0));
+        println!("interrupted, remaining {}.{:09}s", rem.sec, rem.nsec);
+        exit(0);
+    }
+    sleep(200);
+    assert_eq!(kill(pid as usize, SIGUSR1), 0);
+    let mut exit_code = 0;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, 0);
+    println!("nanosleep_test passed!");
+    0
+}
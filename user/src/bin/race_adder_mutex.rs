@@ -0,0 +1,52 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    exit, mutex_create, mutex_lock, mutex_unlock, thread_create, waittid, yield_, Errno,
+};
+
+const THREADS: usize = 4;
+const PER_THREAD: usize = 100;
+
+static mut A: usize = 0;
+static mut MUTEX_ID: usize = 0;
+
+fn adder(_arg: usize) -> ! {
+    for _ in 0..PER_THREAD {
+        let mutex_id = unsafe { MUTEX_ID };
+        assert_eq!(mutex_lock(mutex_id), 0);
+        // 在临界区中让出处理器, 没有互斥锁时一定会丢失更新
+        let t = unsafe { A };
+        yield_();
+        unsafe {
+            A = t + 1;
+        }
+        assert_eq!(mutex_unlock(mutex_id), 0);
+    }
+    exit(0);
+    unreachable!()
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let mutex_id = mutex_create();
+    assert!(mutex_id >= 0);
+    unsafe {
+        MUTEX_ID = mutex_id as usize;
+    }
+    assert_eq!(mutex_unlock(mutex_id as usize), Errno::EPERM.into());
+    assert_eq!(mutex_lock(mutex_id as usize + 1), Errno::EINVAL.into());
+    let mut tids = [0; THREADS];
+    for tid in tids.iter_mut() {
+        *tid = thread_create(adder as usize, 0);
+    }
+    for tid in tids.iter() {
+        assert_eq!(waittid(*tid as usize), 0);
+    }
+    assert_eq!(unsafe { A }, THREADS * PER_THREAD);
+    println!("race_adder_mutex passed!");
+    0
+}
@@ -0,0 +1,52 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{exit, fork, get_time, set_priority, waitpid, Errno};
+
+const RUN_MS: isize = 1000;
+const PRIORITIES: [isize; 5] = [5, 6, 7, 8, 10];
+
+/// 一直计数到`end`, 计数和得到的处理器时间成正比
+fn spin_until(end: isize) -> usize {
+    let mut count = 0usize;
+    while get_time() < end {
+        count += 1;
+    }
+    count
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    assert_eq!(set_priority(1), Errno::EINVAL.into());
+    assert_eq!(set_priority(-1), Errno::EINVAL.into());
+    assert_eq!(set_priority(16), 16);
+
+    // 所有子进程在同一时刻结束, 在 stride 调度下计数和优先级的比值应该接近
+    let end = get_time() + RUN_MS;
+    let mut pids = [0; PRIORITIES.len()];
+    for (i, prio) in PRIORITIES.iter().enumerate() {
+        let pid = fork();
+        if pid == 0 {
+            set_priority(*prio);
+            let count = spin_until(end);
+            println!(
+                "priority {:>2}: count = {:>10}, count / priority = {:>10}",
+                prio,
+                count,
+                count / *prio as usize
+            );
+            exit(0);
+        }
+        pids[i] = pid;
+    }
+    for pid in pids.iter() {
+        let mut exit_code = 0;
+        assert_eq!(waitpid(*pid as usize, &mut exit_code), *pid);
+        assert_eq!(exit_code, 0);
+    }
+    println!("sched_fairness passed!");
+    0
+}
@@ -0,0 +1,53 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{exit, fork, get_time, getpid, setpriority, waitpid, Errno, PRIO_PROCESS};
+
+const RUN_MS: isize = 1000;
+const NICES: [isize; 4] = [0, 3, 6, 10];
+
+/// 一直计数到`end`, 计数和得到的处理器时间成正比
+fn spin_until(end: isize) -> usize {
+    let mut count = 0usize;
+    while get_time() < end {
+        count += 1;
+    }
+    count
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    assert_eq!(setpriority(PRIO_PROCESS + 1, 0, 0), Errno::EINVAL.into());
+    assert_eq!(
+        setpriority(PRIO_PROCESS, usize::MAX >> 1, 0),
+        Errno::ESRCH.into()
+    );
+    assert_eq!(setpriority(PRIO_PROCESS, getpid() as usize, 0), 0);
+    // 超出范围的值被截断
+    assert_eq!(setpriority(PRIO_PROCESS, 0, -100), 0);
+    assert_eq!(setpriority(PRIO_PROCESS, 0, 0), 0);
+
+    // 在完全公平调度下 nice 值每增加 1, 得到的处理器时间约减少到 1/1.25
+    let end = get_time() + RUN_MS;
+    let mut pids = [0; NICES.len()];
+    for (i, nice) in NICES.iter().enumerate() {
+        let pid = fork();
+        if pid == 0 {
+            assert_eq!(setpriority(PRIO_PROCESS, 0, *nice), 0);
+            let count = spin_until(end);
+            println!("nice {:>2}: count = {:>10}", nice, count);
+            exit(0);
+        }
+        pids[i] = pid;
+    }
+    for pid in pids.iter() {
+        let mut exit_code = 0;
+        assert_eq!(waitpid(*pid as usize, &mut exit_code), *pid);
+        assert_eq!(exit_code, 0);
+    }
+    println!("sched_nice passed!");
+    0
+}
@@ -0,0 +1,83 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    exit, fork, sigaction, sigaltstack, waitpid, Errno, SignalAction, SignalInfo, SignalStack,
+    UContext, MINSIGSTKSZ, SA_ONSTACK, SA_SIGINFO, SIGSEGV, SS_DISABLE, SS_ONSTACK,
+};
+
+const ALT_STACK_SIZE: usize = 8192;
+static mut ALT_STACK: [u8; ALT_STACK_SIZE] = [0; ALT_STACK_SIZE];
+
+fn alt_stack() -> SignalStack {
+    SignalStack {
+        sp: unsafe { ALT_STACK.as_ptr() as usize },
+        flags: 0,
+        size: ALT_STACK_SIZE,
+    }
+}
+
+/// 在备用信号栈上处理用户栈溢出
+extern "C" fn on_segv(_signo: i32, _info: &SignalInfo, _ucontext: &mut UContext) {
+    let local = 0u8;
+    let sp = &local as *const u8 as usize;
+    let stack = alt_stack();
+    let mut old = SignalStack::default();
+    let ok = sp > stack.sp
+        && sp <= stack.sp + stack.size
+        && sigaltstack(None, Some(&mut old)) == 0
+        && old.flags == SS_ONSTACK
+        && sigaltstack(Some(&stack), None) == Errno::EPERM.into();
+    exit(if ok { 0 } else { 1 });
+}
+
+#[allow(unconditional_recursion)]
+fn overflow(depth: usize) -> usize {
+    let mut frame = [0u8; 256];
+    unsafe {
+        (&mut frame[0] as *mut u8).write_volatile(depth as u8);
+    }
+    overflow(depth + 1) + frame[0] as usize
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let mut old = SignalStack::default();
+    assert_eq!(sigaltstack(None, Some(&mut old)), 0);
+    assert_eq!(old.flags, SS_DISABLE);
+    let small = SignalStack {
+        size: MINSIGSTKSZ - 1,
+        ..alt_stack()
+    };
+    assert_eq!(sigaltstack(Some(&small), None), Errno::ENOMEM.into());
+    let bad_flags = SignalStack {
+        flags: SS_ONSTACK,
+        ..alt_stack()
+    };
+    assert_eq!(sigaltstack(Some(&bad_flags), None), Errno::EINVAL.into());
+
+    let pid = fork();
+    if pid == 0 {
+        assert_eq!(sigaltstack(Some(&alt_stack()), None), 0);
+        assert_eq!(sigaltstack(None, Some(&mut old)), 0);
+        assert_eq!(old.sp, alt_stack().sp);
+        assert_eq!(old.flags, 0);
+        let action = SignalAction {
+            handler: on_segv as usize,
+            flags: SA_SIGINFO | SA_ONSTACK,
+            ..SignalAction::default()
+        };
+        let old_action = SignalAction::default();
+        assert_eq!(sigaction(SIGSEGV, &action, &old_action), 0);
+        overflow(0);
+        exit(2);
+    }
+    let mut exit_code = 0;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, 0);
+    println!("sig_altstack passed!");
+    0
+}
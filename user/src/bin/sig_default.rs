@@ -0,0 +1,121 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    exit, fork, getpid, kill, sigaction, sigprocmask, sleep, waitpid_status, wcoredump,
+    wexitstatus, wifexited, wifsignaled, wtermsig, SignalAction, SIGCHLD, SIGCONT, SIGKILL,
+    SIGSEGV, SIGTERM, SIGTSTP, SIGURG, SIGUSR1, SIGWINCH, SIG_DFL, SIG_IGN, WNOHANG,
+};
+
+const BAD_ADDR: usize = 0x10;
+
+fn set_handler(signum: i32, handler: usize) -> usize {
+    let action = SignalAction {
+        handler,
+        ..SignalAction::default()
+    };
+    let old = SignalAction::default();
+    assert_eq!(sigaction(signum, &action, &old), 0);
+    old.handler
+}
+
+/// 在子进程中执行`f`, 返回子进程的 wait status
+fn run_child(f: fn()) -> i32 {
+    let pid = fork();
+    if pid == 0 {
+        f();
+        exit(0);
+    }
+    let mut status = 0;
+    assert_eq!(waitpid_status(pid, &mut status, 0), pid);
+    status
+}
+
+fn raise(signum: i32) {
+    assert_eq!(kill(getpid() as usize, signum), 0);
+}
+
+fn segfault() {
+    unsafe {
+        (BAD_ADDR as *mut u8).write_volatile(0);
+    }
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    // 默认终止进程
+    let status = run_child(|| raise(SIGTERM));
+    assert!(wifsignaled(status) && !wcoredump(status));
+    assert_eq!(wtermsig(status), SIGTERM);
+
+    // 默认终止进程并记录 core dump
+    let status = run_child(segfault);
+    assert!(wifsignaled(status) && wcoredump(status));
+    assert_eq!(wtermsig(status), SIGSEGV);
+
+    // 默认忽略
+    let status = run_child(|| {
+        raise(SIGCHLD);
+        raise(SIGURG);
+        raise(SIGWINCH);
+    });
+    assert!(wifexited(status) && wexitstatus(status) == 0);
+
+    // SIG_IGN 忽略信号, 旧的处理函数被返回
+    let status = run_child(|| {
+        assert_eq!(set_handler(SIGUSR1, SIG_IGN), SIG_DFL);
+        raise(SIGUSR1);
+        assert_eq!(set_handler(SIGUSR1, SIG_DFL), SIG_IGN);
+    });
+    assert!(wifexited(status) && wexitstatus(status) == 0);
+
+    // 阻塞中的信号在设置为 SIG_IGN 时被丢弃
+    let status = run_child(|| {
+        sigprocmask(1 << SIGUSR1);
+        raise(SIGUSR1);
+        set_handler(SIGUSR1, SIG_IGN);
+        sigprocmask(0);
+    });
+    assert!(wifexited(status) && wexitstatus(status) == 0);
+
+    // 异常引起的信号不能被忽略
+    let status = run_child(|| {
+        set_handler(SIGSEGV, SIG_IGN);
+        segfault();
+    });
+    assert!(wifsignaled(status) && wcoredump(status));
+    assert_eq!(wtermsig(status), SIGSEGV);
+
+    // 暂停之后由 SIGCONT 继续
+    let pid = fork();
+    if pid == 0 {
+        raise(SIGTSTP);
+        exit(7);
+    }
+    let mut status = 0;
+    sleep(100);
+    assert_eq!(waitpid_status(pid, &mut status, WNOHANG), 0);
+    assert_eq!(kill(pid as usize, SIGCONT), 0);
+    assert_eq!(waitpid_status(pid, &mut status, 0), pid);
+    assert!(wifexited(status) && wexitstatus(status) == 7);
+
+    // SIGKILL 不能被阻塞
+    let pid = fork();
+    if pid == 0 {
+        sigprocmask(1 << SIGKILL);
+        loop {
+            sleep(10);
+        }
+    }
+    sleep(50);
+    assert_eq!(kill(pid as usize, SIGKILL), 0);
+    assert_eq!(waitpid_status(pid, &mut status, 0), pid);
+    assert!(wifsignaled(status) && !wcoredump(status));
+    assert_eq!(wtermsig(status), SIGKILL);
+
+    println!("sig_default passed!");
+    0
+}
@@ -0,0 +1,104 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use core::sync::atomic::{AtomicI32, AtomicUsize, Ordering};
+use user_lib::{
+    exit, fork, getpid, kill, setitimer, sigaction, sigreturn, waitpid, Errno, ITimerVal,
+    SignalAction, SignalInfo, TimeVal, UContext, ITIMER_PROF, SA_SIGINFO, SEGV_MAPERR, SIGPROF,
+    SIGSEGV, SIGUSR1, SIGUSR2, SI_USER,
+};
+
+static SIGNO: AtomicI32 = AtomicI32::new(0);
+static CODE: AtomicI32 = AtomicI32::new(-1);
+static PID: AtomicUsize = AtomicUsize::new(0);
+static PROF: AtomicUsize = AtomicUsize::new(0);
+
+const BAD_ADDR: usize = 0x10;
+
+extern "C" fn on_usr1(signo: i32, info: &SignalInfo, _ucontext: &mut UContext) {
+    SIGNO.store(signo, Ordering::SeqCst);
+    CODE.store(info.code, Ordering::SeqCst);
+    PID.store(info.pid as usize, Ordering::SeqCst);
+}
+
+/// 修改被打断时的 a0, 也就是`kill`的返回值
+extern "C" fn on_usr2(_signo: i32, _info: &SignalInfo, ucontext: &mut UContext) {
+    ucontext.gregs[10] = 42;
+}
+
+fn on_prof(_signo: i32) {
+    PROF.fetch_add(1, Ordering::SeqCst);
+}
+
+extern "C" fn on_segv(_signo: i32, info: &SignalInfo, _ucontext: &mut UContext) {
+    exit(if info.addr == BAD_ADDR && info.code == SEGV_MAPERR {
+        0
+    } else {
+        1
+    });
+}
+
+fn set_action(signum: i32, handler: usize, flags: u32) {
+    let action = SignalAction {
+        handler,
+        flags,
+        ..SignalAction::default()
+    };
+    let old = SignalAction::default();
+    assert_eq!(sigaction(signum, &action, &old), 0);
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    assert_eq!(sigreturn(), Errno::EINVAL.into());
+
+    // 处理函数直接返回, 由跳板调用 sigreturn
+    set_action(SIGUSR1, on_usr1 as usize, SA_SIGINFO);
+    assert_eq!(kill(getpid() as usize, SIGUSR1), 0);
+    assert_eq!(SIGNO.load(Ordering::SeqCst), SIGUSR1);
+    assert_eq!(CODE.load(Ordering::SeqCst), SI_USER);
+    assert_eq!(PID.load(Ordering::SeqCst), getpid() as usize);
+
+    // sigreturn 恢复的是信号帧中的上下文
+    set_action(SIGUSR2, on_usr2 as usize, SA_SIGINFO);
+    assert_eq!(kill(getpid() as usize, SIGUSR2), 42);
+
+    // 随时到达的信号不会破坏寄存器
+    set_action(SIGPROF, on_prof as usize, 0);
+    let period = TimeVal {
+        sec: 0,
+        usec: 10_000,
+    };
+    let prof = ITimerVal {
+        interval: period,
+        value: period,
+    };
+    assert_eq!(setitimer(ITIMER_PROF, &prof, None), 0);
+    let mut a: usize = 1;
+    let mut b: usize = 4;
+    while PROF.load(Ordering::Relaxed) < 5 {
+        a = a.wrapping_mul(5).wrapping_add(7);
+        b = b.wrapping_mul(5).wrapping_add(17);
+        assert_eq!(b, a.wrapping_mul(3).wrapping_add(1));
+    }
+    assert_eq!(setitimer(ITIMER_PROF, &ITimerVal::default(), None), 0);
+
+    // SIGSEGV 带有出错的地址
+    let pid = fork();
+    if pid == 0 {
+        set_action(SIGSEGV, on_segv as usize, SA_SIGINFO);
+        unsafe {
+            (BAD_ADDR as *mut u8).write_volatile(0);
+        }
+        exit(2);
+    }
+    let mut exit_code = 0;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, 0);
+
+    println!("sig_frame passed!");
+    0
+}
@@ -0,0 +1,114 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use core::sync::atomic::{AtomicI32, AtomicUsize, Ordering};
+use user_lib::{
+    exit, fork, getpid, kill, sigaction, sigprocmask, sigqueue, waitpid_status, wifexited,
+    wifsignaled, wtermsig, Errno, SignalAction, SignalInfo, UContext, SA_SIGINFO, SIGQUEUE_MAX,
+    SIGRTMAX, SIGRTMIN, SIGUSR1, SI_QUEUE,
+};
+
+const LOG_SIZE: usize = 8;
+static LOG_SIGNO: [AtomicI32; LOG_SIZE] = [const { AtomicI32::new(0) }; LOG_SIZE];
+static LOG_VALUE: [AtomicUsize; LOG_SIZE] = [const { AtomicUsize::new(0) }; LOG_SIZE];
+static LOG_LEN: AtomicUsize = AtomicUsize::new(0);
+
+extern "C" fn on_signal(signo: i32, info: &SignalInfo, _ucontext: &mut UContext) {
+    let i = LOG_LEN.fetch_add(1, Ordering::SeqCst);
+    if i < LOG_SIZE {
+        LOG_SIGNO[i].store(signo, Ordering::SeqCst);
+        LOG_VALUE[i].store(
+            if info.code == SI_QUEUE { info.value } else { 0 },
+            Ordering::SeqCst,
+        );
+    }
+}
+
+fn set_handler(signum: i32) {
+    let action = SignalAction {
+        handler: on_signal as usize,
+        flags: SA_SIGINFO,
+        ..SignalAction::default()
+    };
+    let old = SignalAction::default();
+    assert_eq!(sigaction(signum, &action, &old), 0);
+}
+
+fn mask_of(signals: &[i32]) -> u64 {
+    signals.iter().fold(0, |mask, signum| mask | 1 << signum)
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let pid = getpid() as usize;
+    assert_eq!(sigqueue(pid, SIGRTMAX + 1, 0), Errno::EINVAL.into());
+    assert_eq!(sigqueue(usize::MAX, SIGRTMIN, 0), Errno::ESRCH.into());
+    assert_eq!(kill(pid, SIGRTMAX + 1), Errno::EINVAL.into());
+    assert_eq!(kill(pid, -1), Errno::EINVAL.into());
+    // 信号 0 只检查进程是否存在
+    assert_eq!(kill(pid, 0), 0);
+    assert_eq!(sigqueue(pid, 0, 0), 0);
+    assert_eq!(kill(usize::MAX, 0), Errno::ESRCH.into());
+    assert_eq!(LOG_LEN.load(Ordering::SeqCst), 0);
+
+    // 阻塞时产生的信号在解除阻塞之后按顺序处理
+    let signals = [SIGUSR1, SIGRTMIN, SIGRTMIN + 1];
+    for signum in signals {
+        set_handler(signum);
+    }
+    sigprocmask(mask_of(&signals));
+    assert_eq!(sigqueue(pid, SIGRTMIN + 1, 100), 0);
+    for value in 1..=3 {
+        assert_eq!(sigqueue(pid, SIGRTMIN, value), 0);
+    }
+    // 普通信号合并为一个
+    assert_eq!(kill(pid, SIGUSR1), 0);
+    assert_eq!(kill(pid, SIGUSR1), 0);
+    assert_eq!(LOG_LEN.load(Ordering::SeqCst), 0);
+    sigprocmask(0);
+    let expected = [
+        (SIGUSR1, 0),
+        (SIGRTMIN, 1),
+        (SIGRTMIN, 2),
+        (SIGRTMIN, 3),
+        (SIGRTMIN + 1, 100),
+    ];
+    assert_eq!(LOG_LEN.load(Ordering::SeqCst), expected.len());
+    for (i, (signo, value)) in expected.iter().enumerate() {
+        assert_eq!(LOG_SIGNO[i].load(Ordering::SeqCst), *signo);
+        assert_eq!(LOG_VALUE[i].load(Ordering::SeqCst), *value);
+    }
+
+    // 排队的实时信号有数量上限
+    let child = fork();
+    if child == 0 {
+        sigprocmask(mask_of(&[SIGRTMIN]));
+        for value in 0..SIGQUEUE_MAX {
+            assert_eq!(sigqueue(getpid() as usize, SIGRTMIN, value), 0);
+        }
+        assert_eq!(
+            sigqueue(getpid() as usize, SIGRTMIN, 0),
+            Errno::EAGAIN.into()
+        );
+        exit(0);
+    }
+    let mut status = 0;
+    assert_eq!(waitpid_status(child, &mut status, 0), child);
+    assert!(wifexited(status));
+
+    // 实时信号默认终止进程
+    let child = fork();
+    if child == 0 {
+        sigqueue(getpid() as usize, SIGRTMAX, 0);
+        exit(0);
+    }
+    assert_eq!(waitpid_status(child, &mut status, 0), child);
+    assert!(wifsignaled(status));
+    assert_eq!(wtermsig(status), SIGRTMAX);
+
+    println!("sig_queue passed!");
+    0
+}
@@ -4,7 +4,7 @@
 #[macro_use]
 extern crate user_lib;
 
-use user_lib::{exit, fork, kill, signal, sigreturn, sleep, waitpid};
+use user_lib::{exit, fork, kill, signal, sigreturn, sleep, waitpid, SIGUSR1};
 
 fn func() {
     println!("user_sig_test succsess");
@@ -25,7 +25,7 @@ pub fn main() -> i32 {
     } else if pid > 0 {
         println!("signal_simple2: parent kill child");
         sleep(500);
-        if kill(pid as usize, 1 << 10) < 0 {
+        if kill(pid as usize, SIGUSR1) < 0 {
             println!("Kill failed!");
             exit(1);
         }
@@ -23,7 +23,7 @@ fn func3() {
 }
 
 fn user_sig_test_failsignum() {
-    if signal(50, func as usize) >= 0 {
+    if signal(SIGRTMAX + 1, func as usize) >= 0 {
         panic!("Wrong sigaction but success!");
     }
 }
@@ -72,11 +72,26 @@ fn user_sig_test_restore() {
         println!("Restore failed!");
         exit(-1);
     }
+
+    // action 和 old_action 是同一个位置: 设置新的动作, 并取回原来的动作
+    let mut same = new;
+    let same_ptr = &mut same as *mut SignalAction as *const SignalAction;
+    if sigaction(SIGUSR1, same_ptr, same_ptr) < 0 {
+        panic!("Sigaction failed!");
+    }
+    if sigaction(SIGUSR1, &old, &old2) < 0 {
+        panic!("Sigaction failed!");
+    }
+    if same.handler != old.handler || old2.handler != new.handler {
+        println!("Swap failed!");
+        exit(-1);
+    }
 }
 
 fn kernel_sig_test_ignore() {
-    sigprocmask(SignalFlags::SIGSTOP.bits() as u32);
-    if kill(getpid() as usize, SignalFlags::SIGSTOP.bits()) < 0 {
+    // 被阻塞的信号一直等待, 不会终止进程
+    sigprocmask(SignalFlags::SIGUSR1.bits());
+    if kill(getpid() as usize, SIGUSR1) < 0 {
         println!("kill faild\n");
         exit(-1);
     }
@@ -85,12 +100,12 @@ fn kernel_sig_test_ignore() {
 fn kernel_sig_test_stop_cont() {
     let pid = fork();
     if pid == 0 {
-        kill(getpid() as usize, SignalFlags::SIGSTOP.bits());
+        kill(getpid() as usize, SIGSTOP);
         sleep(1000);
         exit(-1);
     } else {
         sleep(5000);
-        kill(pid as usize, SignalFlags::SIGCONT.bits());
+        kill(pid as usize, SIGCONT);
         let mut exit_code = 0;
         wait(&mut exit_code);
     }
@@ -129,12 +144,12 @@ fn final_sig_test() {
         }
     } else {
         sleep(1000);
-        if kill(pid as usize, 1 << 14) < 0 {
+        if kill(pid as usize, SIGALRM) < 0 {
             println!("Kill failed!");
             exit(-1);
         }
         sleep(1000);
-        kill(pid as usize, SignalFlags::SIGKILL.bits());
+        kill(pid as usize, SIGKILL);
     }
 }
 
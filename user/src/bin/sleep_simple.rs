@@ -11,8 +11,9 @@ pub fn main() -> i32 {
     println!("into sleep test!");
     let start = get_time();
     println!("current time_msec = {}", start);
-    sleep(100);
+    assert_eq!(sleep(100), 0);
     let end = get_time();
+    assert!(end - start >= 100);
     println!(
         "time_msec = {} after sleeping 100 ticks, delta = {}ms!",
         end,
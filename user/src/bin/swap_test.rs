@@ -0,0 +1,64 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{exit, fork, mmap, munmap, waitpid, MmapFlags, MmapProt};
+
+const PAGE_SIZE: usize = 4096;
+/// 内核管理的物理内存只有 16MiB
+const CHILD_LEN: usize = 18 * 1024 * 1024;
+const PARENT_LEN: usize = 4 * 1024 * 1024;
+
+/// 映射`len`字节的匿名内存, 每个页面写入与页号和`seed`有关的数据
+fn fill(len: usize, seed: usize) -> &'static mut [usize] {
+    let start = mmap(
+        0,
+        len,
+        MmapProt::READ | MmapProt::WRITE,
+        MmapFlags::PRIVATE | MmapFlags::ANONYMOUS,
+        0,
+        0,
+    );
+    assert!(start > 0);
+    let words = PAGE_SIZE / core::mem::size_of::<usize>();
+    let data =
+        unsafe { core::slice::from_raw_parts_mut(start as *mut usize, len / PAGE_SIZE * words) };
+    for (page, chunk) in data.chunks_mut(words).enumerate() {
+        chunk[0] = page ^ seed;
+        chunk[words - 1] = !page ^ seed;
+    }
+    data
+}
+
+/// 检查[`fill`]写入的数据
+fn check(data: &[usize], seed: usize) {
+    let words = PAGE_SIZE / core::mem::size_of::<usize>();
+    for (page, chunk) in data.chunks(words).enumerate() {
+        assert_eq!(chunk[0], page ^ seed);
+        assert_eq!(chunk[words - 1], !page ^ seed);
+    }
+}
+
+/// 使用的内存超过物理内存, 多出的页面被换出到交换区
+#[no_mangle]
+pub fn main() -> i32 {
+    let parent = fill(PARENT_LEN, 0x5a5a);
+    let pid = fork();
+    if pid == 0 {
+        // 不再与父进程共享, 父进程的页面也要被换出才能放下
+        munmap(parent.as_ptr() as usize, PARENT_LEN);
+        let data = fill(CHILD_LEN, 0xa5a5);
+        check(data, 0xa5a5);
+        munmap(data.as_ptr() as usize, CHILD_LEN);
+        exit(0);
+    }
+    let mut exit_code = 0;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, 0);
+    check(parent, 0x5a5a);
+    munmap(parent.as_ptr() as usize, PARENT_LEN);
+    println!("swap_test passed!");
+    0
+}
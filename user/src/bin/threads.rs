@@ -0,0 +1,110 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+use user_lib::{exec, exit, fork, gettid, sleep, thread_create, waitpid, waittid, yield_, Errno};
+
+const THREADS: usize = 4;
+const ROUNDS: usize = 100;
+
+static COUNTER: AtomicUsize = AtomicUsize::new(0);
+static TIDS: [AtomicUsize; THREADS] = [
+    AtomicUsize::new(0),
+    AtomicUsize::new(0),
+    AtomicUsize::new(0),
+    AtomicUsize::new(0),
+];
+
+fn worker(arg: usize) -> ! {
+    TIDS[arg].store(gettid() as usize, Ordering::SeqCst);
+    for _ in 0..ROUNDS {
+        COUNTER.fetch_add(1, Ordering::SeqCst);
+        yield_();
+    }
+    exit(10 + arg as i32);
+    unreachable!()
+}
+
+fn quick_exit(_arg: usize) -> ! {
+    exit(0);
+    unreachable!()
+}
+
+fn fork_in_thread(_arg: usize) -> ! {
+    let local = core::hint::black_box(42);
+    let pid = fork();
+    if pid == 0 {
+        // 调用 fork 的线程成为子进程的主线程, 沿用原来的用户栈
+        assert_eq!(gettid(), 0);
+        assert_eq!(core::hint::black_box(local), 42);
+        let tid = thread_create(quick_exit as usize, 0);
+        assert!(tid > 0);
+        assert_eq!(waittid(tid as usize), 0);
+        exit(8);
+    }
+    let mut exit_code = 0;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    exit(exit_code);
+    unreachable!()
+}
+
+fn spin(_arg: usize) -> ! {
+    loop {
+        core::hint::spin_loop();
+    }
+}
+
+fn exec_in_thread(_arg: usize) -> ! {
+    exec("hello_world\0", &[core::ptr::null::<u8>()]);
+    exit(2);
+    unreachable!()
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    assert_eq!(gettid(), 0);
+    // 线程共享地址空间, 各自有独立的用户栈
+    let mut tids = [0; THREADS];
+    for (i, tid) in tids.iter_mut().enumerate() {
+        *tid = thread_create(worker as usize, i);
+        assert!(*tid > 0);
+    }
+    for (i, tid) in tids.iter().enumerate() {
+        assert_eq!(waittid(*tid as usize), 10 + i as isize);
+        assert_eq!(TIDS[i].load(Ordering::SeqCst), *tid as usize);
+    }
+    assert_eq!(COUNTER.load(Ordering::SeqCst), THREADS * ROUNDS);
+    // 已经被回收的线程和自己都不能等待
+    assert_eq!(waittid(tids[0] as usize), Errno::ESRCH.into());
+    assert_eq!(waittid(0), Errno::EDEADLK.into());
+    // 其它线程也能 fork
+    let tid = thread_create(fork_in_thread as usize, 0);
+    assert_eq!(waittid(tid as usize), 8);
+    // 子进程中只有调用 fork 的线程
+    let tid = thread_create(quick_exit as usize, 0);
+    sleep(10);
+    let pid = fork();
+    if pid == 0 {
+        assert_eq!(waittid(tid as usize), Errno::ESRCH.into());
+        exit(7);
+    }
+    let mut exit_code = 0;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, 7);
+    assert_eq!(waittid(tid as usize), 0);
+    // exec 终止其它线程, 包括正在运行的和阻塞的
+    let pid = fork();
+    if pid == 0 {
+        thread_create(spin as usize, 0);
+        let tid = thread_create(exec_in_thread as usize, 0);
+        waittid(tid as usize);
+        exit(1);
+    }
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, 0);
+    println!("threads passed!");
+    0
+}
@@ -0,0 +1,26 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use core::arch::asm;
+use user_lib::Errno;
+
+/// 内核不认识的系统调用号
+const SYSCALL_UNKNOWN: usize = 9999;
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let mut ret: isize;
+    unsafe {
+        asm!(
+        "ecall",
+        inlateout("x10") 0usize => ret,
+        in("x17") SYSCALL_UNKNOWN
+        );
+    }
+    assert_eq!(Errno::result(ret), Err(Errno::ENOSYS));
+    println!("unknown_syscall passed!");
+    0
+}
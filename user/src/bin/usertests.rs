@@ -10,30 +10,55 @@ extern crate user_lib;
 // item of TESTS : app_name(argv_0), argv_1, argv_2, argv_3, exit_code
 static SUCC_TESTS: &[(&str, &str, &str, &str, i32)] = &[
     ("filetest_simple\0", "\0", "\0", "\0", 0),
+    ("bad_pointer\0", "\0", "\0", "\0", 0),
     ("cat\0", "filea\0", "\0", "\0", 0),
     ("cmdline_args\0", "1\0", "2\0", "3\0", 0),
+    ("condvar_test\0", "\0", "\0", "\0", 0),
+    ("cow_test\0", "\0", "\0", "\0", 0),
+    ("deadlock_test\0", "\0", "\0", "\0", 0),
+    ("errno_test\0", "\0", "\0", "\0", 0),
     ("exit\0", "\0", "\0", "\0", 0),
     ("fantastic_text\0", "\0", "\0", "\0", 0),
     ("forktest_simple\0", "\0", "\0", "\0", 0),
     ("forktest\0", "\0", "\0", "\0", 0),
     ("forktest2\0", "\0", "\0", "\0", 0),
     ("forktree\0", "\0", "\0", "\0", 0),
+    ("futex_test\0", "\0", "\0", "\0", 0),
+    ("heap_grow\0", "\0", "\0", "\0", 0),
     ("hello_world\0", "\0", "\0", "\0", 0),
     ("huge_write\0", "\0", "\0", "\0", 0),
+    ("idle_ticks\0", "\0", "\0", "\0", 0),
+    ("itimer_test\0", "\0", "\0", "\0", 0),
+    ("lazy_test\0", "\0", "\0", "\0", 0),
     ("matrix\0", "\0", "\0", "\0", 0),
+    ("mmap_test\0", "\0", "\0", "\0", 0),
+    ("mpsc_sem\0", "\0", "\0", "\0", 0),
+    ("nanosleep_test\0", "\0", "\0", "\0", 0),
     ("pipe_large_test\0", "\0", "\0", "\0", 0),
     ("pipetest\0", "\0", "\0", "\0", 0),
+    ("race_adder_mutex\0", "\0", "\0", "\0", 0),
     ("run_pipe_test\0", "\0", "\0", "\0", 0),
+    ("sched_fairness\0", "\0", "\0", "\0", 0),
+    ("sched_nice\0", "\0", "\0", "\0", 0),
     ("sleep_simple\0", "\0", "\0", "\0", 0),
     ("sleep\0", "\0", "\0", "\0", 0),
+    ("sig_altstack\0", "\0", "\0", "\0", 0),
+    ("sig_default\0", "\0", "\0", "\0", 0),
+    ("sig_frame\0", "\0", "\0", "\0", 0),
+    ("sig_queue\0", "\0", "\0", "\0", 0),
     ("sig_simple\0", "\0", "\0", "\0", 0),
     ("sig_simple2\0", "\0", "\0", "\0", 0),
     ("sig_tests\0", "\0", "\0", "\0", 0),
+    ("swap_test\0", "\0", "\0", "\0", 0),
+    ("threads\0", "\0", "\0", "\0", 0),
+    ("unknown_syscall\0", "\0", "\0", "\0", 0),
+    ("wait_test\0", "\0", "\0", "\0", 0),
     ("yield\0", "\0", "\0", "\0", 0),
 ];
 
 static FAIL_TESTS: &[(&str, &str, &str, &str, i32)] = &[
     ("stack_overflow\0", "\0", "\0", "\0", -11),
+    ("mmap_protect\0", "\0", "\0", "\0", -11),
     ("priv_csr\0", "\0", "\0", "\0", -4),
     ("priv_inst\0", "\0", "\0", "\0", -4),
     ("store_fault\0", "\0", "\0", "\0", -11),
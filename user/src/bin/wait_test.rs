@@ -0,0 +1,50 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    exit, fork, kill, sleep, waitpid_status, wexitstatus, wifexited, wifsignaled, wtermsig,
+    SIGKILL, WNOHANG,
+};
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let mut status = 0;
+    // 正常退出
+    let pid = fork();
+    if pid == 0 {
+        sleep(50);
+        exit(42);
+    }
+    assert_eq!(waitpid_status(pid, &mut status, WNOHANG), 0);
+    assert_eq!(waitpid_status(pid, &mut status, 0), pid);
+    assert!(wifexited(status) && !wifsignaled(status));
+    assert_eq!(wexitstatus(status), 42);
+    // 被信号杀死
+    let pid = fork();
+    if pid == 0 {
+        loop {
+            sleep(10);
+        }
+    }
+    assert_eq!(kill(pid as usize, SIGKILL), 0);
+    assert_eq!(waitpid_status(-1, &mut status, 0), pid);
+    assert!(wifsignaled(status) && !wifexited(status));
+    assert_eq!(wtermsig(status), SIGKILL);
+    // 阻塞等待任意一个子进程
+    for i in 0..4 {
+        if fork() == 0 {
+            sleep(10 * i);
+            exit(i as i32);
+        }
+    }
+    let mut exited = 0;
+    while waitpid_status(-1, &mut status, 0) > 0 {
+        exited |= 1 << wexitstatus(status);
+    }
+    assert_eq!(exited, 0b1111);
+    println!("wait_test passed!");
+    0
+}
@@ -0,0 +1,99 @@
+//! 系统调用的错误码, 与内核及 Linux (RISC-V) 的编号一致
+
+use core::fmt;
+
+macro_rules! errno {
+    ($($name: ident = $code: expr, $msg: expr;)*) => {
+        /// 系统调用的错误码, 系统调用出错时返回它的相反数
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        pub enum Errno {
+            $(
+                #[doc = $msg]
+                $name,
+            )*
+            /// 这里没有列出的错误码, 保留原始的值
+            Unknown(isize),
+        }
+
+        impl Errno {
+            /// 由错误码构造, 没有列出的错误码构造为[`Errno::Unknown`]
+            pub fn from_code(code: isize) -> Self {
+                match code {
+                    $($code => Self::$name,)*
+                    _ => Self::Unknown(code),
+                }
+            }
+
+            /// 错误码的值
+            pub fn code(self) -> isize {
+                match self {
+                    $(Self::$name => $code,)*
+                    Self::Unknown(code) => code,
+                }
+            }
+        }
+
+        /// 错误码对应的描述, 与 C 库的`strerror`相同
+        pub fn strerror(errno: Errno) -> &'static str {
+            match errno {
+                $(Errno::$name => $msg,)*
+                Errno::Unknown(_) => "Unknown error",
+            }
+        }
+    };
+}
+
+errno! {
+    EPERM = 1, "Operation not permitted";
+    ENOENT = 2, "No such file or directory";
+    ESRCH = 3, "No such process";
+    EINTR = 4, "Interrupted system call";
+    EIO = 5, "I/O error";
+    E2BIG = 7, "Argument list too long";
+    ENOEXEC = 8, "Exec format error";
+    EBADF = 9, "Bad file number";
+    ECHILD = 10, "No child processes";
+    EAGAIN = 11, "Try again";
+    ENOMEM = 12, "Out of memory";
+    EACCES = 13, "Permission denied";
+    EFAULT = 14, "Bad address";
+    EBUSY = 16, "Device or resource busy";
+    EEXIST = 17, "File exists";
+    ENODEV = 19, "No such device";
+    ENOTDIR = 20, "Not a directory";
+    EISDIR = 21, "Is a directory";
+    EINVAL = 22, "Invalid argument";
+    EMFILE = 24, "Too many open files";
+    ESPIPE = 29, "Illegal seek";
+    EPIPE = 32, "Broken pipe";
+    EDEADLK = 35, "Resource deadlock would occur";
+    ENAMETOOLONG = 36, "File name too long";
+    ENOSYS = 38, "Invalid system call number";
+    ETIMEDOUT = 110, "Connection timed out";
+}
+
+impl Errno {
+    /// 把系统调用的返回值转换为`Result`, 负数视为错误码
+    pub fn result(ret: isize) -> Result<usize, Errno> {
+        if ret >= 0 {
+            Ok(ret as usize)
+        } else {
+            Err(Self::from_code(-ret))
+        }
+    }
+}
+
+impl From<Errno> for isize {
+    fn from(errno: Errno) -> Self {
+        -errno.code()
+    }
+}
+
+impl fmt::Display for Errno {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unknown(code) => write!(f, "{}: {}", strerror(*self), code),
+            _ => write!(f, "{:?}: {}", self, strerror(*self)),
+        }
+    }
+}
@@ -0,0 +1,58 @@
+//! 基于 futex 的用户态锁: 没有竞争时完全在用户态完成, 只在竞争时进入内核
+
+use crate::syscall::sys_futex;
+use core::sync::atomic::{AtomicU32, Ordering};
+
+const FUTEX_WAIT: usize = 0;
+const FUTEX_WAKE: usize = 1;
+
+/// `*futex == val`时阻塞, 直到被唤醒, 超过`timeout_ms`毫秒(为 0 时不超时)或者收到信号
+pub fn futex_wait(futex: &AtomicU32, val: u32, timeout_ms: usize) -> isize {
+    sys_futex(futex.as_ptr(), FUTEX_WAIT, val, timeout_ms)
+}
+
+/// 唤醒最多`count`个在`futex`上等待的任务, 返回唤醒的数量
+pub fn futex_wake(futex: &AtomicU32, count: u32) -> isize {
+    sys_futex(futex.as_ptr(), FUTEX_WAKE, count, 0)
+}
+
+const UNLOCKED: u32 = 0;
+const LOCKED: u32 = 1;
+/// 已经被锁上, 并且可能有任务在等待
+const CONTENDED: u32 = 2;
+
+/// 用户态互斥锁, 没有竞争时加锁和解锁都不需要系统调用
+pub struct FutexMutex {
+    state: AtomicU32,
+}
+
+impl FutexMutex {
+    /// 创建未上锁的互斥锁
+    pub const fn new() -> Self {
+        Self {
+            state: AtomicU32::new(UNLOCKED),
+        }
+    }
+    /// 尝试加锁, 已经被锁上时返回`false`
+    pub fn try_lock(&self) -> bool {
+        self.state
+            .compare_exchange(UNLOCKED, LOCKED, Ordering::Acquire, Ordering::Relaxed)
+            .is_ok()
+    }
+    /// 加锁, 锁被占用时在内核中阻塞
+    pub fn lock(&self) {
+        if self.try_lock() {
+            return;
+        }
+        // 标记为有竞争, 解锁的一方就会唤醒等待者
+        while self.state.swap(CONTENDED, Ordering::Acquire) != UNLOCKED {
+            futex_wait(&self.state, CONTENDED, 0);
+        }
+    }
+    /// 解锁, 有任务在等待时唤醒其中一个
+    pub fn unlock(&self) {
+        if self.state.swap(UNLOCKED, Ordering::Release) == CONTENDED {
+            futex_wake(&self.state, 1);
+        }
+    }
+}
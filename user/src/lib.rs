@@ -9,19 +9,38 @@ extern crate alloc;
 pub mod console;
 #[macro_use]
 pub mod logging;
+mod errno;
+mod futex;
 mod lang_items;
 mod syscall;
 
+pub use errno::{strerror, Errno};
+pub use futex::{futex_wait, futex_wake, FutexMutex};
+
 use alloc::vec::Vec;
 use bitflags::bitflags;
-use buddy_system_allocator::LockedHeap;
+use buddy_system_allocator::{Heap, LockedHeapWithRescue};
+use core::alloc::Layout;
 
 const USER_HEAP_SIZE: usize = 16384;
 
 static mut HEAP_SPACE: [u8; USER_HEAP_SIZE] = [0; USER_HEAP_SIZE];
 
 #[global_allocator]
-static HEAP: LockedHeap = LockedHeap::empty();
+static HEAP: LockedHeapWithRescue = LockedHeapWithRescue::new(heap_rescue);
+
+/// 堆空间不足时通过 sbrk 向内核申请更多的空间
+fn heap_rescue(heap: &mut Heap, layout: &Layout) {
+    // buddy 分配器只能从按大小对齐的块中分配, 申请两倍的空间保证其中有这样一块
+    let size = layout.size().max(layout.align()).next_power_of_two() * 2;
+    let size = size.max(USER_HEAP_SIZE);
+    let start = sbrk(size as isize);
+    if start != -1 {
+        unsafe {
+            heap.add_to_heap(start as usize, start as usize + size);
+        }
+    }
+}
 
 #[alloc_error_handler]
 pub fn handle_alloc_error(layout: core::alloc::Layout) -> ! {
@@ -72,6 +91,24 @@ bitflags! {
     }
 }
 
+bitflags! {
+    pub struct MmapProt: u32 {
+        const NONE = 0;
+        const READ = 1 << 0;
+        const WRITE = 1 << 1;
+        const EXEC = 1 << 2;
+    }
+}
+
+bitflags! {
+    pub struct MmapFlags: u32 {
+        const SHARED = 1 << 0;
+        const PRIVATE = 1 << 1;
+        const FIXED = 1 << 4;
+        const ANONYMOUS = 1 << 5;
+    }
+}
+
 pub fn dup(fd: usize) -> isize {
     sys_dup(fd)
 }
@@ -112,6 +149,32 @@ pub fn get_time() -> isize {
     sys_get_time()
 }
 
+pub fn set_priority(prio: isize) -> isize {
+    sys_set_priority(prio)
+}
+
+/// `setpriority`的`which`: 按进程设置
+pub const PRIO_PROCESS: usize = 0;
+
+/// 设置 nice 值, `who`为 0 时设置当前线程
+pub fn setpriority(which: usize, who: usize, nice: isize) -> isize {
+    sys_setpriority(which, who, nice)
+}
+
+/// 所有 hart 的空闲和忙碌时钟中断次数
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CpuTicks {
+    /// 没有任务可以运行时经过的时钟中断次数
+    pub idle: usize,
+    /// 运行任务时经过的时钟中断次数
+    pub busy: usize,
+}
+
+pub fn cpu_ticks(ticks: &mut CpuTicks) -> isize {
+    sys_cpu_ticks(ticks)
+}
+
 pub fn getpid() -> isize {
     sys_getpid()
 }
@@ -124,62 +187,394 @@ pub fn exec(path: &str, args: &[*const u8]) -> isize {
     sys_exec(path, args)
 }
 
+/// `waitpid_status`的选项: 子进程都没有结束时立即返回0
+pub const WNOHANG: usize = 1;
+
+/// 子进程是否正常退出
+pub fn wifexited(status: i32) -> bool {
+    status & 0x7f == 0
+}
+
+/// 正常退出的子进程的退出码(低 8 位)
+pub fn wexitstatus(status: i32) -> i32 {
+    (status >> 8) & 0xff
+}
+
+/// 子进程是否被信号杀死
+pub fn wifsignaled(status: i32) -> bool {
+    status & 0x7f != 0
+}
+
+/// 杀死子进程的信号
+pub fn wtermsig(status: i32) -> i32 {
+    status & 0x7f
+}
+
+/// 被信号杀死的子进程是否产生了 core dump
+pub fn wcoredump(status: i32) -> bool {
+    status & 0x80 != 0
+}
+
+/// 把 wait status 转换为退出码: 正常退出时为有符号的 8 位退出码, 被信号杀死时为信号编号的相反数
+fn status_to_exit_code(status: i32) -> i32 {
+    if wifsignaled(status) {
+        -wtermsig(status)
+    } else {
+        wexitstatus(status) as i8 as i32
+    }
+}
+
+/// 等待子进程结束, 得到原始的 wait status. `pid`为 -1 时等待任意子进程
+pub fn waitpid_status(pid: isize, status: &mut i32, options: usize) -> isize {
+    sys_waitpid(pid, status as *mut _, options)
+}
+
+fn waitpid_exit_code(pid: isize, exit_code: &mut i32, options: usize) -> isize {
+    let mut status = 0;
+    let ret = waitpid_status(pid, &mut status, options);
+    if ret > 0 {
+        *exit_code = status_to_exit_code(status);
+    }
+    ret
+}
+
 /// 任意子进程结束,就返回
 pub fn wait(exit_code: &mut i32) -> isize {
-    loop {
-        match sys_waitpid(-1, exit_code as *mut _) {
-            -2 => {
-                yield_();
-            }
-            // -1 or a real pid
-            exit_pid => return exit_pid,
-        }
-    }
+    waitpid_exit_code(-1, exit_code, 0)
 }
 
 /// 必须等到指定pid的子进程结束,才返回
 pub fn waitpid(pid: usize, exit_code: &mut i32) -> isize {
-    loop {
-        match sys_waitpid(pid as isize, exit_code as *mut _) {
-            -2 => {
-                yield_();
-            }
-            // -1 or a real pid
-            exit_pid => return exit_pid,
-        }
-    }
+    waitpid_exit_code(pid as isize, exit_code, 0)
 }
 
+/// 指定pid的子进程还没有结束时返回0
 pub fn waitpid_nb(pid: usize, exit_code: &mut i32) -> isize {
-    sys_waitpid(pid as isize, exit_code as *mut _)
+    waitpid_exit_code(pid as isize, exit_code, WNOHANG)
+}
+
+/// 创建一个线程执行`entry(arg)`, 返回它的 tid. 线程需要调用`exit`结束
+pub fn thread_create(entry: usize, arg: usize) -> isize {
+    sys_thread_create(entry, arg)
+}
+
+pub fn gettid() -> isize {
+    sys_gettid()
+}
+
+/// 等待线程`tid`退出, 返回它的退出码
+pub fn waittid(tid: usize) -> isize {
+    sys_waittid(tid)
+}
+
+pub fn mutex_create() -> isize {
+    sys_mutex_create()
+}
+
+pub fn mutex_lock(mutex_id: usize) -> isize {
+    sys_mutex_lock(mutex_id)
+}
+
+pub fn mutex_unlock(mutex_id: usize) -> isize {
+    sys_mutex_unlock(mutex_id)
+}
+
+pub fn semaphore_create(res_count: usize) -> isize {
+    sys_semaphore_create(res_count)
+}
+
+pub fn semaphore_up(sem_id: usize) -> isize {
+    sys_semaphore_up(sem_id)
+}
+
+pub fn semaphore_down(sem_id: usize) -> isize {
+    sys_semaphore_down(sem_id)
+}
+
+pub fn condvar_create() -> isize {
+    sys_condvar_create()
+}
+
+pub fn condvar_signal(condvar_id: usize) -> isize {
+    sys_condvar_signal(condvar_id)
+}
+
+/// 调用时必须持有`mutex_id`, 返回时重新持有它
+pub fn condvar_wait(condvar_id: usize, mutex_id: usize) -> isize {
+    sys_condvar_wait(condvar_id, mutex_id)
+}
+
+pub fn enable_deadlock_detect(enabled: bool) -> isize {
+    sys_enable_deadlock_detect(enabled as usize)
+}
+
+pub fn mmap(
+    addr: usize,
+    len: usize,
+    prot: MmapProt,
+    flags: MmapFlags,
+    fd: usize,
+    offset: usize,
+) -> isize {
+    sys_mmap(addr, len, prot.bits, flags.bits, fd, offset)
+}
+
+pub fn munmap(addr: usize, len: usize) -> isize {
+    sys_munmap(addr, len)
+}
+
+pub fn mprotect(addr: usize, len: usize, prot: MmapProt) -> isize {
+    sys_mprotect(addr, len, prot.bits)
 }
 
-pub fn sleep(period_ms: usize) {
-    let start = sys_get_time();
-    while sys_get_time() < start + period_ms as isize {
-        sys_yield();
+pub fn brk(addr: usize) -> isize {
+    sys_brk(addr)
+}
+
+/// 把堆扩大(或缩小)`increment`字节, 返回原来的 program break, 失败返回 -1
+pub fn sbrk(increment: isize) -> isize {
+    let old_brk = sys_brk(0);
+    let new_brk = old_brk + increment;
+    if sys_brk(new_brk as usize) == new_brk {
+        old_brk
+    } else {
+        -1
     }
 }
 
+// 以下是返回`Result`的版本, 出错时给出错误码
+
+pub fn try_dup(fd: usize) -> Result<usize, Errno> {
+    Errno::result(dup(fd))
+}
+
+pub fn try_open(path: &str, flags: OpenFlags) -> Result<usize, Errno> {
+    Errno::result(open(path, flags))
+}
+
+pub fn try_close(fd: usize) -> Result<(), Errno> {
+    Errno::result(close(fd)).map(|_| ())
+}
+
+pub fn try_pipe(pipe_fd: &mut [usize]) -> Result<(), Errno> {
+    Errno::result(pipe(pipe_fd)).map(|_| ())
+}
+
+pub fn try_read(fd: usize, buf: &mut [u8]) -> Result<usize, Errno> {
+    Errno::result(read(fd, buf))
+}
+
+pub fn try_write(fd: usize, buf: &[u8]) -> Result<usize, Errno> {
+    Errno::result(write(fd, buf))
+}
+
+/// 成功时不会返回
+pub fn try_exec(path: &str, args: &[*const u8]) -> Result<usize, Errno> {
+    Errno::result(exec(path, args))
+}
+
+pub fn try_wait(exit_code: &mut i32) -> Result<usize, Errno> {
+    Errno::result(wait(exit_code))
+}
+
+pub fn try_waitpid(pid: usize, exit_code: &mut i32) -> Result<usize, Errno> {
+    Errno::result(waitpid(pid, exit_code))
+}
+
+pub fn try_mmap(
+    addr: usize,
+    len: usize,
+    prot: MmapProt,
+    flags: MmapFlags,
+    fd: usize,
+    offset: usize,
+) -> Result<usize, Errno> {
+    Errno::result(mmap(addr, len, prot, flags, fd, offset))
+}
+
+pub fn try_munmap(addr: usize, len: usize) -> Result<(), Errno> {
+    Errno::result(munmap(addr, len)).map(|_| ())
+}
+
+pub fn try_mprotect(addr: usize, len: usize, prot: MmapProt) -> Result<(), Errno> {
+    Errno::result(mprotect(addr, len, prot)).map(|_| ())
+}
+
+/// 睡眠`period_ms`毫秒, 被信号打断时返回`-EINTR`
+pub fn sleep(period_ms: usize) -> isize {
+    sys_sleep(period_ms)
+}
+
+/// 与 Linux 的`struct timespec`相同的时间间隔
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TimeSpec {
+    /// 秒
+    pub sec: usize,
+    /// 纳秒, 小于 10^9
+    pub nsec: usize,
+}
+
+/// 与 Linux 的`struct timeval`相同的时间间隔
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TimeVal {
+    /// 秒
+    pub sec: usize,
+    /// 微秒, 小于 10^6
+    pub usec: usize,
+}
+
+/// 间隔定时器的设置
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ITimerVal {
+    /// 到期之后重新开始计时的时间, 为 0 时只触发一次
+    pub interval: TimeVal,
+    /// 距离下一次到期的时间, 为 0 时停止计时
+    pub value: TimeVal,
+}
+
+/// 按照墙上时间计时, 到期时发送 SIGALRM
+pub const ITIMER_REAL: usize = 0;
+/// 按照进程在用户态运行的时间计时, 到期时发送 SIGVTALRM
+pub const ITIMER_VIRTUAL: usize = 1;
+/// 按照进程运行的全部时间计时, 到期时发送 SIGPROF
+pub const ITIMER_PROF: usize = 2;
+
+pub fn getitimer(which: usize, curr_value: &mut ITimerVal) -> isize {
+    sys_getitimer(which, curr_value)
+}
+
+/// 设置间隔定时器, `old_value`不为`None`时返回原来的设置
+pub fn setitimer(which: usize, new_value: &ITimerVal, old_value: Option<&mut ITimerVal>) -> isize {
+    let old_value = old_value.map_or(core::ptr::null_mut(), |old| old as *mut ITimerVal);
+    sys_setitimer(which, new_value, old_value)
+}
+
+/// `seconds`秒之后收到 SIGALRM, 返回之前的设置剩余的秒数
+pub fn alarm(seconds: usize) -> isize {
+    sys_alarm(seconds)
+}
+
+/// 睡眠`req`指定的时间, 被信号打断时返回`-EINTR`并把剩余的时间写到`rem`
+pub fn nanosleep(req: &TimeSpec, rem: Option<&mut TimeSpec>) -> isize {
+    let rem = rem.map_or(core::ptr::null_mut(), |rem| rem as *mut TimeSpec);
+    sys_nanosleep(req, rem)
+}
+
 #[repr(C)]
 #[derive(Debug, Clone, Copy)]
-/// 信号和对应handler
+/// 信号和对应handler.
+///
+/// 信号处理函数返回时由内核提供的跳板自动调用`sigreturn`, 也可以在处理函数中手动调用
 pub struct SignalAction {
-    /// 信号处理函数的地址
+    /// 信号处理函数的地址, 或者`SIG_DFL`和`SIG_IGN`. 处理函数的原型为`fn(signo: i32)`,
+    /// 设置了`SA_SIGINFO`时为`extern "C" fn(signo: i32, info: &SignalInfo, ucontext: &mut UContext)`
     pub handler: usize,
     /// 信号掩码
     pub mask: SignalFlags,
+    /// 选项, `SA_*`
+    pub flags: u32,
 }
 
 impl Default for SignalAction {
     fn default() -> Self {
         Self {
-            handler: 0,
+            handler: SIG_DFL,
             mask: SignalFlags::empty(),
+            flags: 0,
         }
     }
 }
 
+/// `SignalAction::handler`: 执行信号的默认动作
+pub const SIG_DFL: usize = 0;
+/// `SignalAction::handler`: 忽略信号
+pub const SIG_IGN: usize = 1;
+
+/// `SignalAction::flags`: 信号处理函数需要`SignalInfo`和`UContext`参数
+pub const SA_SIGINFO: u32 = 4;
+/// `SignalAction::flags`: 在`sigaltstack`设置的备用信号栈上执行信号处理函数
+pub const SA_ONSTACK: u32 = 0x0800_0000;
+
+/// `SignalStack::flags`: 正在备用信号栈上执行
+pub const SS_ONSTACK: i32 = 1;
+/// `SignalStack::flags`: 不使用备用信号栈
+pub const SS_DISABLE: i32 = 2;
+/// 备用信号栈的最小大小
+pub const MINSIGSTKSZ: usize = 2048;
+
+/// 备用信号栈
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct SignalStack {
+    /// 栈的起始地址(低地址)
+    pub sp: usize,
+    /// `SS_*`
+    pub flags: i32,
+    /// 栈的大小
+    pub size: usize,
+}
+
+impl Default for SignalStack {
+    fn default() -> Self {
+        Self {
+            sp: 0,
+            flags: SS_DISABLE,
+            size: 0,
+        }
+    }
+}
+
+/// 设置当前线程的备用信号栈, `old_ss`不为`None`时返回原来的设置
+pub fn sigaltstack(ss: Option<&SignalStack>, old_ss: Option<&mut SignalStack>) -> isize {
+    let ss = ss.map_or(core::ptr::null(), |ss| ss as *const SignalStack);
+    let old_ss = old_ss.map_or(core::ptr::null_mut(), |old| old as *mut SignalStack);
+    sys_sigaltstack(ss, old_ss)
+}
+
+/// `SignalInfo::code`: 由`kill`发送
+pub const SI_USER: i32 = 0;
+/// `SignalInfo::code`: 由内核发送
+pub const SI_KERNEL: i32 = 0x80;
+/// `SignalInfo::code`: 由`sigqueue`发送
+pub const SI_QUEUE: i32 = -1;
+/// `SignalInfo::code`: 由间隔定时器发送
+pub const SI_TIMER: i32 = -2;
+/// `SignalInfo::code`: SIGSEGV, 地址没有映射或者没有权限
+pub const SEGV_MAPERR: i32 = 1;
+/// `SignalInfo::code`: SIGCHLD, 子进程退出
+pub const CLD_EXITED: i32 = 1;
+
+/// 信号的附加信息, 对应 Linux 的`siginfo_t`中常用的字段
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SignalInfo {
+    /// 信号编号
+    pub signo: i32,
+    /// 错误码
+    pub errno: i32,
+    /// 信号的来源, `SI_*`等常量
+    pub code: i32,
+    /// 发送信号的进程
+    pub pid: u32,
+    /// 引起 SIGSEGV 和 SIGILL 的地址
+    pub addr: usize,
+    /// `sigqueue`附带的整数或者指针
+    pub value: usize,
+}
+
+/// 被信号打断时的上下文, 信号处理函数修改之后由`sigreturn`恢复
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct UContext {
+    /// 被打断时的信号掩码
+    pub sigmask: SignalFlags,
+    /// 被打断时的寄存器: `gregs[0]`是 pc, `gregs[i]`是`x[i]`
+    pub gregs: [usize; 32],
+}
+
 pub const SIGDEF: i32 = 0; // Default signal handling
 pub const SIGHUP: i32 = 1;
 pub const SIGINT: i32 = 2;
@@ -212,9 +607,15 @@ pub const SIGWINCH: i32 = 28;
 pub const SIGIO: i32 = 29;
 pub const SIGPWR: i32 = 30;
 pub const SIGSYS: i32 = 31;
+/// 第一个实时信号
+pub const SIGRTMIN: i32 = 32;
+/// 最后一个实时信号
+pub const SIGRTMAX: i32 = 63;
+/// 每个进程最多排队的实时信号数量
+pub const SIGQUEUE_MAX: usize = 32;
 
 bitflags! {
-    pub struct SignalFlags: i32 {
+    pub struct SignalFlags: u64 {
         /// Default signal handling
         const SIGDEF = 1;
         /// 挂起
@@ -279,6 +680,8 @@ bitflags! {
         const SIGPWR = 1 << 30;
         /// 系统调用异常
         const SIGSYS = 1 << 31;
+        /// 实时信号`SIGRTMIN..=SIGRTMAX`
+        const SIGRT = !0xffff_ffff;
     }
 }
 
@@ -294,10 +697,27 @@ pub fn sigaction(
     sys_sigaction(signum, action, old_action)
 }
 
-pub fn sigprocmask(mask: u32) -> isize {
+pub fn try_kill(pid: usize, signal: i32) -> Result<(), Errno> {
+    Errno::result(kill(pid, signal)).map(|_| ())
+}
+
+pub fn try_sigaction(
+    signum: i32,
+    action: *const SignalAction,
+    old_action: *const SignalAction,
+) -> Result<(), Errno> {
+    Errno::result(sigaction(signum, action, old_action)).map(|_| ())
+}
+
+pub fn sigprocmask(mask: u64) -> isize {
     sys_sigprocmask(mask)
 }
 
+/// 向进程`pid`发送附带`value`的信号`signum`
+pub fn sigqueue(pid: usize, signum: i32, value: usize) -> isize {
+    sys_sigqueue(pid, signum, value)
+}
+
 pub fn sigreturn() -> isize {
     sys_sigreturn()
 }
@@ -1,4 +1,4 @@
-use crate::SignalAction;
+use crate::{CpuTicks, ITimerVal, SignalAction, SignalStack, TimeSpec};
 use core::arch::asm;
 
 const SYSCALL_LS: usize = 22;
@@ -10,16 +10,44 @@ const SYSCALL_PIPE: usize = 59;
 const SYSCALL_READ: usize = 63;
 const SYSCALL_WRITE: usize = 64;
 const SYSCALL_EXIT: usize = 93;
+const SYSCALL_FUTEX: usize = 98;
+const SYSCALL_NANOSLEEP: usize = 101;
+const SYSCALL_GETITIMER: usize = 102;
+const SYSCALL_SETITIMER: usize = 103;
 const SYSCALL_YIELD: usize = 124;
 const SYSCALL_KILL: usize = 129;
+const SYSCALL_SIGALTSTACK: usize = 132;
 const SYSCALL_SIGACTION: usize = 134;
 const SYSCALL_SIGPROCMASK: usize = 135;
+const SYSCALL_SIGQUEUE: usize = 138;
 const SYSCALL_SIGRETURN: usize = 139;
+const SYSCALL_SET_PRIORITY: usize = 140;
 const SYSCALL_GET_TIME: usize = 169;
 const SYSCALL_GETPID: usize = 172;
+const SYSCALL_BRK: usize = 214;
+const SYSCALL_MUNMAP: usize = 215;
 const SYSCALL_FORK: usize = 220;
 const SYSCALL_EXEC: usize = 221;
+const SYSCALL_MMAP: usize = 222;
+const SYSCALL_MPROTECT: usize = 226;
 const SYSCALL_WAITPID: usize = 260;
+const SYSCALL_ENABLE_DEADLOCK_DETECT: usize = 469;
+const SYSCALL_THREAD_CREATE: usize = 1000;
+const SYSCALL_GETTID: usize = 1001;
+const SYSCALL_WAITTID: usize = 1002;
+const SYSCALL_MUTEX_CREATE: usize = 1010;
+const SYSCALL_MUTEX_LOCK: usize = 1011;
+const SYSCALL_MUTEX_UNLOCK: usize = 1012;
+const SYSCALL_SEMAPHORE_CREATE: usize = 1020;
+const SYSCALL_SEMAPHORE_UP: usize = 1021;
+const SYSCALL_SEMAPHORE_DOWN: usize = 1022;
+const SYSCALL_CONDVAR_CREATE: usize = 1030;
+const SYSCALL_CONDVAR_SIGNAL: usize = 1031;
+const SYSCALL_CONDVAR_WAIT: usize = 1032;
+const SYSCALL_SETPRIORITY: usize = 1040;
+const SYSCALL_CPU_TICKS: usize = 1050;
+const SYSCALL_SLEEP: usize = 1060;
+const SYSCALL_ALARM: usize = 1070;
 
 #[inline(always)]
 fn syscall(id: usize, args: [usize; 3]) -> isize {
@@ -36,6 +64,24 @@ fn syscall(id: usize, args: [usize; 3]) -> isize {
     ret
 }
 
+#[inline(always)]
+fn syscall6(id: usize, args: [usize; 6]) -> isize {
+    let mut ret: isize;
+    unsafe {
+        asm!(
+        "ecall",
+        inlateout("x10") args[0] => ret,
+        in("x11") args[1],
+        in("x12") args[2],
+        in("x13") args[3],
+        in("x14") args[4],
+        in("x15") args[5],
+        in("x17") id
+        );
+    }
+    ret
+}
+
 /// 功能: 显示某个路径下的所有文件
 /// 参数: `path` 待显示所有文件的路径
 /// 返回值: 暂无返回值
@@ -46,7 +92,7 @@ pub fn sys_ls(path: &str) -> isize {
 
 /// 功能: 将进程中一个已经打开的文件复制一份并分配到一个新的文件描述符中。
 /// 参数: `fd` 表示进程中一个已经打开的文件的文件描述符。
-/// 返回值: 如果出现了错误则返回 -1，否则能够访问已打开文件的新文件描述符。
+/// 返回值: 如果出现了错误则返回 -EBADF，否则能够访问已打开文件的新文件描述符。
 /// 可能的错误原因是: 传入的 fd 并不对应一个合法的已打开文件。
 /// syscall ID: 24
 pub fn sys_dup(fd: usize) -> isize {
@@ -56,7 +102,7 @@ pub fn sys_dup(fd: usize) -> isize {
 /// 功能: 打开一个常规文件，并返回可以访问它的文件描述符。
 /// 参数: `path` 描述要打开的文件的文件名（简单起见，文件系统不需要支持目录，所有的文件都放在根目录 / 下），
 ///      `flags` 描述打开文件的标志，具体含义下面给出。
-/// 返回值: 如果出现了错误则返回 -1，否则返回打开常规文件的文件描述符。可能的错误原因是: 文件不存在。
+/// 返回值: 如果出现了错误则返回错误码的相反数，否则返回打开常规文件的文件描述符。可能的错误原因是: 文件不存在(ENOENT)、`flags` 不合法(EINVAL)、`path` 不可访问(EFAULT)。
 /// syscall ID: 56
 pub fn sys_open(path: &str, flags: u32) -> isize {
     syscall(SYSCALL_OPEN, [path.as_ptr() as usize, flags as usize, 0])
@@ -64,7 +110,7 @@ pub fn sys_open(path: &str, flags: u32) -> isize {
 
 /// 功能: 当前进程关闭一个文件。
 /// 参数: `fd` 表示要关闭的文件的文件描述符。
-/// 返回值: 如果成功关闭则返回 0 ，否则返回 -1 。可能的出错原因: 传入的文件描述符并不对应一个打开的文件。
+/// 返回值: 如果成功关闭则返回 0 ，否则返回 -EBADF 。可能的出错原因: 传入的文件描述符并不对应一个打开的文件。
 pub fn sys_close(fd: usize) -> isize {
     syscall(SYSCALL_CLOSE, [fd as usize, 0, 0])
 }
@@ -72,7 +118,7 @@ pub fn sys_close(fd: usize) -> isize {
 /// 功能: 为当前进程打开一个管道。
 /// 参数: `pipe` 表示应用地址空间中的一个长度为 2 的 usize 数组的起始地址，内核需要按顺序将管道读端
 ///             和写端的文件描述符写入到数组中。
-/// 返回值: 如果出现了错误则返回 -1，否则返回 0 。可能的错误原因是: 传入的地址不合法。
+/// 返回值: 如果出现了错误则返回 -EFAULT，否则返回 0 。可能的错误原因是: 传入的地址不合法。
 /// syscall ID: 59
 pub fn sys_pipe(pipe: &mut [usize]) -> isize {
     syscall(SYSCALL_PIPE, [pipe.as_mut_ptr() as usize, 0, 0])
@@ -81,7 +127,7 @@ pub fn sys_pipe(pipe: &mut [usize]) -> isize {
 /// 功能: 从文件中读取一段内容到缓冲区。
 /// 参数: `fd` 是待读取文件的文件描述符，
 ///      `buffer` 切片 则给出缓冲区。
-/// 返回值: 如果出现了错误则返回 -1，否则返回实际读到的字节数。
+/// 返回值: 如果出现了错误则返回错误码的相反数，否则返回实际读到的字节数。可能的错误原因是: `fd` 不可读(EBADF)、`buffer` 不可写(EFAULT)。
 /// syscall ID: 63
 pub fn sys_read(fd: usize, buffer: &mut [u8]) -> isize {
     syscall(
@@ -93,14 +139,14 @@ pub fn sys_read(fd: usize, buffer: &mut [u8]) -> isize {
 /// 功能: 将内存中缓冲区中的数据写入文件。
 /// 参数: `fd` 表示待写入文件的文件描述符；
 ///      `buffer` 表示内存中缓冲区的起始地址；
-/// 返回值: 返回成功写入的长度。
+/// 返回值: 返回成功写入的长度；出错时返回错误码的相反数。可能的错误原因是: `fd` 不可写(EBADF)、`buffer` 不可读(EFAULT)。
 /// syscall ID: 64
 pub fn sys_write(fd: usize, buffer: &[u8]) -> isize {
     syscall(SYSCALL_WRITE, [fd, buffer.as_ptr() as usize, buffer.len()])
 }
 
-/// 功能: 退出应用程序并将返回值告知批处理系统。
-/// 参数: `xstate` 表示应用程序的返回值。
+/// 功能: 退出当前线程。主线程退出时整个进程退出，返回值告知父进程。
+/// 参数: `xstate` 表示线程（进程）的返回值。
 /// 返回值: 该系统调用不应该返回。
 /// syscall ID: 93
 pub fn sys_exit(xstate: i32) -> isize {
@@ -121,6 +167,67 @@ pub fn sys_get_time() -> isize {
     syscall(SYSCALL_GET_TIME, [0; 3])
 }
 
+/// 功能: 当前线程睡眠一段时间，睡眠期间不占用处理器。
+/// 参数: `ms` 睡眠的毫秒数。
+/// 返回值: 0 成功；被信号打断返回 -EINTR 。
+/// syscall ID: 1060
+pub fn sys_sleep(ms: usize) -> isize {
+    syscall(SYSCALL_SLEEP, [ms, 0, 0])
+}
+
+/// 功能: 当前线程睡眠一段时间，时间向上取整到毫秒。
+/// 参数: `req` 睡眠的时间；`rem` 不为空时，被信号打断后内核把剩余的时间写到这里。
+/// 返回值: 0 成功；被信号打断返回 -EINTR，`req->nsec` 不小于 10^9 返回 -EINVAL，
+///        地址不合法返回 -EFAULT 。
+/// syscall ID: 101
+pub fn sys_nanosleep(req: *const TimeSpec, rem: *mut TimeSpec) -> isize {
+    syscall(SYSCALL_NANOSLEEP, [req as usize, rem as usize, 0])
+}
+
+/// 功能: 获取当前进程的间隔定时器。
+/// 参数: `which` 为 ITIMER_REAL(0)、ITIMER_VIRTUAL(1) 或 ITIMER_PROF(2)；
+///      `curr_value` 内核把定时器的间隔和剩余的时间写到这里。
+/// 返回值: 0 成功；`which` 不合法返回 -EINVAL，地址不合法返回 -EFAULT 。
+/// syscall ID: 102
+pub fn sys_getitimer(which: usize, curr_value: *mut ITimerVal) -> isize {
+    syscall(SYSCALL_GETITIMER, [which, curr_value as usize, 0])
+}
+
+/// 功能: 设置当前进程的间隔定时器，到期时分别发送 SIGALRM、SIGVTALRM 或 SIGPROF 。
+///      ITIMER_REAL 按照墙上时间计时，ITIMER_VIRTUAL 按照进程在用户态运行的时间计时，
+///      ITIMER_PROF 按照进程在用户态和内核态运行的时间计时。
+/// 参数: `which` 为 ITIMER_REAL(0)、ITIMER_VIRTUAL(1) 或 ITIMER_PROF(2)；
+///      `new_value` 新的设置，`value` 为 0 时停止计时，`interval` 为 0 时只触发一次；
+///      `old_value` 不为空时，内核把原来的设置写到这里。
+/// 返回值: 0 成功；参数不合法返回 -EINVAL，地址不合法返回 -EFAULT 。
+/// syscall ID: 103
+pub fn sys_setitimer(
+    which: usize,
+    new_value: *const ITimerVal,
+    old_value: *mut ITimerVal,
+) -> isize {
+    syscall(
+        SYSCALL_SETITIMER,
+        [which, new_value as usize, old_value as usize],
+    )
+}
+
+/// 功能: 一段时间之后给当前进程发送 SIGALRM，会替换之前的设置。
+/// 参数: `seconds` 秒数，为 0 时取消之前的设置。
+/// 返回值: 之前的设置剩余的秒数（向上取整），没有设置时返回 0 。
+/// syscall ID: 1070
+pub fn sys_alarm(seconds: usize) -> isize {
+    syscall(SYSCALL_ALARM, [seconds, 0, 0])
+}
+
+/// 功能: 获取所有 hart 的空闲和忙碌时钟中断次数。
+/// 参数: `ticks` 内核把空闲(没有任务可以运行)和忙碌(正在运行任务)的时钟中断次数写到这里。
+/// 返回值: 0 成功；地址不合法返回 -EFAULT 。
+/// syscall ID: 1050
+pub fn sys_cpu_ticks(ticks: *mut CpuTicks) -> isize {
+    syscall(SYSCALL_CPU_TICKS, [ticks as usize, 0, 0])
+}
+
 /// 功能: 获取process id
 /// 返回值: process id
 /// syscall ID: 172
@@ -130,14 +237,17 @@ pub fn sys_getpid() -> isize {
 
 /// 功能: 当前进程 fork 出来一个子进程。
 /// 返回值: 对于子进程返回 0，对于当前进程则返回子进程的 PID 。
+/// 子进程中只有调用 fork 的线程，它成为子进程的主线程。
 /// syscall ID: 220
 pub fn sys_fork() -> isize {
     syscall(SYSCALL_FORK, [0, 0, 0])
 }
 
 /// 功能: 将当前进程的地址空间清空并加载一个特定的可执行文件，返回用户态后开始它的执行。
+///       进程的其它线程都被终止，调用的线程成为新程序的主线程。
 /// 参数: `path` 给出了要加载的可执行文件的名字；
-/// 返回值: 如果出错的话（如找不到名字相符的可执行文件）则返回 -1，否则不应该返回。
+/// 返回值: 如果出错的话（如找不到名字相符的可执行文件, ENOENT；文件不是 ELF 格式, ENOEXEC；
+///        参数放不下用户栈, E2BIG）则返回错误码的相反数，否则不应该返回。
 /// syscall ID: 221
 pub fn sys_exec(path: &str, args: &[*const u8]) -> isize {
     syscall(
@@ -148,28 +258,40 @@ pub fn sys_exec(path: &str, args: &[*const u8]) -> isize {
 
 /// 功能: 当前进程等待一个子进程变为僵尸进程，回收其全部资源并收集其返回值。
 /// 参数: `pid` 表示要等待的子进程的进程 ID，如果为 -1 的话表示等待任意一个子进程；
-///      `exit_code` 表示保存子进程返回值的地址，如果这个地址为 0 的话表示不必保存。
-/// 返回值: 如果要等待的子进程不存在则返回 -1；否则如果要等待的子进程均未结束则返回 -2；
+///      `exit_code` 表示保存子进程 wait status 的地址，如果这个地址为 0 的话表示不必保存；
+///      `options` 为 0 或者 `WNOHANG`。
+/// 返回值: 如果要等待的子进程不存在则返回 -ECHILD；否则如果要等待的子进程均未结束，
+///        带 `WNOHANG` 时返回 0，否则阻塞直到其中一个结束，阻塞时收到信号则返回 -EINTR；
 ///        否则返回结束的子进程的进程 ID。
 /// syscall ID: 260
-pub fn sys_waitpid(pid: isize, exit_code: *mut i32) -> isize {
-    syscall(SYSCALL_WAITPID, [pid as usize, exit_code as usize, 0])
+pub fn sys_waitpid(pid: isize, exit_code: *mut i32, options: usize) -> isize {
+    syscall(SYSCALL_WAITPID, [pid as usize, exit_code as usize, options])
 }
 
 /// 功能: 向指定进程发送信号
 /// 参数: `pid` 表示接收信号的进程
 ///      `signal` 表示要发送的信号
-/// 返回值: 0成功 -1失败
+/// 返回值: 0成功；进程不存在返回 -ESRCH，信号不合法返回 -EINVAL，信号已在等待处理返回 -EAGAIN
 /// syscall ID: 129
 pub fn sys_kill(pid: usize, signal: i32) -> isize {
     syscall(SYSCALL_KILL, [pid, signal as usize, 0])
 }
 
+/// 功能: 设置当前线程的备用信号栈，设置了 SA_ONSTACK 的信号处理函数在备用信号栈上执行。
+/// 参数: `ss` 不为空时设置新的备用信号栈，`flags` 为 SS_DISABLE 时不使用备用信号栈；
+///      `old_ss` 不为空时，内核把原来的设置写到这里，正在备用信号栈上执行时 `flags` 为 SS_ONSTACK 。
+/// 返回值: 0 成功；正在备用信号栈上执行时修改返回 -EPERM，栈小于 MINSIGSTKSZ 返回 -ENOMEM，
+///        `flags` 不合法返回 -EINVAL，地址不合法返回 -EFAULT 。
+/// syscall ID: 132
+pub fn sys_sigaltstack(ss: *const SignalStack, old_ss: *mut SignalStack) -> isize {
+    syscall(SYSCALL_SIGALTSTACK, [ss as usize, old_ss as usize, 0])
+}
+
 /// 功能: 替换进程对应信号的action
 /// 参数: `signum` 表示信号
 ///      `action` 表示新的信号和action
 ///      `old_action` 表示被替换的信号和action
-/// 返回值: 0成功 -1失败
+/// 返回值: 0成功 -EINVAL失败
 /// syscall ID: 134
 pub fn sys_sigaction(
     signum: i32,
@@ -184,15 +306,206 @@ pub fn sys_sigaction(
 
 /// 功能: 设置要阻止的信号
 /// 参数: `mask` 信号掩码
-/// 返回值: -1失败 其他值表示`old_mask`
+/// 返回值: -EINVAL失败 其他值表示`old_mask`
 /// syscall ID: 135
-pub fn sys_sigprocmask(mask: u32) -> isize {
+pub fn sys_sigprocmask(mask: u64) -> isize {
     syscall(SYSCALL_SIGPROCMASK, [mask as usize, 0, 0])
 }
 
-/// 功能: 在信号处理后恢复继续执行
-/// 返回值: 0成功 -1失败
+/// 功能: 向进程发送附带整数或者指针的信号。SIGRTMIN 到 SIGRTMAX 的实时信号按照发送的顺序排队，
+///      普通信号已经在等待时与它合并。
+/// 参数: `pid` 表示目标进程，`signum` 表示信号，`value` 由信号处理函数从`SignalInfo::value`中得到。
+/// 返回值: 0 成功；进程不存在返回 -ESRCH，信号不合法返回 -EINVAL，实时信号队列已满返回 -EAGAIN 。
+/// syscall ID: 138
+pub fn sys_sigqueue(pid: usize, signum: i32, value: usize) -> isize {
+    syscall(SYSCALL_SIGQUEUE, [pid, signum as usize, value])
+}
+
+/// 功能: 在信号处理后恢复继续执行，恢复信号帧中保存的寄存器和信号掩码。
+///      信号处理函数返回时内核提供的跳板会自动调用。
+/// 返回值: 成功时不返回到调用处，-EINVAL失败(不在信号处理例程中)，-EFAULT 信号帧不可访问
 /// syscall ID: 139
 pub fn sys_sigreturn() -> isize {
     syscall(SYSCALL_SIGRETURN, [0, 0, 0])
 }
+
+/// 功能: 设置当前线程的优先级，只有 stride 调度器使用优先级。
+/// 参数: `prio` 优先级，至少为 2，越大得到的处理器时间越多。
+/// 返回值: 成功返回 `prio`，`prio < 2` 返回 -EINVAL 。
+/// syscall ID: 140
+pub fn sys_set_priority(prio: isize) -> isize {
+    syscall(SYSCALL_SET_PRIORITY, [prio as usize, 0, 0])
+}
+
+/// 功能: 设置当前进程的 program break，即堆的结尾。
+/// 参数: `addr` 新的 program break，为 0 时只查询当前的 program break。
+/// 返回值: 返回新的 program break，失败时返回原来的 program break。
+/// 可能的错误原因是: `addr` 低于堆的起始地址、堆与其他映射重叠。
+/// syscall ID: 214
+pub fn sys_brk(addr: usize) -> isize {
+    syscall(SYSCALL_BRK, [addr, 0, 0])
+}
+
+/// 功能: 将文件或匿名内存映射到当前进程的地址空间。
+/// 参数: `addr` 映射的起始地址，只有 `flags` 包含 MAP_FIXED 时才使用，必须按页对齐，范围内已有的映射被替换；
+///      `len` 映射的长度；
+///      `prot` 访问权限，第 0/1/2 位分别表示可读/可写/可执行；
+///      `flags` 映射方式，MAP_SHARED/MAP_PRIVATE 必须且只能有一个；
+///      `fd` 要映射的文件，匿名映射(MAP_ANONYMOUS)时忽略；
+///      `offset` 映射开始的文件偏移，必须按页对齐。
+/// 返回值: 如果出现了错误则返回错误码的相反数，否则返回映射的起始地址。
+/// 可能的错误原因是: 参数不合法(EINVAL)、没有足够的地址空间(ENOMEM)、`fd` 不合法(EBADF)、文件不可读或共享可写映射时文件不可写(EACCES)。
+/// syscall ID: 222
+pub fn sys_mmap(addr: usize, len: usize, prot: u32, flags: u32, fd: usize, offset: usize) -> isize {
+    syscall6(
+        SYSCALL_MMAP,
+        [addr, len, prot as usize, flags as usize, fd, offset],
+    )
+}
+
+/// 功能: 解除 `[addr, addr + len)` 的映射，可以只解除一段映射的一部分。
+/// 参数: `addr` 起始地址，必须按页对齐；`len` 长度。
+/// 返回值: 成功返回 0，参数不合法或者范围与堆重叠(堆只能通过 brk 缩小)则返回 -EINVAL 。
+///        范围内没有映射的页面被忽略。
+/// syscall ID: 215
+pub fn sys_munmap(addr: usize, len: usize) -> isize {
+    syscall(SYSCALL_MUNMAP, [addr, len, 0])
+}
+
+/// 功能: 修改 `[addr, addr + len)` 的访问权限。
+/// 参数: `addr` 起始地址，必须按页对齐；`len` 长度；`prot` 新的访问权限，含义同 `sys_mmap`。
+/// 返回值: 成功返回 0，否则返回错误码的相反数。可能的错误原因是: 参数不合法(EINVAL)、范围内有没有映射的页面(ENOMEM)。
+/// syscall ID: 226
+pub fn sys_mprotect(addr: usize, len: usize, prot: u32) -> isize {
+    syscall(SYSCALL_MPROTECT, [addr, len, prot as usize])
+}
+
+/// 功能: 在当前进程中创建一个线程。
+/// 参数: `entry` 线程的入口地址；`arg` 传给线程入口函数的参数。
+/// 返回值: 新线程的 TID 。
+/// syscall ID: 1000
+pub fn sys_thread_create(entry: usize, arg: usize) -> isize {
+    syscall(SYSCALL_THREAD_CREATE, [entry, arg, 0])
+}
+
+/// 功能: 获取当前线程的 TID，主线程的 TID 为 0 。
+/// 返回值: 当前线程的 TID 。
+/// syscall ID: 1001
+pub fn sys_gettid() -> isize {
+    syscall(SYSCALL_GETTID, [0; 3])
+}
+
+/// 功能: 等待当前进程中的一个线程退出，回收其资源并收集其返回值。
+/// 参数: `tid` 要等待的线程的 TID 。
+/// 返回值: 线程的返回值。线程不存在或已被回收返回 -ESRCH，等待自己返回 -EDEADLK，
+///        阻塞时收到信号返回 -EINTR 。
+/// syscall ID: 1002
+pub fn sys_waittid(tid: usize) -> isize {
+    syscall(SYSCALL_WAITTID, [tid, 0, 0])
+}
+
+/// 功能: 在当前进程中创建一个互斥锁。fork 出的子进程不继承互斥锁、信号量和条件变量。
+/// 返回值: 互斥锁的 ID 。
+/// syscall ID: 1010
+pub fn sys_mutex_create() -> isize {
+    syscall(SYSCALL_MUTEX_CREATE, [0; 3])
+}
+
+/// 功能: 互斥锁加锁，锁已被占用时阻塞。
+/// 参数: `mutex_id` 互斥锁的 ID 。
+/// 返回值: 0 成功；ID 不合法返回 -EINVAL，阻塞时收到信号返回 -EINTR，
+///        打开了死锁检测并且等待会导致死锁时返回 -EDEADLK 。
+/// syscall ID: 1011
+pub fn sys_mutex_lock(mutex_id: usize) -> isize {
+    syscall(SYSCALL_MUTEX_LOCK, [mutex_id, 0, 0])
+}
+
+/// 功能: 互斥锁解锁，唤醒一个等待的线程。
+/// 参数: `mutex_id` 互斥锁的 ID 。
+/// 返回值: 0 成功；ID 不合法返回 -EINVAL，锁没有被锁上返回 -EPERM 。
+/// syscall ID: 1012
+pub fn sys_mutex_unlock(mutex_id: usize) -> isize {
+    syscall(SYSCALL_MUTEX_UNLOCK, [mutex_id, 0, 0])
+}
+
+/// 功能: 在当前进程中创建一个信号量。
+/// 参数: `res_count` 初始资源数。
+/// 返回值: 信号量的 ID 。
+/// syscall ID: 1020
+pub fn sys_semaphore_create(res_count: usize) -> isize {
+    syscall(SYSCALL_SEMAPHORE_CREATE, [res_count, 0, 0])
+}
+
+/// 功能: 信号量的 V 操作，释放一个资源。
+/// 参数: `sem_id` 信号量的 ID 。
+/// 返回值: 0 成功；ID 不合法返回 -EINVAL 。
+/// syscall ID: 1021
+pub fn sys_semaphore_up(sem_id: usize) -> isize {
+    syscall(SYSCALL_SEMAPHORE_UP, [sem_id, 0, 0])
+}
+
+/// 功能: 信号量的 P 操作，获取一个资源，没有资源时阻塞。
+/// 参数: `sem_id` 信号量的 ID 。
+/// 返回值: 0 成功；ID 不合法返回 -EINVAL，阻塞时收到信号返回 -EINTR，
+///        打开了死锁检测并且等待会导致死锁时返回 -EDEADLK 。
+/// syscall ID: 1022
+pub fn sys_semaphore_down(sem_id: usize) -> isize {
+    syscall(SYSCALL_SEMAPHORE_DOWN, [sem_id, 0, 0])
+}
+
+/// 功能: 在当前进程中创建一个条件变量。
+/// 返回值: 条件变量的 ID 。
+/// syscall ID: 1030
+pub fn sys_condvar_create() -> isize {
+    syscall(SYSCALL_CONDVAR_CREATE, [0; 3])
+}
+
+/// 功能: 唤醒一个在条件变量上等待的线程。
+/// 参数: `condvar_id` 条件变量的 ID 。
+/// 返回值: 0 成功；ID 不合法返回 -EINVAL 。
+/// syscall ID: 1031
+pub fn sys_condvar_signal(condvar_id: usize) -> isize {
+    syscall(SYSCALL_CONDVAR_SIGNAL, [condvar_id, 0, 0])
+}
+
+/// 功能: 释放互斥锁并在条件变量上等待，返回前重新获得互斥锁。可能被虚假唤醒。
+/// 参数: `condvar_id` 条件变量的 ID；`mutex_id` 已经锁上的互斥锁的 ID 。
+/// 返回值: 0 成功；ID 不合法返回 -EINVAL，互斥锁没有被锁上返回 -EPERM 。
+/// syscall ID: 1032
+pub fn sys_condvar_wait(condvar_id: usize, mutex_id: usize) -> isize {
+    syscall(SYSCALL_CONDVAR_WAIT, [condvar_id, mutex_id, 0])
+}
+
+/// 功能: 打开或关闭当前进程的死锁检测。打开后，互斥锁加锁和信号量 P 操作
+///      会先用银行家算法检查等待是否安全，不安全时不等待而是返回 -EDEADLK 。
+/// 参数: `enabled` 为 1 表示打开，为 0 表示关闭。
+/// 返回值: 0 成功；参数不合法返回 -EINVAL 。
+/// syscall ID: 469
+pub fn sys_enable_deadlock_detect(enabled: usize) -> isize {
+    syscall(SYSCALL_ENABLE_DEADLOCK_DETECT, [enabled, 0, 0])
+}
+
+/// 功能: futex 操作，用户态锁在发生竞争时通过它阻塞和唤醒。
+/// 参数: `uaddr` futex 的地址，必须 4 字节对齐；
+///      `op` 为 FUTEX_WAIT(0) 或 FUTEX_WAKE(1)；
+///      `val` FUTEX_WAIT 时为期望的值，FUTEX_WAKE 时为最多唤醒的数量；
+///      `timeout_ms` FUTEX_WAIT 的超时时间（毫秒），为 0 表示不超时。
+/// 返回值: FUTEX_WAIT 被唤醒返回 0，`*uaddr != val` 返回 -EAGAIN，超时返回 -ETIMEDOUT，
+///        收到信号返回 -EINTR；FUTEX_WAKE 返回唤醒的数量。
+///        地址不合法返回 -EFAULT，参数不合法返回 -EINVAL 。
+/// syscall ID: 98
+pub fn sys_futex(uaddr: *const u32, op: usize, val: u32, timeout_ms: usize) -> isize {
+    syscall6(
+        SYSCALL_FUTEX,
+        [uaddr as usize, op, val as usize, timeout_ms, 0, 0],
+    )
+}
+
+/// 功能: 设置 nice 值，只有完全公平调度器使用 nice 值。
+/// 参数: `which` 只支持 PRIO_PROCESS(0)；`who` 为 0 表示当前线程，否则表示该进程的所有线程；
+///      `nice` 新的 nice 值，超出 -20~19 的部分被截断，越小得到的处理器时间越多。
+/// 返回值: 0 成功；`which` 不合法返回 -EINVAL，进程不存在返回 -ESRCH 。
+/// syscall ID: 1040
+pub fn sys_setpriority(which: usize, who: usize, nice: isize) -> isize {
+    syscall(SYSCALL_SETPRIORITY, [which, who, nice as usize])
+}